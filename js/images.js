@@ -112,6 +112,8 @@ function getImageFileInt(type){
     case 'Iron Plate':
         return ironPlate;
     case 'Steel Plate':
+    case 'Solar Panel':
+    case 'Plastic':
         return steelPlate;
     case 'Copper Ore':
         return copperOre;
@@ -120,50 +122,98 @@ function getImageFileInt(type){
     case 'Coal Ore':
         return coalOre;
     case 'Stone Ore':
+    case 'Sulfur':
+    case 'Concrete':
         return stoneOre;
     case 'Gear':
         return gear;
     case 'Science Pack 1':
         return sciencePack1;
     case 'Science Pack 2':
+    case 'Military Science Pack':
+    case 'Chemical Science Pack':
+    case 'Production Science Pack':
         return sciencePack2;
     case 'Copper Wire':
+    case 'Red Wire':
+    case 'Green Wire':
         return copperWire;
     case 'Circuit':
+    case 'Constant Combinator':
+    case 'Arithmetic Combinator':
+    case 'Decider Combinator':
+    case 'Lamp':
+    case 'Speed Module':
+    case 'Efficiency Module':
+    case 'Productivity Module':
         return circuit;
     case 'Transport Belt':
+    case 'Loader':
         return transport;
     case 'Underground Belt':
+    case 'Fast Underground Belt':
         return undergroundBeltItem;
     case 'Splitter':
         return splitter;
     case 'Inserter':
+    case 'Electric Inserter':
+    case 'Logistic Robot':
         return [inserter, 2];
     case 'Chest':
+    case 'Iron Chest':
+    case 'Steel Chest':
+    case 'Passive Provider Chest':
+    case 'Active Provider Chest':
+    case 'Storage Chest':
+    case 'Requester Chest':
+    case 'Roboport':
+    case 'Train Stop':
+    case 'Cargo Wagon':
         return chest;
     case 'Ore Mine':
+    case 'Pumpjack':
+    case 'Electric Mining Drill':
         return [mine, 3];
     case 'Furnace':
         return [furnace, 3];
     case 'Electric Furnace':
         return [electricFurnace, 3];
     case 'Assembler':
+    case 'Oil Refinery':
+    case 'Chemical Plant':
         return [assembler, 4];
     case 'Lab':
         return [lab, 4];
     case 'Water Well':
+    case 'Storage Tank':
+    case 'Accumulator':
         return waterWell;
     case 'Offshore Pump':
+    case 'Pump':
         return offshorePump;
     case 'Boiler':
         return [boiler, 3];
     case 'Pipe':
+    case 'Water':
+    case 'Steam':
+    case 'Crude Oil':
+    case 'Heavy Oil':
+    case 'Light Oil':
+    case 'Petroleum Gas':
+    case 'Rail':
+    case 'Curved Rail':
+    case 'Rail Signal':
         return pipeItem;
     case 'Underground Pipe':
         return undergroundPipeItem;
     case 'Steam Engine':
+    case 'Locomotive':
         return [steamEngine, 3];
     case 'Electric Pole':
+    case 'Medium Electric Pole':
+    case 'Big Electric Pole':
+    case 'Substation':
+    case 'Beacon':
         return electPole;
     default:
         return "";
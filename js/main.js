@@ -2,6 +2,7 @@ import rotateImage from "../img/rotate.png";
 import inventory from "../img/inventory.png";
 import sciencePack1 from "../img/science-pack-1.png";
 import menuIcon from "../img/menuIcon.png";
+import blueprintImage from "../img/list.png";
 
 import { loadImages, getImageFile } from "./images.js";
 import { FactorishState } from "../pkg";
@@ -11,6 +12,7 @@ import { createApp, nextTick } from "vue";
 import MainMenuWindow from "./components/MainMenuWindow.vue";
 import NewGameWindow from "./components/NewGameWindow.vue";
 import ViewSettingsWindow from "./components/ViewSettingsWindow.vue";
+import BlueprintWindow from "./components/BlueprintWindow.vue";
 import InventoryWindow from "./components/InventoryWindow.vue";
 import RecipeSelectorWindow from "./components/RecipeSelectorWindow.vue";
 import ResearchSelectorWindow from "./components/ResearchSelectorWindow.vue";
@@ -39,6 +41,7 @@ let unlimited = true;
         noiseScale: 5.,
         noiseThreshold: 0.30,
         noiseOctaves: 3,
+        characterMode: false,
     };
 
     function initPane(buttonId, containerId){
@@ -401,7 +404,7 @@ let unlimited = true;
     researchButton.style.height = '31px';
     researchButton.style.position = 'absolute';
     researchButton.style.top = '4px';
-    researchButton.style.left = (32.0 * i + 4) + 'px';
+    researchButton.style.left = (32.0 * i++ + 4) + 'px';
     researchButton.style.border = '1px blue solid';
     researchButton.style.backgroundImage = `url(${sciencePack1})`;
     researchButton.onmousedown = (evt) => showReserachSelect(evt);
@@ -409,6 +412,19 @@ let unlimited = true;
     researchButton.onmouseleave = () => vueToolTipApp.visible = false;
     toolBarElem.appendChild(researchButton);
 
+    const blueprintButton = document.createElement('div');
+    blueprintButton.style.width = '31px';
+    blueprintButton.style.height = '31px';
+    blueprintButton.style.position = 'absolute';
+    blueprintButton.style.top = '4px';
+    blueprintButton.style.left = (32.0 * i + 4) + 'px';
+    blueprintButton.style.border = '1px blue solid';
+    blueprintButton.style.backgroundImage = `url(${blueprintImage})`;
+    blueprintButton.onmousedown = () => showBlueprintWindow();
+    blueprintButton.onmouseenter = (e) => setToolTip(e.target, "<b>Blueprint</b><br><i>Shortcut: (B)</i>");
+    blueprintButton.onmouseleave = () => vueToolTipApp.visible = false;
+    toolBarElem.appendChild(blueprintButton);
+
     function updateToolBarImage(){
         for(var i = 0; i < toolBarCanvases.length; i++){
             var canvasElem = toolBarCanvases[i];
@@ -468,6 +484,40 @@ let unlimited = true;
         }
     }
 
+    /// Queue a hand craft of the recipe; right click queues 5 at once.
+    function craftClickHandler(recipes, i, evt, rightClick){
+        if(i < recipes.length){
+            try{
+                if(!sim.queue_craft(recipes[i].index, rightClick ? 5 : 1)){
+                    const r = evt.target.getBoundingClientRect();
+                    const cr = container.getBoundingClientRect();
+                    popupText("Not enough materials", r.left - cr.left, r.top - cr.top);
+                }
+            }
+            catch(e){
+                console.error(e);
+            }
+            vueApp.craftQueue = sim.get_crafting_queue();
+        }
+        evt.preventDefault();
+    }
+
+    function cancelCraftHandler(i, evt){
+        if(sim.cancel_craft(i)){
+            vueApp.craftQueue = sim.get_crafting_queue();
+            updateInventory(sim.get_player_inventory());
+        }
+        evt.preventDefault();
+    }
+
+    function cancelConstructionHandler(i, evt){
+        if(sim.cancel_construction(i)){
+            vueApp.constructionQueue = sim.get_construction_queue();
+            updateInventory(sim.get_player_inventory());
+        }
+        evt.preventDefault();
+    }
+
     function structureDestroyed(isSelectedStructure){
         if(isSelectedStructure){
             vueApp.inventoryVisible = false;
@@ -485,6 +535,7 @@ let unlimited = true;
         updateVueInputInventory(sim.get_structure_inventory(...position, "Input"));
         updateVueOutputInventory(sim.get_structure_inventory(...position, "Output"));
         updateVueStorageInventory(sim.get_structure_inventory(...position, "Storage"));
+        updateVueModuleInventory(sim.get_structure_inventory(...position, "Module"));
     }
 
     function setItemImageToElem(img, i, iconSize){
@@ -685,6 +736,38 @@ let unlimited = true;
 
     windowOrder.push(vueViewSettingsWindow);
 
+    const vueBlueprintWindow = createApp(
+        BlueprintWindow,
+        {
+            onSelectArea() { sim.start_blueprint_selection(); },
+            onImport(blueprintString) {
+                try{
+                    sim.import_blueprint(blueprintString);
+                }
+                catch(e){
+                    return `Import failed: ${e}`;
+                }
+            },
+            onClear() { sim.clear_area_tools(); },
+            onDeconstructArea(filter) { sim.start_deconstruction_selection(filter || undefined); },
+            onCancelDeconstructArea() { sim.start_cancel_deconstruction_selection(); },
+            onClearDeconstruction() { sim.clear_deconstruction_marks(); },
+            getStructureItems: () => sim.tool_defs().map(([name]) => name),
+            dragWindowMouseDown,
+            bringToTop: () => bringToTop(vueBlueprintWindow),
+        }
+    ).mount('#vueBlueprintWindow');
+
+    windowOrder.push(vueBlueprintWindow);
+
+    function showBlueprintWindow(){
+        vueBlueprintWindow.visible = !vueBlueprintWindow.visible;
+        if(vueBlueprintWindow.visible){
+            vueBlueprintWindow.placeCenter();
+            bringToTop(vueBlueprintWindow);
+        }
+    }
+
     const vueApplication = createApp(
         InventoryWindow,
         {
@@ -698,6 +781,12 @@ let unlimited = true;
             showRecipeSelect,
             recipeSelectMouseEnterHandler: evt => setToolTip(evt.target, "Select a recipe", "recipe"),
             recipeSelectMouseLeaveHandler: () => vueToolTipApp.visible = false,
+            setCircuitConfig,
+            craftClickHandler,
+            cancelCraftHandler,
+            craftMouseEnterHandler: (recipes, i, evt) => recipeMouseEnterHandler(recipes, i, evt),
+            craftMouseLeaveHandler: () => vueToolTipApp.visible = false,
+            cancelConstructionHandler,
             bringToTop: () => bringToTop(vueApp),
         }
     );
@@ -742,6 +831,15 @@ let unlimited = true;
         }) : [];
     }
 
+    function updateVueModuleInventory(inventory){
+        vueApp.moduleItems.value = inventory.length !== 0 ? inventory[0].map(item => {
+            return {
+                name: item[0],
+                count: item[1],
+            };
+        }) : [];
+    }
+
     function updateVuePlayerInventory(inventory){
         vueApp.playerItems.value = inventory.length !== 0 ? inventory[0].map(item => {
             return {
@@ -792,9 +890,16 @@ let unlimited = true;
 
     windowOrder.push(vueRecipeSelector);
 
-    function researchClickHandler(_technologies, i, evt){
+    function researchClickHandler(_technologies, i, evt, queue){
         console.log(`researchClickHandler: evt.ctrlKey: ${evt.ctrlKey}`);
-        if(sim.select_research(i)){
+        if(queue || evt.shiftKey ? sim.queue_research(i) : sim.select_research(i)){
+            updateResearch();
+        }
+        evt.preventDefault();
+    };
+
+    function researchQueueClickHandler(i, evt){
+        if(sim.dequeue_research(i)){
             updateResearch();
         }
         evt.preventDefault();
@@ -820,6 +925,7 @@ let unlimited = true;
         {
             dragWindowMouseDown,
             researchClickHandler,
+            researchQueueClickHandler,
             researchMouseEnterHandler,
             researchMouseLeaveHandler: () => vueToolTipApp.visible = false,
             bringToTop: () => bringToTop(vueResearchSelector),
@@ -950,12 +1056,23 @@ let unlimited = true;
             else{
                 vueApp.hasStorage = false;
             }
+            vueApp.moduleSlots = sim.get_structure_module_slots(pos[0], pos[1]);
+            if(0 < vueApp.moduleSlots){
+                updateVueModuleInventory(sim.get_structure_inventory(pos[0], pos[1], "Module"));
+            }
             showBurnerStatus(pos);
 
+            vueApp.circuitConfig = sim.get_structure_circuit_config(pos[0], pos[1]);
+            if(vueApp.circuitConfig && vueApp.signalNames.length === 0){
+                vueApp.signalNames = sim.get_signal_names();
+            }
         }
         else{
             vueApp.hasPosition = false;
         }
+        vueApp.craftRecipes = sim.get_hand_craft_recipes();
+        vueApp.craftQueue = sim.get_crafting_queue();
+        vueApp.constructionQueue = sim.get_construction_queue();
         bringToTop(vueApp);
         vueApp.placeCenter();
     }
@@ -973,6 +1090,19 @@ let unlimited = true;
         return txt;
     }
 
+    function setCircuitConfig(config){
+        const pos = sim.get_selected_inventory();
+        if(!pos)
+            return;
+        try{
+            sim.set_structure_circuit_config(pos[0], pos[1], config);
+        }
+        catch(e){
+            console.error(e);
+        }
+        vueApp.circuitConfig = sim.get_structure_circuit_config(pos[0], pos[1]);
+    }
+
     function showRecipeSelect(evt){
         evt.stopPropagation();
         if(vueRecipeSelector.visible){
@@ -1008,6 +1138,7 @@ let unlimited = true;
         vueResearchSelector.placeCenter();
         bringToTop(vueResearchSelector);
         vueResearchSelector.research = sim.get_research();
+        vueResearchSelector.researchQueue = sim.get_research_queue();
         const tech = sim.get_technologies();
         vueResearchSelector.technologies = tech;
     }
@@ -1037,7 +1168,8 @@ let unlimited = true;
     let dragging = null;
     canvas.addEventListener("mousedown", function(evt){
         processEvents(sim.mouse_down([evt.offsetX, evt.offsetY], evt.button));
-        if(evt.button === 0)
+        // Dragging with an area tool selects an area instead of scrolling
+        if(evt.button === 0 && !sim.is_area_tool_active())
             dragging = [evt.offsetX, evt.offsetY, false];
         evt.stopPropagation();
         evt.preventDefault();
@@ -1090,6 +1222,10 @@ let unlimited = true;
             // 't'
             showReserachSelect(event);
             return;
+        case 66:
+            // 'b'
+            showBlueprintWindow();
+            return;
         }
         const result = sim.on_key_down(event.keyCode);
         if(result){
@@ -1105,6 +1241,18 @@ let unlimited = true;
     }
     window.addEventListener( 'keydown', onKeyDown, false );
 
+    function onKeyUp(event){
+        if(sim.on_key_up(event.keyCode))
+            event.preventDefault();
+    }
+    window.addEventListener( 'keyup', onKeyUp, false );
+
+    // Stop the character when the window loses focus, since key up events would not arrive
+    window.addEventListener( 'blur', () => {
+        for(const keyCode of [65, 68, 70, 83, 87])
+            sim.on_key_up(keyCode);
+    }, false );
+
     try{
         sim.load_game();
         // If succeeded to load a game, update the research panel which is rarely updated.
@@ -1138,10 +1286,12 @@ let unlimited = true;
         const research = sim.get_research();
         if(vueResearchSelector.visible) {
             vueResearchSelector.research = research;
+            vueResearchSelector.researchQueue = sim.get_research_queue();
             vueResearchSelector.technologies = sim.get_technologies();
         }
         if(research){
-            researchTitleElem.innerHTML = `${research.technology} (${(research.progress * 100).toFixed(0)}%)`;
+            const level = research.level ? ` ${research.level}` : "";
+            researchTitleElem.innerHTML = `${research.technology}${level} (${(research.progress * 100).toFixed(0)}%)`;
             researchProgressElem.style.width = `${research.progress * 100}%`;
         }
         else{
@@ -1162,6 +1312,8 @@ let unlimited = true;
                 console.log("UpdatePlayerInventory event received");
                 updateInventory(sim.get_player_inventory());
                 updateToolBar();
+                if(vueApp.inventoryVisible)
+                    vueApp.craftRecipes = sim.get_hand_craft_recipes();
             }
             else if(event.ShowInventoryAt && event.ShowInventoryAt instanceof Object){
                 showInventory(event.ShowInventoryAt);
@@ -1169,6 +1321,16 @@ let unlimited = true;
             else if(event === "UpdateResearch") {
                 updateResearch();
             }
+            else if(event === "UpdateBlueprint") {
+                const blueprintString = sim.get_blueprint_string();
+                if(blueprintString){
+                    vueBlueprintWindow.blueprintString = blueprintString;
+                    vueBlueprintWindow.message = "Click on the map to place the blueprint";
+                }
+                else{
+                    vueBlueprintWindow.message = "No structures in the area";
+                }
+            }
         }
     }
 
@@ -1206,6 +1368,7 @@ let unlimited = true;
         } catch(e) {
             alert(`FactorishState.render_init failed: ${e}`);
         }
+        sim.set_character_mode(params.characterMode);
         updateInventory(sim.get_player_inventory());
     }
 
@@ -1237,6 +1400,10 @@ let unlimited = true;
             showBurnerStatus(selPos);
             updateStructureProgress(selPos);
         }
+        if(vueApp.inventoryVisible){
+            vueApp.craftQueue = sim.get_crafting_queue();
+            vueApp.constructionQueue = sim.get_construction_queue();
+        }
 
         const minimapData = sim.render_minimap(miniMapSize, miniMapSize);
         const viewportScale = sim.get_viewport_scale();
@@ -0,0 +1,146 @@
+use super::{
+    gl::draw_flat_rect_gl,
+    power_network::PowerPriority,
+    structure::{Size, Structure},
+    FactorishState, Position, TILE_SIZE,
+};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+use web_sys::{CanvasRenderingContext2d, WebGlRenderingContext as GL};
+
+/// Energy in kilojoules an accumulator can store
+const ACCUMULATOR_CAPACITY: f64 = 300.;
+/// Maximum energy in kilojoules charged or discharged in a tick
+const ACCUMULATOR_RATE: f64 = 0.5;
+
+/// An accumulator stores the surplus energy of the generators in the network and
+/// discharges it when the generators cannot meet the demand.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct Accumulator {
+    position: Position,
+    energy: f64,
+}
+
+impl Accumulator {
+    pub(crate) fn new(position: &Position) -> Self {
+        Self {
+            position: *position,
+            energy: 0.,
+        }
+    }
+}
+
+impl Structure for Accumulator {
+    fn name(&self) -> &str {
+        "Accumulator"
+    }
+
+    fn position(&self) -> &Position {
+        &self.position
+    }
+
+    fn size(&self) -> Size {
+        Size::new(2, 2)
+    }
+
+    fn draw(
+        &self,
+        _state: &FactorishState,
+        context: &CanvasRenderingContext2d,
+        depth: i32,
+        is_toolbar: bool,
+    ) -> Result<(), JsValue> {
+        if depth != 0 {
+            return Ok(());
+        }
+        let (x, y) = (
+            self.position.x as f64 * TILE_SIZE,
+            self.position.y as f64 * TILE_SIZE,
+        );
+        let size = if is_toolbar {
+            TILE_SIZE
+        } else {
+            TILE_SIZE * 2.
+        };
+        context.set_fill_style(&js_str!("#5f5f5f"));
+        context.fill_rect(x, y, size, size);
+        context.set_fill_style(&js_str!("#3fbf3f"));
+        let charge = self.energy / ACCUMULATOR_CAPACITY;
+        context.fill_rect(
+            x + size * 0.3,
+            y + size * (0.1 + 0.8 * (1. - charge)),
+            size * 0.4,
+            size * 0.8 * charge,
+        );
+        Ok(())
+    }
+
+    fn draw_gl(
+        &self,
+        state: &FactorishState,
+        gl: &GL,
+        depth: i32,
+        is_ghost: bool,
+    ) -> Result<(), JsValue> {
+        if depth != 0 {
+            return Ok(());
+        }
+        let (x, y) = (
+            self.position.x as f32 + state.viewport.x as f32,
+            self.position.y as f32 + state.viewport.y as f32,
+        );
+        let alpha = if is_ghost { 0.5 } else { 1. };
+        draw_flat_rect_gl(state, gl, (x, y, 2., 2.), &[0.37, 0.37, 0.37, alpha])?;
+        draw_flat_rect_gl(
+            state,
+            gl,
+            (x + 0.6, y + 0.2, 0.8, 1.6),
+            &[0.1, 0.1, 0.1, alpha],
+        )?;
+        let charge = (self.energy / ACCUMULATOR_CAPACITY) as f32;
+        draw_flat_rect_gl(
+            state,
+            gl,
+            (x + 0.6, y + 0.2 + 1.6 * (1. - charge), 0.8, 1.6 * charge),
+            &[0.25, 0.75, 0.25, alpha],
+        )?;
+        Ok(())
+    }
+
+    fn desc(&self, _state: &FactorishState) -> String {
+        format!(
+            "Charge: {:.1}kJ / {:.0}kJ ({:.0}%)",
+            self.energy,
+            ACCUMULATOR_CAPACITY,
+            self.energy / ACCUMULATOR_CAPACITY * 100.
+        )
+    }
+
+    fn power_source(&self) -> bool {
+        true
+    }
+
+    fn power_priority(&self) -> PowerPriority {
+        PowerPriority::Storage
+    }
+
+    fn power_outlet(&mut self, demand: f64) -> Option<f64> {
+        let energy = demand.min(self.energy).min(ACCUMULATOR_RATE);
+        self.energy -= energy;
+        Some(energy)
+    }
+
+    fn power_charge_demand(&self) -> Option<f64> {
+        Some((ACCUMULATOR_CAPACITY - self.energy).min(ACCUMULATOR_RATE))
+    }
+
+    fn power_charge(&mut self, energy: f64) {
+        self.energy = (self.energy + energy).min(ACCUMULATOR_CAPACITY);
+    }
+
+    fn reset_for_blueprint(&mut self) {
+        self.energy = 0.;
+    }
+
+    crate::serialize_impl!();
+}
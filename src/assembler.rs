@@ -3,18 +3,24 @@ use crate::{items::render_item_overlay_gl, structure::Size};
 use super::{
     drop_items::DropItem,
     gl::{
-        draw_electricity_alarm_gl,
+        draw_direction_arrow_gl, draw_electricity_alarm_gl,
         utils::{enable_buffer, Flatten},
         ShaderBundle,
     },
     inventory::{filter_inventory, Inventory, InventoryTrait, InventoryType},
     items::get_item_image_url,
+    modules::{
+        get_module_power_cost, get_module_progress, module_effect, proceed_productivity,
+        ModuleEffect,
+    },
     research::TechnologyTag,
     serialize_impl,
     structure::{
-        default_add_inventory, get_powered_progress, Structure, StructureDynIter, StructureId,
+        default_add_inventory, default_reset_for_blueprint, Structure, StructureDynIter,
+        StructureId,
     },
-    FactorishState, FrameProcResult, ItemType, Position, Recipe, TILE_SIZE,
+    water_well::{FluidBox, FluidType},
+    FactorishState, FrameProcResult, ItemType, Position, Recipe, Rotation, TILE_SIZE,
 };
 use cgmath::{Matrix3, Matrix4, Vector2, Vector3};
 use serde::{Deserialize, Serialize};
@@ -32,11 +38,39 @@ fn generate_item_image(item_image: &str, icon_size: bool, count: usize) -> Strin
     })
 }
 
+fn default_rotation() -> Rotation {
+    Rotation::Left
+}
+
+fn default_input_fluid_box() -> FluidBox {
+    FluidBox::new(false, false)
+}
+
+fn default_output_fluid_box() -> FluidBox {
+    FluidBox::new(false, true)
+}
+
+/// An assembler crafts the selected recipe with electricity.
+/// If the recipe has fluids, the input fluid comes from the back side and the output fluid
+/// goes out of the front side, in the direction of `rotation`.
 #[derive(Serialize, Deserialize)]
 pub(crate) struct Assembler {
     position: Position,
+    #[serde(default = "default_rotation")]
+    rotation: Rotation,
     input_inventory: Inventory,
     output_inventory: Inventory,
+    #[serde(default = "default_input_fluid_box")]
+    input_fluid_box: FluidBox,
+    #[serde(default = "default_output_fluid_box")]
+    output_fluid_box: FluidBox,
+    #[serde(default)]
+    module_inventory: Inventory,
+    /// Accumulated productivity bonus. When it reaches 1, an extra set of outputs is produced.
+    #[serde(default)]
+    bonus_progress: f64,
+    #[serde(skip)]
+    module_effect: ModuleEffect,
     progress: Option<f64>,
     power: f64,
     max_power: f64,
@@ -44,11 +78,17 @@ pub(crate) struct Assembler {
 }
 
 impl Assembler {
-    pub(crate) fn new(position: &Position) -> Self {
+    pub(crate) fn new(position: &Position, rotation: Rotation) -> Self {
         Assembler {
             position: *position,
+            rotation,
             input_inventory: Inventory::new(),
             output_inventory: Inventory::new(),
+            input_fluid_box: default_input_fluid_box(),
+            output_fluid_box: default_output_fluid_box(),
+            module_inventory: Inventory::new(),
+            bonus_progress: 0.,
+            module_effect: ModuleEffect::default(),
             progress: None,
             power: 0.,
             max_power: 20.,
@@ -56,6 +96,13 @@ impl Assembler {
         }
     }
 
+    fn uses_fluid(&self) -> bool {
+        self.recipe
+            .as_ref()
+            .map(|recipe| recipe.input_fluid.is_some() || recipe.output_fluid.is_some())
+            .unwrap_or(false)
+    }
+
     pub(crate) fn get_recipes() -> &'static [Recipe] {
         static RECIPES: once_cell::sync::Lazy<Vec<Recipe>> = once_cell::sync::Lazy::new(|| {
             vec![
@@ -79,6 +126,13 @@ impl Assembler {
                     50.,
                     hash_set!(TechnologyTag::Transportation),
                 ),
+                Recipe::new_with_requires(
+                    hash_map!(ItemType::UndergroundBelt => 2, ItemType::Gear => 10),
+                    hash_map!(ItemType::FastUndergroundBelt => 2usize),
+                    20.,
+                    100.,
+                    hash_set!(TechnologyTag::Transportation),
+                ),
                 Recipe::new_with_requires(
                     hash_map!(ItemType::TransportBelt => 2, ItemType::Gear => 2),
                     hash_map!(ItemType::Splitter => 1),
@@ -135,12 +189,39 @@ impl Assembler {
                     20.,
                     20.,
                 ),
+                Recipe::new_with_requires(
+                    hash_map!(ItemType::Inserter => 1, ItemType::Circuit => 1, ItemType::CopperWire => 2),
+                    hash_map!(ItemType::ElectricInserter => 1),
+                    20.,
+                    20.,
+                    hash_set!(TechnologyTag::Electricity),
+                ),
                 Recipe::new(
                     hash_map!(ItemType::IronPlate => 1, ItemType::Gear => 5, ItemType::Circuit => 3),
                     hash_map!(ItemType::OreMine => 1),
                     100.,
                     100.,
                 ),
+                Recipe::new_with_requires(
+                    hash_map!(ItemType::Gear => 5, ItemType::Circuit => 3, ItemType::IronPlate => 10),
+                    hash_map!(ItemType::ElectricMiningDrill => 1),
+                    100.,
+                    100.,
+                    hash_set!(TechnologyTag::Electricity),
+                ),
+                Recipe::new(
+                    hash_map!(ItemType::IronPlate => 8),
+                    hash_map!(ItemType::IronChest => 1),
+                    20.,
+                    20.,
+                ),
+                Recipe::new_with_requires(
+                    hash_map!(ItemType::SteelPlate => 8),
+                    hash_map!(ItemType::SteelChest => 1),
+                    20.,
+                    20.,
+                    hash_set!(TechnologyTag::SteelWorks),
+                ),
                 Recipe::new(
                     hash_map!(ItemType::IronPlate => 2),
                     hash_map!(ItemType::Pipe => 1),
@@ -184,6 +265,245 @@ impl Assembler {
                     50.,
                     50.,
                 ),
+                Recipe::new_with_requires(
+                    hash_map!(ItemType::TransportBelt => 5, ItemType::Inserter => 5, ItemType::IronPlate => 5),
+                    hash_map!(ItemType::Loader => 1),
+                    50.,
+                    50.,
+                    hash_set!(TechnologyTag::Transportation),
+                ),
+                Recipe::new_with_requires(
+                    hash_map!(ItemType::SteelPlate => 10, ItemType::Gear => 5, ItemType::Circuit => 10),
+                    hash_map!(ItemType::Roboport => 1),
+                    200.,
+                    200.,
+                    hash_set!(TechnologyTag::LogisticRobotics),
+                ),
+                Recipe::new_with_requires(
+                    hash_map!(ItemType::IronPlate => 2, ItemType::Gear => 2, ItemType::Circuit => 3),
+                    hash_map!(ItemType::LogisticRobot => 1),
+                    50.,
+                    50.,
+                    hash_set!(TechnologyTag::LogisticRobotics),
+                ),
+                Recipe::new_with_requires(
+                    hash_map!(ItemType::SteelChest => 1, ItemType::Circuit => 3),
+                    hash_map!(ItemType::PassiveProviderChest => 1),
+                    20.,
+                    20.,
+                    hash_set!(TechnologyTag::LogisticRobotics),
+                ),
+                Recipe::new_with_requires(
+                    hash_map!(ItemType::SteelChest => 1, ItemType::Circuit => 3),
+                    hash_map!(ItemType::ActiveProviderChest => 1),
+                    20.,
+                    20.,
+                    hash_set!(TechnologyTag::LogisticRobotics),
+                ),
+                Recipe::new_with_requires(
+                    hash_map!(ItemType::SteelChest => 1, ItemType::Circuit => 3),
+                    hash_map!(ItemType::StorageChest => 1),
+                    20.,
+                    20.,
+                    hash_set!(TechnologyTag::LogisticRobotics),
+                ),
+                Recipe::new_with_requires(
+                    hash_map!(ItemType::SteelChest => 1, ItemType::Circuit => 3),
+                    hash_map!(ItemType::RequesterChest => 1),
+                    20.,
+                    20.,
+                    hash_set!(TechnologyTag::LogisticRobotics),
+                ),
+                Recipe::new_with_requires(
+                    hash_map!(ItemType::SteelPlate => 1, ItemType::IronPlate => 1),
+                    hash_map!(ItemType::Rail => 2),
+                    10.,
+                    10.,
+                    hash_set!(TechnologyTag::Railway),
+                ),
+                Recipe::new_with_requires(
+                    hash_map!(ItemType::SteelPlate => 1, ItemType::IronPlate => 1),
+                    hash_map!(ItemType::CurvedRail => 2),
+                    10.,
+                    10.,
+                    hash_set!(TechnologyTag::Railway),
+                ),
+                Recipe::new_with_requires(
+                    hash_map!(ItemType::Rail => 1, ItemType::Circuit => 1),
+                    hash_map!(ItemType::RailSignal => 1),
+                    20.,
+                    20.,
+                    hash_set!(TechnologyTag::Railway),
+                ),
+                Recipe::new_with_requires(
+                    hash_map!(ItemType::Rail => 1, ItemType::Circuit => 5, ItemType::SteelPlate => 3),
+                    hash_map!(ItemType::TrainStop => 1),
+                    50.,
+                    50.,
+                    hash_set!(TechnologyTag::Railway),
+                ),
+                Recipe::new_with_requires(
+                    hash_map!(ItemType::SteelPlate => 20, ItemType::Gear => 10, ItemType::Circuit => 10),
+                    hash_map!(ItemType::Locomotive => 1),
+                    200.,
+                    200.,
+                    hash_set!(TechnologyTag::Railway),
+                ),
+                Recipe::new_with_requires(
+                    hash_map!(ItemType::SteelPlate => 10, ItemType::Gear => 10, ItemType::IronPlate => 20),
+                    hash_map!(ItemType::CargoWagon => 1),
+                    100.,
+                    100.,
+                    hash_set!(TechnologyTag::Railway),
+                ),
+                Recipe::new_with_requires(
+                    hash_map!(ItemType::IronPlate => 20, ItemType::SteelPlate => 5),
+                    hash_map!(ItemType::StorageTank => 1),
+                    50.,
+                    50.,
+                    hash_set!(TechnologyTag::FluidHandling),
+                ),
+                Recipe::new_with_requires(
+                    hash_map!(ItemType::Gear => 1, ItemType::SteelPlate => 1, ItemType::Pipe => 1),
+                    hash_map!(ItemType::Pump => 1),
+                    20.,
+                    20.,
+                    hash_set!(TechnologyTag::FluidHandling),
+                ),
+                Recipe::new_with_requires(
+                    hash_map!(ItemType::SteelPlate => 5, ItemType::Gear => 10, ItemType::Pipe => 10),
+                    hash_map!(ItemType::Pumpjack => 1),
+                    50.,
+                    50.,
+                    hash_set!(TechnologyTag::OilProcessing),
+                ),
+                Recipe::new_with_requires(
+                    hash_map!(ItemType::SteelPlate => 15, ItemType::Gear => 10, ItemType::Pipe => 10),
+                    hash_map!(ItemType::OilRefinery => 1),
+                    80.,
+                    80.,
+                    hash_set!(TechnologyTag::OilProcessing),
+                ),
+                Recipe::new_with_requires(
+                    hash_map!(ItemType::SteelPlate => 5, ItemType::Gear => 5, ItemType::Pipe => 5),
+                    hash_map!(ItemType::ChemicalPlant => 1),
+                    50.,
+                    50.,
+                    hash_set!(TechnologyTag::OilProcessing),
+                ),
+                Recipe::new_with_requires(
+                    hash_map!(ItemType::StoneOre => 5, ItemType::IronOre => 1),
+                    hash_map!(ItemType::Concrete => 10),
+                    30.,
+                    100.,
+                    hash_set!(TechnologyTag::FluidHandling),
+                )
+                .with_fluids(Some((FluidType::Water, 100.)), None),
+                Recipe::new_with_requires(
+                    hash_map!(ItemType::Circuit => 5, ItemType::Plastic => 5),
+                    hash_map!(ItemType::SpeedModule => 1),
+                    100.,
+                    150.,
+                    hash_set!(TechnologyTag::Modules),
+                ),
+                Recipe::new_with_requires(
+                    hash_map!(ItemType::Circuit => 5, ItemType::Plastic => 5),
+                    hash_map!(ItemType::EfficiencyModule => 1),
+                    100.,
+                    150.,
+                    hash_set!(TechnologyTag::Modules),
+                ),
+                Recipe::new_with_requires(
+                    hash_map!(ItemType::Circuit => 5, ItemType::Plastic => 5),
+                    hash_map!(ItemType::ProductivityModule => 1),
+                    100.,
+                    150.,
+                    hash_set!(TechnologyTag::Modules),
+                ),
+                Recipe::new_with_requires(
+                    hash_map!(ItemType::Circuit => 20, ItemType::SteelPlate => 10, ItemType::CopperWire => 10),
+                    hash_map!(ItemType::Beacon => 1),
+                    100.,
+                    150.,
+                    hash_set!(TechnologyTag::Modules),
+                ),
+                Recipe::new_with_requires(
+                    hash_map!(ItemType::SteelPlate => 5, ItemType::Circuit => 15, ItemType::CopperPlate => 5),
+                    hash_map!(ItemType::SolarPanel => 1),
+                    100.,
+                    100.,
+                    hash_set!(TechnologyTag::SolarEnergy),
+                ),
+                Recipe::new_with_requires(
+                    hash_map!(ItemType::IronPlate => 10, ItemType::CopperPlate => 10, ItemType::Sulfur => 5),
+                    hash_map!(ItemType::Accumulator => 1),
+                    100.,
+                    100.,
+                    hash_set!(TechnologyTag::SolarEnergy),
+                ),
+                Recipe::new_with_requires(
+                    hash_map!(ItemType::SteelPlate => 2, ItemType::CopperPlate => 2),
+                    hash_map!(ItemType::MediumElectPole => 1),
+                    20.,
+                    50.,
+                    hash_set!(TechnologyTag::ElectricEnergyDistribution),
+                ),
+                Recipe::new_with_requires(
+                    hash_map!(ItemType::SteelPlate => 5, ItemType::CopperPlate => 5),
+                    hash_map!(ItemType::BigElectPole => 1),
+                    20.,
+                    50.,
+                    hash_set!(TechnologyTag::ElectricEnergyDistribution),
+                ),
+                Recipe::new_with_requires(
+                    hash_map!(ItemType::SteelPlate => 10, ItemType::Circuit => 5, ItemType::CopperPlate => 5),
+                    hash_map!(ItemType::Substation => 1),
+                    50.,
+                    100.,
+                    hash_set!(TechnologyTag::ElectricEnergyDistribution),
+                ),
+                Recipe::new_with_requires(
+                    hash_map!(ItemType::Circuit => 1, ItemType::CopperWire => 1),
+                    hash_map!(ItemType::RedWire => 1),
+                    20.,
+                    20.,
+                    hash_set!(TechnologyTag::CircuitNetwork),
+                ),
+                Recipe::new_with_requires(
+                    hash_map!(ItemType::Circuit => 1, ItemType::CopperWire => 1),
+                    hash_map!(ItemType::GreenWire => 1),
+                    20.,
+                    20.,
+                    hash_set!(TechnologyTag::CircuitNetwork),
+                ),
+                Recipe::new_with_requires(
+                    hash_map!(ItemType::Circuit => 2, ItemType::CopperWire => 5),
+                    hash_map!(ItemType::ConstantCombinator => 1),
+                    20.,
+                    50.,
+                    hash_set!(TechnologyTag::CircuitNetwork),
+                ),
+                Recipe::new_with_requires(
+                    hash_map!(ItemType::Circuit => 5, ItemType::CopperWire => 5),
+                    hash_map!(ItemType::ArithmeticCombinator => 1),
+                    20.,
+                    50.,
+                    hash_set!(TechnologyTag::CircuitNetwork),
+                ),
+                Recipe::new_with_requires(
+                    hash_map!(ItemType::Circuit => 5, ItemType::CopperWire => 5),
+                    hash_map!(ItemType::DeciderCombinator => 1),
+                    20.,
+                    50.,
+                    hash_set!(TechnologyTag::CircuitNetwork),
+                ),
+                Recipe::new_with_requires(
+                    hash_map!(ItemType::Circuit => 1, ItemType::CopperWire => 3, ItemType::IronPlate => 1),
+                    hash_map!(ItemType::Lamp => 1),
+                    20.,
+                    20.,
+                    hash_set!(TechnologyTag::Optics),
+                ),
                 Recipe::new_with_requires(
                     hash_map!(ItemType::Inserter => 1, ItemType::TransportBelt => 1),
                     hash_map!(ItemType::SciencePack2 => 1),
@@ -191,6 +511,27 @@ impl Assembler {
                     150.,
                     hash_set!(TechnologyTag::Transportation),
                 ),
+                Recipe::new_with_requires(
+                    hash_map!(ItemType::SteelPlate => 1, ItemType::Gear => 2, ItemType::Inserter => 1),
+                    hash_map!(ItemType::MilitarySciencePack => 2),
+                    150.,
+                    200.,
+                    hash_set!(TechnologyTag::SteelWorks),
+                ),
+                Recipe::new_with_requires(
+                    hash_map!(ItemType::Plastic => 2, ItemType::Circuit => 3, ItemType::SteelPlate => 1),
+                    hash_map!(ItemType::ChemicalSciencePack => 2),
+                    200.,
+                    250.,
+                    hash_set!(TechnologyTag::OilProcessing),
+                ),
+                Recipe::new_with_requires(
+                    hash_map!(ItemType::ElectricFurnace => 1, ItemType::Assembler => 1, ItemType::SpeedModule => 1),
+                    hash_map!(ItemType::ProductionSciencePack => 3),
+                    200.,
+                    300.,
+                    hash_set!(TechnologyTag::Modules),
+                ),
             ]
         });
 
@@ -207,6 +548,10 @@ impl Structure for Assembler {
         &self.position
     }
 
+    fn rotation(&self) -> Option<Rotation> {
+        Some(self.rotation)
+    }
+
     fn size(&self) -> Size {
         Size::new(3, 3)
     }
@@ -310,6 +655,9 @@ impl Structure for Assembler {
                 gl.draw_arrays(GL::TRIANGLE_FAN, 0, 4);
             }
             2 => {
+                if self.uses_fluid() {
+                    draw_direction_arrow_gl((x + 1., y + 1.), &self.rotation, state, gl)?;
+                }
                 if !is_ghost {
                     if state.alt_mode {
                         if let Some((output, _)) =
@@ -355,7 +703,16 @@ impl Structure for Assembler {
             },
             format!("Input Items: <br>{}", self.input_inventory.describe()),
             format!("Output Items: <br>{}", self.output_inventory.describe())
-        )
+        ) + &self.module_effect.describe()
+            + &if self.uses_fluid() {
+                format!(
+                    "Input fluid: {}<br>Output fluid: {}",
+                    self.input_fluid_box.desc(),
+                    self.output_fluid_box.desc()
+                )
+            } else {
+                String::new()
+            }
     }
 
     fn frame_proc(
@@ -364,29 +721,19 @@ impl Structure for Assembler {
         state: &mut FactorishState,
         structures: &mut StructureDynIter,
     ) -> Result<FrameProcResult, ()> {
+        // Only the front side gets the output fluid
+        let mut front = [false; 4];
+        front[(self.rotation.angle_4() as usize + 2) % 4] = true;
+        self.output_fluid_box.simulate_sides(structures, front);
+
+        // Crafting speed researched is added to the speed bonus of the modules
+        self.module_effect = module_effect(state, me, &self.module_inventory).add(ModuleEffect {
+            speed: state.research_modifiers.crafting_speed,
+            ..ModuleEffect::default()
+        });
+
         if let Some(recipe) = &self.recipe {
             let mut ret = FrameProcResult::None;
-            // First, check if we need to refill the energy buffer in order to continue the current work.
-            // Refill the energy from the fuel
-            if self.power < recipe.power_cost {
-                let mut accumulated = 0.;
-                if let Some(network) = state
-                    .power_networks
-                    .iter()
-                    .find(|network| network.sinks.contains(&me))
-                {
-                    for id in network.sources.iter() {
-                        if let Some(source) = structures.get_mut(*id) {
-                            let demand = self.max_power - self.power - accumulated;
-                            if let Some(energy) = source.power_outlet(demand) {
-                                accumulated += energy;
-                                // console_log!("draining {:?}kJ of energy with {:?} demand, from {:?}, accumulated {:?}", energy, demand, structure.name(), accumulated);
-                            }
-                        }
-                    }
-                }
-                self.power += accumulated;
-            }
 
             if self.progress.is_none() {
                 // First, check if we have enough ingredients to finish this recipe.
@@ -398,10 +745,12 @@ impl Structure for Assembler {
                     .iter()
                     .map(|(item, count)| count <= &self.input_inventory.count_item(item))
                     .all(|b| b)
+                    && self.input_fluid_box.has_recipe_input(recipe)
                 {
                     for (item, count) in &recipe.input {
                         self.input_inventory.remove_items(item, *count);
                     }
+                    self.input_fluid_box.amount -= recipe.input_fluid_amount;
                     self.progress = Some(0.);
                     console_log!("inputting from Assembler {}", recipe.output.len());
                     ret = FrameProcResult::InventoryChanged(self.position);
@@ -410,20 +759,34 @@ impl Structure for Assembler {
 
             if let Some(prev_progress) = self.progress {
                 // Proceed only if we have sufficient energy in the buffer.
-                let progress = get_powered_progress(self.power, prev_progress, recipe);
+                let effect = self.module_effect;
+                let progress = get_module_progress(self.power, prev_progress, recipe, &effect);
                 if 1. <= prev_progress + progress {
+                    // Hold the finished product until the output fluid box has room for it
+                    if !self.output_fluid_box.has_recipe_output_space(recipe) {
+                        return Ok(ret);
+                    }
                     self.progress = None;
 
-                    // Produce outputs into inventory
+                    // Produce outputs into inventory, twice if the productivity bonus is earned
+                    let sets = if proceed_productivity(&mut self.bonus_progress, &effect) {
+                        2
+                    } else {
+                        1
+                    };
                     for output_item in &recipe.output {
                         self.output_inventory
-                            .add_items(&output_item.0, *output_item.1);
+                            .add_items(output_item.0, *output_item.1 * sets);
+                    }
+                    if let Some(fluid) = recipe.output_fluid {
+                        self.output_fluid_box.type_ = Some(fluid);
+                        self.output_fluid_box.amount += recipe.output_fluid_amount;
                     }
                     console_log!("outputting from Assembler {}", recipe.output.len());
                     return Ok(FrameProcResult::InventoryChanged(self.position));
                 } else {
                     self.progress = Some(prev_progress + progress);
-                    self.power -= progress * recipe.power_cost;
+                    self.power -= progress * get_module_power_cost(recipe, &effect);
                 }
             }
             return Ok(ret);
@@ -458,6 +821,7 @@ impl Structure for Assembler {
         Some(match invtype {
             InventoryType::Input => &self.input_inventory,
             InventoryType::Output => &self.output_inventory,
+            InventoryType::Module => &self.module_inventory,
             _ => return None,
         })
     }
@@ -466,6 +830,7 @@ impl Structure for Assembler {
         Some(match invtype {
             InventoryType::Input => &mut self.input_inventory,
             InventoryType::Output => &mut self.output_inventory,
+            InventoryType::Module => &mut self.module_inventory,
             _ => return None,
         })
     }
@@ -473,6 +838,7 @@ impl Structure for Assembler {
     fn destroy_inventory(&mut self) -> Inventory {
         let mut ret = std::mem::take(&mut self.input_inventory);
         ret.merge(std::mem::take(&mut self.output_inventory));
+        ret.merge(std::mem::take(&mut self.module_inventory));
         // Return the ingredients if it was in the middle of processing a recipe.
         if let Some(mut recipe) = self.recipe.take() {
             if self.progress.is_some() {
@@ -509,6 +875,11 @@ impl Structure for Assembler {
             player_inventory,
         );
 
+        // Fluids of the previous recipe are discarded
+        self.input_fluid_box.set_recipe_fluid(recipe.input_fluid);
+        self.input_fluid_box.input_enable = recipe.input_fluid.is_some();
+        self.output_fluid_box.set_recipe_fluid(recipe.output_fluid);
+
         self.recipe = Some(recipe);
         Ok(true)
     }
@@ -521,9 +892,49 @@ impl Structure for Assembler {
         self.progress
     }
 
+    fn module_slots(&self) -> usize {
+        2
+    }
+
+    fn set_rotation(&mut self, rotation: &Rotation) -> Result<(), ()> {
+        self.rotation = *rotation;
+        Ok(())
+    }
+
     fn power_sink(&self) -> bool {
         true
     }
 
+    fn power_demand(&self) -> f64 {
+        match &self.recipe {
+            Some(recipe) if self.power < recipe.power_cost => self.max_power - self.power,
+            _ => 0.,
+        }
+    }
+
+    fn power_inlet(&mut self, energy: f64) {
+        self.power += energy;
+    }
+
+    fn fluid_connections(&self) -> [bool; 4] {
+        let mut ret = [false; 4];
+        ret[self.rotation.angle_4() as usize] = true;
+        ret[self.rotation.next().next().angle_4() as usize] = true;
+        ret
+    }
+
+    fn fluid_box(&self) -> Option<Vec<&FluidBox>> {
+        Some(vec![&self.input_fluid_box, &self.output_fluid_box])
+    }
+
+    fn fluid_box_mut(&mut self) -> Option<Vec<&mut FluidBox>> {
+        Some(vec![&mut self.input_fluid_box, &mut self.output_fluid_box])
+    }
+
+    fn reset_for_blueprint(&mut self) {
+        default_reset_for_blueprint(self);
+        self.power = 0.;
+    }
+
     serialize_impl!();
 }
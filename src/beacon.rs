@@ -0,0 +1,276 @@
+use super::{
+    gl::{
+        draw_electricity_alarm_gl,
+        utils::{enable_buffer, Flatten},
+    },
+    inventory::{Inventory, InventoryTrait, InventoryType},
+    items::ItemType,
+    modules::ModuleEffect,
+    structure::{
+        default_add_inventory, BoundingBox, Size, Structure, StructureDynIter, StructureEntry,
+        StructureId,
+    },
+    FactorishState, FrameProcResult, Position, TILE_SIZE,
+};
+use cgmath::{Matrix3, Matrix4, Vector3};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use wasm_bindgen::prelude::*;
+use web_sys::{CanvasRenderingContext2d, WebGlRenderingContext as GL};
+
+const BEACON_MODULE_SLOTS: usize = 2;
+/// Tiles the supply area extends beyond the footprint
+const BEACON_RANGE: i32 = 3;
+/// Ratio of the module effects transmitted to the machines
+const BEACON_EFFECTIVITY: f64 = 0.5;
+/// Energy in kilojoules consumed in a tick while active
+const BEACON_POWER: f64 = 0.5;
+const BEACON_MAX_POWER: f64 = 10.;
+
+/// A beacon transmits the effects of its modules to every machine whose bounding box
+/// overlaps its supply area. Productivity modules are not accepted.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct Beacon {
+    position: Position,
+    module_inventory: Inventory,
+    power: f64,
+    #[serde(skip)]
+    active: bool,
+}
+
+impl Beacon {
+    pub(crate) fn new(position: &Position) -> Self {
+        Self {
+            position: *position,
+            module_inventory: Inventory::new(),
+            power: 0.,
+            active: false,
+        }
+    }
+}
+
+/// Sums up the effects of the beacons covering each machine that accepts modules.
+pub(crate) fn collect_beacon_effects(
+    structures: &[StructureEntry],
+) -> HashMap<StructureId, ModuleEffect> {
+    let beacons: Vec<_> = structures
+        .iter()
+        .filter_map(|entry| entry.dynamic.as_deref()?.beacon_effect())
+        .collect();
+    let mut ret = HashMap::new();
+    if beacons.is_empty() {
+        return ret;
+    }
+    for (i, entry) in structures.iter().enumerate() {
+        let Some(dynamic) = entry.dynamic.as_deref() else {
+            continue;
+        };
+        if dynamic.module_slots() == 0 || dynamic.beacon_effect().is_some() {
+            continue;
+        }
+        let bbox = dynamic.bounding_box();
+        let effect = beacons
+            .iter()
+            .filter(|(area, _)| area.intersects(&bbox))
+            .fold(ModuleEffect::default(), |acc, (_, effect)| acc.add(*effect));
+        if effect != ModuleEffect::default() {
+            ret.insert(
+                StructureId {
+                    id: i as u32,
+                    gen: entry.gen,
+                },
+                effect,
+            );
+        }
+    }
+    ret
+}
+
+impl Structure for Beacon {
+    fn name(&self) -> &str {
+        "Beacon"
+    }
+
+    fn position(&self) -> &Position {
+        &self.position
+    }
+
+    fn size(&self) -> Size {
+        Size::new(3, 3)
+    }
+
+    fn draw(
+        &self,
+        state: &FactorishState,
+        context: &CanvasRenderingContext2d,
+        depth: i32,
+        is_toolbar: bool,
+    ) -> Result<(), JsValue> {
+        if depth != 0 {
+            return Ok(());
+        }
+        let (x, y) = (
+            self.position.x as f64 * TILE_SIZE,
+            self.position.y as f64 * TILE_SIZE,
+        );
+        let size = if is_toolbar {
+            TILE_SIZE
+        } else {
+            TILE_SIZE * 3.
+        };
+        match state.image_elect_pole.as_ref() {
+            Some(img) => {
+                context.draw_image_with_image_bitmap_and_dw_and_dh(
+                    &img.bitmap,
+                    x,
+                    y,
+                    size,
+                    size,
+                )?;
+                Ok(())
+            }
+            None => Err(JsValue::from_str("elect pole image not available")),
+        }
+    }
+
+    fn draw_gl(
+        &self,
+        state: &FactorishState,
+        gl: &GL,
+        depth: i32,
+        is_ghost: bool,
+    ) -> Result<(), JsValue> {
+        let (x, y) = (
+            self.position.x as f32 + state.viewport.x as f32,
+            self.position.y as f32 + state.viewport.y as f32,
+        );
+        match depth {
+            0 => {
+                let shader = state
+                    .assets
+                    .textured_shader
+                    .as_ref()
+                    .ok_or_else(|| js_str!("Shader not found"))?;
+                gl.use_program(Some(&shader.program));
+                gl.uniform1f(shader.alpha_loc.as_ref(), if is_ghost { 0.5 } else { 1. });
+                gl.active_texture(GL::TEXTURE0);
+                gl.bind_texture(GL::TEXTURE_2D, Some(&state.assets.tex_elect_pole));
+                gl.uniform_matrix3fv_with_f32_array(
+                    shader.tex_transform_loc.as_ref(),
+                    false,
+                    Matrix3::from_scale(1.).flatten(),
+                );
+                enable_buffer(gl, &state.assets.screen_buffer, 2, shader.vertex_position);
+                gl.uniform_matrix4fv_with_f32_array(
+                    shader.transform_loc.as_ref(),
+                    false,
+                    (state.get_world_transform()?
+                        * Matrix4::from_scale(2.)
+                        * Matrix4::from_translation(Vector3::new(x, y, 0.))
+                        * Matrix4::from_scale(3.))
+                    .flatten(),
+                );
+                gl.draw_arrays(GL::TRIANGLE_FAN, 0, 4);
+            }
+            2 if !is_ghost && !self.module_inventory.is_empty() && self.power == 0. => {
+                draw_electricity_alarm_gl((x, y), state, gl)?;
+            }
+            _ => (),
+        }
+        Ok(())
+    }
+
+    fn desc(&self, _state: &FactorishState) -> String {
+        format!(
+            "{}<br>Power: {:.1}kJ<br>Modules:<br>{}{}",
+            if self.active { "Active" } else { "Inactive" },
+            self.power,
+            self.module_inventory.describe(),
+            ModuleEffect::from_inventory(&self.module_inventory)
+                .scale(BEACON_EFFECTIVITY)
+                .describe()
+        )
+    }
+
+    fn frame_proc(
+        &mut self,
+        _me: StructureId,
+        _state: &mut FactorishState,
+        _structures: &mut StructureDynIter,
+    ) -> Result<FrameProcResult, ()> {
+        if self.module_inventory.is_empty() {
+            self.active = false;
+            return Ok(FrameProcResult::None);
+        }
+        self.active = BEACON_POWER <= self.power;
+        if self.active {
+            self.power -= BEACON_POWER;
+        }
+        Ok(FrameProcResult::None)
+    }
+
+    fn power_sink(&self) -> bool {
+        true
+    }
+
+    fn power_demand(&self) -> f64 {
+        if self.module_inventory.is_empty() {
+            0.
+        } else {
+            (BEACON_MAX_POWER - self.power).max(0.)
+        }
+    }
+
+    fn power_inlet(&mut self, energy: f64) {
+        self.power += energy;
+    }
+
+    fn module_slots(&self) -> usize {
+        BEACON_MODULE_SLOTS
+    }
+
+    fn beacon_effect(&self) -> Option<(BoundingBox, ModuleEffect)> {
+        let effect = if self.active {
+            ModuleEffect::from_inventory(&self.module_inventory).scale(BEACON_EFFECTIVITY)
+        } else {
+            ModuleEffect::default()
+        };
+        Some((self.bounding_box().expand(BEACON_RANGE), effect))
+    }
+
+    fn add_inventory(
+        &mut self,
+        inventory_type: InventoryType,
+        item_type: &ItemType,
+        count: isize,
+    ) -> isize {
+        if 0 < count && *item_type == ItemType::ProductivityModule {
+            return 0;
+        }
+        default_add_inventory(self, inventory_type, item_type, count)
+    }
+
+    fn inventory(&self, invtype: InventoryType) -> Option<&Inventory> {
+        Some(match invtype {
+            InventoryType::Module => &self.module_inventory,
+            _ => return None,
+        })
+    }
+
+    fn inventory_mut(&mut self, invtype: InventoryType) -> Option<&mut Inventory> {
+        Some(match invtype {
+            InventoryType::Module => &mut self.module_inventory,
+            _ => return None,
+        })
+    }
+
+    fn destroy_inventory(&mut self) -> Inventory {
+        std::mem::take(&mut self.module_inventory)
+    }
+
+    fn reset_for_blueprint(&mut self) {
+        self.power = 0.;
+    }
+
+    crate::serialize_impl!();
+}
@@ -0,0 +1,375 @@
+use super::{
+    gl::draw_flat_rect_gl,
+    items::str_to_item,
+    power_network::build_power_networks,
+    structure::{BoundingBox, Structure, StructureDynIter, StructureId},
+    FactorishState, InventoryTrait, ItemType, Position, PowerWire, MAX_WIRES,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use wasm_bindgen::prelude::*;
+use web_sys::WebGlRenderingContext as GL;
+
+/// Version prefix of the blueprint strings, to tell the format apart in the future
+const BLUEPRINT_STRING_VERSION: char = '0';
+const BASE64_CHARS: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// A tool that the player drags over the map to select a rectangular area
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum AreaTool {
+    Blueprint,
+    /// Mark the structures for deconstruction, only the ones made of the item if given
+    Deconstruct(Option<ItemType>),
+    CancelDeconstruct,
+}
+
+impl AreaTool {
+    fn color(&self) -> [f32; 4] {
+        match self {
+            Self::Blueprint => [0.2, 0.5, 1., 0.3],
+            Self::Deconstruct(_) => [1., 0.2, 0.2, 0.3],
+            Self::CancelDeconstruct => [1., 0.8, 0.2, 0.3],
+        }
+    }
+}
+
+/// A structure in a blueprint, in the same form as the structures in a saved game.
+/// The items, fluids and energy in it are removed and the position is relative to the
+/// top left corner of the blueprint.
+#[derive(Serialize, Deserialize, Clone)]
+struct BlueprintEntry {
+    #[serde(rename = "type")]
+    type_: String,
+    payload: serde_json::Value,
+}
+
+impl BlueprintEntry {
+    /// Copy the structure without the items, fluids and energy in it, so that pasting
+    /// a blueprint does not duplicate them.
+    fn from_structure(structure: &dyn Structure) -> Result<Self, JsValue> {
+        let mut value = serde_json::json!({
+            "type": structure.name(),
+            "payload": structure
+                .serialize()
+                .map_err(|e| js_str!("Serialize error: {}", e))?,
+        });
+        let mut copy = FactorishState::structure_from_json(&mut value)?;
+        copy.destroy_inventory();
+        copy.reset_for_blueprint();
+        Ok(Self {
+            type_: copy.name().to_string(),
+            payload: copy
+                .serialize()
+                .map_err(|e| js_str!("Serialize error: {}", e))?,
+        })
+    }
+
+    fn shift_position(&mut self, dx: i32, dy: i32) -> Result<(), JsValue> {
+        let type_ = &self.type_;
+        let position = self
+            .payload
+            .get_mut("position")
+            .ok_or_else(|| js_str!("{} has no position", type_))?;
+        let mut value: Position = serde_json::from_value(position.take())
+            .map_err(|e| js_str!("position deserialization error: {}", e))?;
+        value.x += dx;
+        value.y += dy;
+        *position = serde_json::to_value(value)
+            .map_err(|e| js_str!("position serialization error: {}", e))?;
+        Ok(())
+    }
+}
+
+/// A layout of structures captured from the map, which can be pasted elsewhere or shared
+/// as a text string.
+#[derive(Serialize, Deserialize, Clone)]
+pub(crate) struct Blueprint {
+    entries: Vec<BlueprintEntry>,
+    /// Power wires between the entries by their indices
+    power_wires: Vec<(usize, usize)>,
+}
+
+impl Blueprint {
+    pub(crate) fn to_blueprint_string(&self) -> Result<String, JsValue> {
+        let json =
+            serde_json::to_string(self).map_err(|e| js_str!("blueprint serialize error: {}", e))?;
+        Ok(format!(
+            "{}{}",
+            BLUEPRINT_STRING_VERSION,
+            base64_encode(json.as_bytes())
+        ))
+    }
+
+    pub(crate) fn from_blueprint_string(s: &str) -> Result<Self, JsValue> {
+        let s = s.trim();
+        let data = s
+            .strip_prefix(BLUEPRINT_STRING_VERSION)
+            .ok_or_else(|| js_str!("Unknown blueprint string version"))?;
+        let json = base64_decode(data).ok_or_else(|| js_str!("Malformed blueprint string"))?;
+        serde_json::from_slice(&json).map_err(|e| js_str!("blueprint deserialize error: {}", e))
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+fn base64_encode(data: &[u8]) -> String {
+    let mut ret = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bits = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |acc, (i, b)| acc | (*b as u32) << (16 - i * 8));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                ret.push(BASE64_CHARS[(bits >> (18 - i * 6) & 0x3f) as usize] as char);
+            } else {
+                ret.push('=');
+            }
+        }
+    }
+    ret
+}
+
+fn base64_decode(s: &str) -> Option<Vec<u8>> {
+    let s = s.trim_end_matches('=').as_bytes();
+    let mut ret = Vec::with_capacity(s.len() * 3 / 4);
+    for chunk in s.chunks(4) {
+        if chunk.len() < 2 {
+            return None;
+        }
+        let mut bits = 0u32;
+        for (i, c) in chunk.iter().enumerate() {
+            let value = BASE64_CHARS.iter().position(|b| b == c)? as u32;
+            bits |= value << (18 - i * 6);
+        }
+        for i in 0..chunk.len() - 1 {
+            ret.push((bits >> (16 - i * 8)) as u8);
+        }
+    }
+    Some(ret)
+}
+
+impl FactorishState {
+    /// Capture the structures whose origins are in the area into a blueprint.
+    pub(super) fn capture_blueprint(&self, area: &BoundingBox) -> Result<Blueprint, JsValue> {
+        let mut entries = vec![];
+        let mut id_to_index = HashMap::new();
+        for (id, structure) in self.structure_id_iter() {
+            if !area.intersects_position(*structure.position()) {
+                continue;
+            }
+            let mut entry = BlueprintEntry::from_structure(structure)?;
+            entry.shift_position(-area.x0, -area.y0)?;
+            id_to_index.insert(id, entries.len());
+            entries.push(entry);
+        }
+        let power_wires = self
+            .power_wires
+            .iter()
+            .filter_map(|w| Some((*id_to_index.get(&w.0)?, *id_to_index.get(&w.1)?)))
+            .collect();
+        Ok(Blueprint {
+            entries,
+            power_wires,
+        })
+    }
+
+    /// Instantiate the structures in the blueprint with the top left corner at `origin`.
+    pub(super) fn blueprint_structures(
+        blueprint: &Blueprint,
+        origin: &Position,
+    ) -> Result<Vec<Box<dyn Structure>>, JsValue> {
+        blueprint
+            .entries
+            .iter()
+            .map(|entry| {
+                let mut entry = entry.clone();
+                entry.shift_position(origin.x, origin.y)?;
+                let mut value = serde_json::json!({
+                    "type": entry.type_,
+                    "payload": entry.payload,
+                });
+                Self::structure_from_json(&mut value)
+            })
+            .collect()
+    }
+
+    /// Whether the player can build the structure at its position without replacing anything
+    pub(super) fn can_place_structure(&self, structure: &dyn Structure) -> bool {
+        self.in_reach(structure.position())
+            && !self.blocked_by_character(structure)
+            && self.is_site_free(structure)
+    }
+
+    /// Whether the tiles under the structure exist, match its placement on land or water and
+    /// have no structure on them
+    pub(super) fn is_site_free(&self, structure: &dyn Structure) -> bool {
+        let Some(item) = str_to_item(structure.name()) else {
+            return false;
+        };
+        let bbox = structure.bounding_box();
+        (bbox.y0..bbox.y1).all(|y| {
+            (bbox.x0..bbox.x1).all(|x| {
+                let position = Position::new(x, y);
+                self.tile_at(&position)
+                    .is_some_and(|cell| cell.water == (item == ItemType::OffshorePump))
+                    && self.find_structure_tile(&[x, y]).is_none()
+            })
+        })
+    }
+
+    /// Build the structures in the blueprint held by the player with the top left corner at
+    /// `origin`, using the items in the player's inventory. The structures that cannot be built
+    /// now for lack of items or reach are queued as ghosts.
+    /// @returns true if any structure is built.
+    pub(super) fn paste_blueprint(&mut self, origin: &Position) -> Result<bool, JsValue> {
+        let Some(blueprint) = self.blueprint.clone() else {
+            return Ok(false);
+        };
+        let mut index_to_id: HashMap<usize, StructureId> = HashMap::new();
+        let mut ghost_positions: HashMap<usize, Position> = HashMap::new();
+        let mut positions: HashMap<usize, Position> = HashMap::new();
+        for (i, structure) in Self::blueprint_structures(&blueprint, origin)?
+            .into_iter()
+            .enumerate()
+        {
+            let Some(item) = str_to_item(structure.name()) else {
+                continue;
+            };
+            let position = *structure.position();
+            if !self.can_place_structure(structure.as_ref())
+                || self.player.inventory.count_item(&item) == 0
+            {
+                if self.add_ghost(structure) {
+                    ghost_positions.insert(i, position);
+                    positions.insert(i, position);
+                }
+                continue;
+            }
+            self.player.inventory.remove_item(&item);
+            index_to_id.insert(i, self.place_structure(structure)?);
+            positions.insert(i, position);
+        }
+
+        for (first, second) in &blueprint.power_wires {
+            if let Some((first, second)) = index_to_id.get(first).zip(index_to_id.get(second)) {
+                let wire = PowerWire(*first, *second);
+                if !self.power_wires.contains(&wire)
+                    && self.wire_count(*first) < MAX_WIRES
+                    && self.wire_count(*second) < MAX_WIRES
+                {
+                    self.power_wires.push(wire);
+                }
+                continue;
+            }
+            // The wires of the ghosts are connected when they are built
+            for &(from, to) in &[(first, second), (second, first)] {
+                let (Some(from), Some(to)) = (ghost_positions.get(from), positions.get(to)) else {
+                    continue;
+                };
+                if let Some(ghost) = self.ghosts.iter_mut().find(|g| g.position() == *from) {
+                    ghost.wires.push(*to);
+                }
+            }
+        }
+        if !blueprint.power_wires.is_empty() {
+            self.power_networks = build_power_networks(
+                &StructureDynIter::new_all(&mut self.structures),
+                &self.power_wires,
+            );
+        }
+
+        Ok(!index_to_id.is_empty())
+    }
+
+    /// The area being selected by dragging an area tool
+    pub(super) fn selected_area(&self) -> Option<BoundingBox> {
+        let start = self.area_start?;
+        let cursor = self.cursor?;
+        Some(BoundingBox {
+            x0: start.x.min(cursor[0]),
+            y0: start.y.min(cursor[1]),
+            x1: start.x.max(cursor[0]) + 1,
+            y1: start.y.max(cursor[1]) + 1,
+        })
+    }
+
+    pub(super) fn render_blueprint_gl(&self, gl: &GL) -> Result<(), JsValue> {
+        if let Some((area, tool)) = self.selected_area().zip(self.area_tool) {
+            draw_flat_rect_gl(
+                self,
+                gl,
+                (
+                    area.x0 as f32 + self.viewport.x as f32,
+                    area.y0 as f32 + self.viewport.y as f32,
+                    (area.x1 - area.x0) as f32,
+                    (area.y1 - area.y0) as f32,
+                ),
+                &tool.color(),
+            )?;
+        }
+        if let Some((blueprint, cursor)) = self.blueprint.as_ref().zip(self.cursor.as_ref()) {
+            let structures = Self::blueprint_structures(blueprint, &Position::from(cursor))?;
+            for depth in 0..3 {
+                for structure in &structures {
+                    structure.draw_gl(self, gl, depth, true)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_base64() {
+    for data in [&b""[..], b"f", b"fo", b"foo", b"foob", b"fooba", b"foobar"] {
+        let encoded = base64_encode(data);
+        assert_eq!(encoded.len() % 4, 0);
+        assert_eq!(base64_decode(&encoded).as_deref(), Some(data));
+    }
+    assert_eq!(base64_encode(b"foobar"), "Zm9vYmFy");
+    assert_eq!(base64_encode(b"fo"), "Zm8=");
+    assert_eq!(base64_decode("Zm9v!"), None);
+}
+
+#[test]
+fn test_blueprint_string() {
+    let blueprint = Blueprint {
+        entries: vec![BlueprintEntry {
+            type_: "Chest".to_string(),
+            payload: serde_json::json!({"position": {"x": 1, "y": 2}}),
+        }],
+        power_wires: vec![(0, 0)],
+    };
+    let s = blueprint.to_blueprint_string().unwrap();
+    assert!(s.starts_with(BLUEPRINT_STRING_VERSION));
+    let decoded = Blueprint::from_blueprint_string(&s).unwrap();
+    assert_eq!(decoded.entries[0].type_, "Chest");
+    assert_eq!(decoded.entries[0].payload, blueprint.entries[0].payload);
+    assert_eq!(decoded.power_wires, [(0, 0)]);
+}
+
+#[test]
+fn test_blueprint_empties_fluids() {
+    use super::storage_tank::StorageTank;
+
+    let mut tank = StorageTank::new(&Position::new(3, 4));
+    let fluid_box = &mut tank.fluid_box_mut().unwrap()[0];
+    fluid_box.amount = 1000.;
+    fluid_box.type_ = Some(super::water_well::FluidType::Water);
+    let mut entry = BlueprintEntry::from_structure(&tank).unwrap();
+    entry.shift_position(-3, -4).unwrap();
+    let blueprint = Blueprint {
+        entries: vec![entry],
+        power_wires: vec![],
+    };
+
+    let pasted = FactorishState::blueprint_structures(&blueprint, &Position::new(10, 20)).unwrap();
+    assert_eq!(pasted.len(), 1);
+    assert_eq!(pasted[0].name(), "Storage Tank");
+    assert_eq!(*pasted[0].position(), Position::new(10, 20));
+    assert_eq!(pasted[0].fluid_box().unwrap()[0].amount, 0.);
+    assert_eq!(tank.fluid_box().unwrap()[0].amount, 1000.);
+}
@@ -4,7 +4,7 @@ use super::{
     gl::utils::{enable_buffer, Flatten},
     inventory::InventoryType,
     serialize_impl,
-    structure::{Structure, StructureDynIter, StructureId},
+    structure::{default_reset_for_blueprint, Structure, StructureDynIter, StructureId},
     water_well::{FluidBox, FluidType},
     FactorishState, FrameProcResult, Inventory, InventoryTrait, ItemType, Position, Recipe,
     TempEnt, COAL_POWER,
@@ -41,8 +41,10 @@ impl Boiler {
             recipe: Some(Recipe {
                 input: hash_map!(ItemType::CoalOre => 1usize),
                 input_fluid: Some(FluidType::Water),
+                input_fluid_amount: Self::FLUID_PER_PROGRESS,
                 output: HashMap::new(),
                 output_fluid: Some(FluidType::Steam),
+                output_fluid_amount: Self::FLUID_PER_PROGRESS,
                 power_cost: 100.,
                 recipe_time: 30.,
                 requires_technology: HashSet::new(),
@@ -354,5 +356,10 @@ impl Structure for Boiler {
         Some(vec![&mut self.input_fluid_box, &mut self.output_fluid_box])
     }
 
+    fn reset_for_blueprint(&mut self) {
+        default_reset_for_blueprint(self);
+        self.power = 0.;
+    }
+
     serialize_impl!();
 }
@@ -0,0 +1,263 @@
+use super::{
+    gl::draw_flat_rect_gl,
+    structure::{BoundingBox, Structure},
+    FactorishState, Position, TILE_SIZE,
+};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+use web_sys::WebGlRenderingContext as GL;
+
+/// Distance in tiles that the character walks in a tick
+const CHARACTER_SPEED: f64 = 0.15;
+/// Half the width of the character's collision box in tiles
+const CHARACTER_RADIUS: f64 = 0.3;
+/// Distance in tiles from the character within which the player can build and mine
+const CHARACTER_REACH: f64 = 10.;
+/// Distance in tiles from the character within which dropped items are picked up
+const PICKUP_RADIUS: f64 = 1.5;
+/// Maximum distance in tiles to look for a free spot to put the character at
+const SPAWN_SEARCH_RANGE: i32 = 16;
+
+/// The player's avatar in the character mode. It walks on the map with WASD keys and
+/// the player can only build and mine within its reach.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct Character {
+    /// Center of the character in tiles
+    x: f64,
+    y: f64,
+    /// Walking directions held down, in the order of left, up, right and down
+    #[serde(skip)]
+    walking: [bool; 4],
+    #[serde(skip)]
+    picking_up: bool,
+}
+
+impl Character {
+    pub(crate) fn new(x: f64, y: f64) -> Self {
+        Self {
+            x,
+            y,
+            walking: [false; 4],
+            picking_up: false,
+        }
+    }
+
+    /// Update the keys held down to control the character.
+    /// @returns false if the key is not for the character.
+    pub(crate) fn set_key(&mut self, key_code: i32, pressed: bool) -> bool {
+        let direction = match key_code {
+            65 => 0, // 'a'
+            87 => 1, // 'w'
+            68 => 2, // 'd'
+            83 => 3, // 's'
+            70 => {
+                // 'f'
+                self.picking_up = pressed;
+                return true;
+            }
+            _ => return false,
+        };
+        self.walking[direction] = pressed;
+        true
+    }
+
+    pub(crate) fn in_reach(&self, position: &Position) -> bool {
+        let (dx, dy) = (
+            position.x as f64 + 0.5 - self.x,
+            position.y as f64 + 0.5 - self.y,
+        );
+        dx * dx + dy * dy <= CHARACTER_REACH * CHARACTER_REACH
+    }
+
+    pub(crate) fn overlaps(&self, bbox: &BoundingBox) -> bool {
+        (bbox.x0 as f64) < self.x + CHARACTER_RADIUS
+            && self.x - CHARACTER_RADIUS < bbox.x1 as f64
+            && (bbox.y0 as f64) < self.y + CHARACTER_RADIUS
+            && self.y - CHARACTER_RADIUS < bbox.y1 as f64
+    }
+
+    fn collides(x: f64, y: f64, is_blocked: &impl Fn(Position) -> bool) -> bool {
+        let x0 = (x - CHARACTER_RADIUS).floor() as i32;
+        let x1 = (x + CHARACTER_RADIUS).floor() as i32;
+        let y0 = (y - CHARACTER_RADIUS).floor() as i32;
+        let y1 = (y + CHARACTER_RADIUS).floor() as i32;
+        (y0..=y1).any(|y| (x0..=x1).any(|x| is_blocked(Position::new(x, y))))
+    }
+
+    /// Walk a tick in the directions held down. Each axis is checked separately so that
+    /// the character slides along obstacles.
+    /// @returns true if the character has moved.
+    fn walk(&mut self, is_blocked: impl Fn(Position) -> bool) -> bool {
+        let dx = self.walking[2] as i32 - self.walking[0] as i32;
+        let dy = self.walking[3] as i32 - self.walking[1] as i32;
+        if dx == 0 && dy == 0 {
+            return false;
+        }
+        let step = if dx != 0 && dy != 0 {
+            CHARACTER_SPEED * std::f64::consts::FRAC_1_SQRT_2
+        } else {
+            CHARACTER_SPEED
+        };
+        // Let the character get out if it is stuck in an obstacle somehow
+        let stuck = Self::collides(self.x, self.y, &is_blocked);
+        let mut moved = false;
+        let x = self.x + dx as f64 * step;
+        if dx != 0 && (stuck || !Self::collides(x, self.y, &is_blocked)) {
+            self.x = x;
+            moved = true;
+        }
+        let y = self.y + dy as f64 * step;
+        if dy != 0 && (stuck || !Self::collides(self.x, y, &is_blocked)) {
+            self.y = y;
+            moved = true;
+        }
+        moved
+    }
+}
+
+impl FactorishState {
+    /// Whether the character cannot walk into the tile
+    fn character_blocked(&self, position: Position) -> bool {
+        if let Some(bounds) = &self.bounds {
+            if position.x < 0
+                || position.y < 0
+                || bounds.width <= position.x
+                || bounds.height <= position.y
+            {
+                return true;
+            }
+        }
+        self.tile_at(&position).map_or(true, |cell| cell.water)
+            || self
+                .find_structure_tile(&[position.x, position.y])
+                .is_some_and(|s| !s.walkable())
+    }
+
+    /// Put the character on a free tile near the center of the screen.
+    pub(super) fn spawn_character(&mut self) {
+        let center = (
+            (self.viewport_width / self.viewport.scale / TILE_SIZE / 2. - self.viewport.x) as i32,
+            (self.viewport_height / self.viewport.scale / TILE_SIZE / 2. - self.viewport.y) as i32,
+        );
+        let spot = (0..SPAWN_SEARCH_RANGE)
+            .flat_map(|r| {
+                (-r..=r).flat_map(move |dy| (-r..=r).map(move |dx| (center.0 + dx, center.1 + dy)))
+            })
+            .find(|(x, y)| !self.character_blocked(Position::new(*x, *y)))
+            .unwrap_or(center);
+        self.player.character = Some(Character::new(spot.0 as f64 + 0.5, spot.1 as f64 + 0.5));
+    }
+
+    /// Whether the player can build or mine at the position, which is always true out of the
+    /// character mode.
+    pub(super) fn in_reach(&self, position: &Position) -> bool {
+        self.player
+            .character
+            .as_ref()
+            .map_or(true, |character| character.in_reach(position))
+    }
+
+    /// Whether the character stands in the way of building the structure
+    pub(super) fn blocked_by_character(&self, structure: &dyn Structure) -> bool {
+        !structure.walkable()
+            && self
+                .player
+                .character
+                .as_ref()
+                .is_some_and(|c| c.overlaps(&structure.bounding_box()))
+    }
+
+    /// Move the character and let it pick up items around it.
+    /// @returns true if the player's inventory has changed.
+    pub(super) fn character_step(&mut self) -> bool {
+        let Some(mut character) = self.player.character.take() else {
+            return false;
+        };
+        if character.walk(|position| self.character_blocked(position)) {
+            // Keep the character at the center of the screen
+            self.viewport.x =
+                self.viewport_width / self.viewport.scale / TILE_SIZE / 2. - character.x;
+            self.viewport.y =
+                self.viewport_height / self.viewport.scale / TILE_SIZE / 2. - character.y;
+            self.gen_chunks_in_viewport();
+        }
+        if self
+            .ore_harvesting
+            .is_some_and(|harvesting| !character.in_reach(&harvesting.pos))
+        {
+            self.ore_harvesting = None;
+        }
+
+        let mut picked_item = None;
+        if character.picking_up {
+            // Pick up one item in a tick, like grabbing them from a belt one by one
+            self.drop_items.retain(|item| {
+                if picked_item.is_some() {
+                    return true;
+                }
+                let (dx, dy) = (
+                    item.x / TILE_SIZE - character.x,
+                    item.y / TILE_SIZE - character.y,
+                );
+                if PICKUP_RADIUS * PICKUP_RADIUS < dx * dx + dy * dy {
+                    return true;
+                }
+                picked_item = Some(item.type_);
+                false
+            });
+        }
+        self.player.character = Some(character);
+
+        let Some(item_type) = picked_item else {
+            return false;
+        };
+        self.player.add_item(&item_type, 1);
+        true
+    }
+
+    pub(super) fn render_character_gl(&self, gl: &GL) -> Result<(), JsValue> {
+        let Some(character) = &self.player.character else {
+            return Ok(());
+        };
+        let (x, y) = (
+            (character.x + self.viewport.x) as f32,
+            (character.y + self.viewport.y) as f32,
+        );
+        let r = CHARACTER_RADIUS as f32;
+        draw_flat_rect_gl(
+            self,
+            gl,
+            (x - r, y - r, 2. * r, 2. * r),
+            &[0.9, 0.55, 0.1, 1.],
+        )?;
+        draw_flat_rect_gl(
+            self,
+            gl,
+            (x - r / 2., y - r * 1.2, r, r),
+            &[0.95, 0.8, 0.65, 1.],
+        )?;
+        Ok(())
+    }
+}
+
+#[test]
+fn test_character_walk() {
+    // A wall at x == 2 with a gap at y == 0
+    let is_blocked = |position: Position| position.x == 2 && position.y != 0;
+
+    let mut character = Character::new(0.5, 1.5);
+    character.set_key(68, true);
+    for _ in 0..100 {
+        character.walk(is_blocked);
+    }
+    assert!((character.x - (2. - CHARACTER_RADIUS)).abs() < CHARACTER_SPEED);
+    assert_eq!(character.y, 1.5);
+
+    // Slides along the wall while walking diagonally and goes through the gap
+    character.set_key(87, true);
+    for _ in 0..100 {
+        character.walk(is_blocked);
+    }
+    assert!(3. < character.x);
+    assert!(!character.in_reach(&Position::new(-20, 0)));
+}
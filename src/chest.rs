@@ -1,9 +1,11 @@
 use super::{
+    circuit::{SignalId, Signals},
     drop_items::DropItem,
     gl::utils::{enable_buffer, Flatten},
-    inventory::InventoryType,
-    items::ItemType,
-    structure::{ItemResponse, ItemResponseResult, Structure, StructureDynIter},
+    inventory::{storage_capacity, InventoryType},
+    items::{item_to_str, ItemType},
+    logistics::{draw_logistic_marker_gl, LogisticChestKind},
+    structure::{ItemResponse, ItemResponseResult, Structure, StructureDynIter, StructureId},
     FactorishState, FrameProcResult, Inventory, InventoryTrait, Position,
 };
 use cgmath::{Matrix3, Matrix4, Vector3};
@@ -11,26 +13,98 @@ use serde::{Deserialize, Serialize};
 use wasm_bindgen::prelude::*;
 use web_sys::{CanvasRenderingContext2d, WebGlRenderingContext as GL};
 
-const CHEST_CAPACITY: usize = 100;
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub(crate) enum ChestTier {
+    #[default]
+    Wooden,
+    Iron,
+    Steel,
+}
+
+impl ChestTier {
+    /// Number of stacks the chest can hold
+    fn slots(&self) -> usize {
+        match self {
+            Self::Wooden => 16,
+            Self::Iron => 32,
+            Self::Steel => 48,
+        }
+    }
+}
 
 #[derive(Serialize, Deserialize)]
 pub(crate) struct Chest {
     position: Position,
     inventory: Inventory,
+    #[serde(default)]
+    tier: ChestTier,
+    /// Number of slots that inserters are allowed to fill, or `None` to use all of them.
+    #[serde(default)]
+    bar: Option<usize>,
+    #[serde(default)]
+    filters: Vec<Option<ItemType>>,
+    /// The role in the logistic network, or `None` for an ordinary chest.
+    #[serde(default)]
+    logistic: Option<LogisticChestKind>,
+    /// Items that a requester chest asks the logistic network for
+    #[serde(default)]
+    requests: Inventory,
+    /// Slots added by research, updated every tick
+    #[serde(skip)]
+    bonus_slots: usize,
 }
 
 impl Chest {
     pub(crate) fn new(position: &Position) -> Self {
+        Self::new_tier(position, ChestTier::Wooden)
+    }
+
+    pub(crate) fn new_tier(position: &Position, tier: ChestTier) -> Self {
         Chest {
             position: *position,
             inventory: Inventory::new(),
+            tier,
+            bar: None,
+            filters: vec![],
+            logistic: None,
+            requests: Inventory::new(),
+            bonus_slots: 0,
         }
     }
+
+    /// Logistic chests have the same capacity as steel chests.
+    pub(crate) fn new_logistic(position: &Position, kind: LogisticChestKind) -> Self {
+        Chest {
+            logistic: Some(kind),
+            ..Self::new_tier(position, ChestTier::Steel)
+        }
+    }
+
+    /// Number of stacks including the bonus slots granted by research
+    fn slots(&self) -> usize {
+        self.tier.slots() + self.bonus_slots
+    }
+
+    fn bar_slots(&self) -> usize {
+        self.bar.unwrap_or(self.slots()).min(self.slots())
+    }
 }
 
 impl Structure for Chest {
     fn name(&self) -> &'static str {
-        "Chest"
+        if let Some(kind) = self.logistic {
+            return match kind {
+                LogisticChestKind::PassiveProvider => "Passive Provider Chest",
+                LogisticChestKind::ActiveProvider => "Active Provider Chest",
+                LogisticChestKind::Storage => "Storage Chest",
+                LogisticChestKind::Requester => "Requester Chest",
+            };
+        }
+        match self.tier {
+            ChestTier::Wooden => "Chest",
+            ChestTier::Iron => "Iron Chest",
+            ChestTier::Steel => "Steel Chest",
+        }
     }
 
     fn position(&self) -> &Position {
@@ -97,12 +171,38 @@ impl Structure for Chest {
         );
         gl.draw_arrays(GL::TRIANGLE_FAN, 0, 4);
 
+        if let Some(kind) = self.logistic {
+            draw_logistic_marker_gl((x, y), &kind.color(), state, gl)?;
+        }
+
         Ok(())
     }
 
     fn desc(&self, _state: &FactorishState) -> String {
         format!(
-            "Items: \n{}",
+            "Slots: {} / {}{}{}<br>{}{}Items: \n{}",
+            self.inventory.count_slots(),
+            self.slots(),
+            if 0 < self.bonus_slots {
+                format!(" (+{} by research)", self.bonus_slots)
+            } else {
+                "".to_string()
+            },
+            if let Some(bar) = self.bar {
+                format!(" (limited to {})", bar)
+            } else {
+                "".to_string()
+            },
+            self.filters
+                .iter()
+                .enumerate()
+                .filter_map(|(i, f)| Some(format!("Slot {}: {}<br>", i, item_to_str(f.as_ref()?))))
+                .fold(String::from(""), |accum, item| accum + &item),
+            if self.requests.is_empty() {
+                "".to_string()
+            } else {
+                format!("Requests: <br>{}", self.requests.describe())
+            },
             self.inventory
                 .iter()
                 .map(|item| format!("{:?}: {}<br>", item.0, item.1))
@@ -110,8 +210,33 @@ impl Structure for Chest {
         )
     }
 
+    fn frame_proc(
+        &mut self,
+        _me: StructureId,
+        state: &mut FactorishState,
+        _structures: &mut StructureDynIter,
+    ) -> Result<FrameProcResult, ()> {
+        self.bonus_slots = state.research_modifiers.chest_slots;
+        Ok(FrameProcResult::None)
+    }
+
+    fn circuit_connectable(&self) -> bool {
+        true
+    }
+
+    fn circuit_signals(&self) -> Option<Signals> {
+        Some(
+            self.inventory
+                .iter()
+                .map(|(item, count)| (SignalId::Item(*item), *count as i32))
+                .collect(),
+        )
+    }
+
     fn item_response(&mut self, _item: &DropItem) -> Result<ItemResponseResult, ()> {
-        if 0 < self.add_inventory(InventoryType::Storage, &_item.type_, 1) {
+        if self.can_input(&_item.type_)
+            && 0 < self.add_inventory(InventoryType::Storage, &_item.type_, 1)
+        {
             Ok((
                 ItemResponse::Consume,
                 Some(FrameProcResult::InventoryChanged(self.position)),
@@ -127,9 +252,9 @@ impl Structure for Chest {
             .map_err(|_| JsValue::from_str("ItemResponse failed"))
     }
 
-    /// Chest can put any item
-    fn can_input(&self, _o: &ItemType) -> bool {
-        self.inventory.len() < CHEST_CAPACITY
+    /// Chest can put any item unless limited by the bar or slot filters
+    fn can_input(&self, o: &ItemType) -> bool {
+        0 < storage_capacity(&self.inventory, o, self.bar_slots(), &self.filters)
     }
 
     fn can_output(&self, _structures: &StructureDynIter) -> Inventory {
@@ -158,5 +283,60 @@ impl Structure for Chest {
         }
     }
 
+    fn storage_slots(&self) -> usize {
+        self.slots()
+    }
+
+    fn slot_filters(&self) -> &[Option<ItemType>] {
+        &self.filters
+    }
+
+    fn inventory_bar(&self) -> Option<usize> {
+        self.bar
+    }
+
+    fn set_inventory_bar(&mut self, bar: Option<usize>) -> Result<(), JsValue> {
+        self.bar = bar.map(|bar| bar.min(self.slots()));
+        Ok(())
+    }
+
+    fn set_slot_filter(&mut self, slot: usize, item: Option<ItemType>) -> Result<(), JsValue> {
+        if self.slots() <= slot {
+            return js_err!("Slot {} is out of range", slot);
+        }
+        if self.filters.len() <= slot {
+            self.filters.resize(slot + 1, None);
+        }
+        self.filters[slot] = item;
+        while let Some(None) = self.filters.last() {
+            self.filters.pop();
+        }
+        Ok(())
+    }
+
+    fn logistic_chest(&self) -> Option<LogisticChestKind> {
+        self.logistic
+    }
+
+    fn logistic_requests(&self) -> Option<&Inventory> {
+        if self.logistic == Some(LogisticChestKind::Requester) {
+            Some(&self.requests)
+        } else {
+            None
+        }
+    }
+
+    fn set_logistic_request(&mut self, item: ItemType, count: usize) -> Result<(), JsValue> {
+        if self.logistic != Some(LogisticChestKind::Requester) {
+            return js_err!("Only requester chests can have requests");
+        }
+        if count == 0 {
+            self.requests.remove(&item);
+        } else {
+            self.requests.insert(item, count);
+        }
+        Ok(())
+    }
+
     super::serialize_impl!();
 }
@@ -0,0 +1,461 @@
+use super::{
+    assembler::Assembler,
+    furnace,
+    items::{item_to_str, str_to_item, ItemType},
+    structure::{StructureDynIter, StructureId},
+    water_well::FluidType,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    convert::TryFrom,
+};
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub(crate) enum WireColor {
+    Red,
+    Green,
+}
+
+impl WireColor {
+    pub(crate) fn from_item(item: &ItemType) -> Option<Self> {
+        match item {
+            ItemType::RedWire => Some(Self::Red),
+            ItemType::GreenWire => Some(Self::Green),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn item(&self) -> ItemType {
+        match self {
+            Self::Red => ItemType::RedWire,
+            Self::Green => ItemType::GreenWire,
+        }
+    }
+
+    pub(crate) fn color(&self) -> [f32; 4] {
+        match self {
+            Self::Red => [0.9, 0.1, 0.1, 1.],
+            Self::Green => [0.1, 0.8, 0.1, 1.],
+        }
+    }
+}
+
+/// A kind of value transmitted in a circuit network.
+/// It is serialized as the name of the item or the fluid, so that JavaScript can show it as is.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "String")]
+pub(crate) enum SignalId {
+    Item(ItemType),
+    Fluid(FluidType),
+}
+
+impl SignalId {
+    pub(crate) fn name(&self) -> String {
+        match self {
+            Self::Item(item) => item_to_str(item),
+            Self::Fluid(fluid) => fluid.name().to_string(),
+        }
+    }
+}
+
+impl From<SignalId> for String {
+    fn from(signal: SignalId) -> Self {
+        signal.name()
+    }
+}
+
+impl TryFrom<String> for SignalId {
+    type Error = String;
+    fn try_from(name: String) -> Result<Self, Self::Error> {
+        if let Some(item) = str_to_item(&name) {
+            return Ok(Self::Item(item));
+        }
+        FluidType::from_name(&name)
+            .map(Self::Fluid)
+            .ok_or_else(|| format!("Unknown signal: {}", name))
+    }
+}
+
+pub(crate) type Signals = HashMap<SignalId, i32>;
+
+pub(crate) fn add_signals(signals: &mut Signals, other: &Signals) {
+    for (signal, value) in other {
+        let entry = signals.entry(*signal).or_default();
+        *entry = entry.wrapping_add(*value);
+    }
+}
+
+pub(crate) fn describe_signals(signals: &Signals) -> String {
+    let mut signals: Vec<_> = signals
+        .iter()
+        .filter(|(_, value)| **value != 0)
+        .map(|(signal, value)| (signal.name(), *value))
+        .collect();
+    signals.sort();
+    signals
+        .iter()
+        .map(|(name, value)| format!("{}: {}<br>", name, value))
+        .collect()
+}
+
+/// Names of all the signals that can be selected in the circuit settings.
+pub(crate) fn signal_names() -> Vec<String> {
+    Assembler::get_recipes()
+        .iter()
+        .chain(furnace::RECIPES.iter())
+        .flat_map(|recipe| recipe.input.keys().chain(recipe.output.keys()))
+        .map(item_to_str)
+        .chain(FluidType::ALL.iter().map(|fluid| fluid.name().to_string()))
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect()
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub(crate) enum Comparator {
+    #[serde(rename = "<")]
+    Less,
+    #[serde(rename = "<=")]
+    LessEq,
+    #[serde(rename = ">")]
+    Greater,
+    #[serde(rename = ">=")]
+    GreaterEq,
+    #[serde(rename = "=")]
+    Equal,
+    #[serde(rename = "!=")]
+    NotEqual,
+}
+
+impl Comparator {
+    fn compare(&self, lhs: i32, rhs: i32) -> bool {
+        match self {
+            Self::Less => lhs < rhs,
+            Self::LessEq => lhs <= rhs,
+            Self::Greater => lhs > rhs,
+            Self::GreaterEq => lhs >= rhs,
+            Self::Equal => lhs == rhs,
+            Self::NotEqual => lhs != rhs,
+        }
+    }
+
+    fn symbol(&self) -> &'static str {
+        match self {
+            Self::Less => "<",
+            Self::LessEq => "<=",
+            Self::Greater => ">",
+            Self::GreaterEq => ">=",
+            Self::Equal => "=",
+            Self::NotEqual => "!=",
+        }
+    }
+}
+
+/// Compares a signal with a constant. A missing signal counts as 0.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) struct CircuitCondition {
+    pub signal: SignalId,
+    pub comparator: Comparator,
+    pub constant: i32,
+}
+
+impl CircuitCondition {
+    pub(crate) fn evaluate(&self, signals: &Signals) -> bool {
+        let value = signals.get(&self.signal).copied().unwrap_or(0);
+        self.comparator.compare(value, self.constant)
+    }
+
+    fn describe(&self) -> String {
+        format!(
+            "{} {} {}",
+            self.signal.name(),
+            self.comparator.symbol(),
+            self.constant
+        )
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub(crate) enum ArithmeticOp {
+    #[serde(rename = "+")]
+    Add,
+    #[serde(rename = "-")]
+    Sub,
+    #[serde(rename = "*")]
+    Mul,
+    #[serde(rename = "/")]
+    Div,
+    #[serde(rename = "%")]
+    Mod,
+}
+
+impl ArithmeticOp {
+    /// Integer arithmetic that wraps around on overflow. Division by zero yields 0.
+    fn apply(&self, lhs: i32, rhs: i32) -> i32 {
+        match self {
+            Self::Add => lhs.wrapping_add(rhs),
+            Self::Sub => lhs.wrapping_sub(rhs),
+            Self::Mul => lhs.wrapping_mul(rhs),
+            Self::Div => lhs.checked_div(rhs).unwrap_or(0),
+            Self::Mod => lhs.checked_rem(rhs).unwrap_or(0),
+        }
+    }
+
+    fn symbol(&self) -> &'static str {
+        match self {
+            Self::Add => "+",
+            Self::Sub => "-",
+            Self::Mul => "*",
+            Self::Div => "/",
+            Self::Mod => "%",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) struct ArithmeticConfig {
+    pub input: SignalId,
+    pub op: ArithmeticOp,
+    pub constant: i32,
+    pub output: SignalId,
+}
+
+impl ArithmeticConfig {
+    pub(crate) fn evaluate(&self, signals: &Signals) -> Signals {
+        let value = signals.get(&self.input).copied().unwrap_or(0);
+        hash_map!(self.output => self.op.apply(value, self.constant))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) struct DeciderConfig {
+    pub condition: CircuitCondition,
+    pub output: SignalId,
+    /// Output the input value of the output signal instead of 1
+    pub copy_count: bool,
+}
+
+impl DeciderConfig {
+    pub(crate) fn evaluate(&self, signals: &Signals) -> Signals {
+        if !self.condition.evaluate(signals) {
+            return Signals::new();
+        }
+        let value = if self.copy_count {
+            signals.get(&self.output).copied().unwrap_or(0)
+        } else {
+            1
+        };
+        hash_map!(self.output => value)
+    }
+}
+
+/// Settings of a structure that can be edited from the circuit network section of the
+/// inventory window. The variant of a structure never changes.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) enum CircuitConfig {
+    /// Enable condition of an inserter or a transport belt. It is always enabled if `None`
+    /// or not connected to a circuit network.
+    Condition(Option<CircuitCondition>),
+    Constant(Vec<(SignalId, i32)>),
+    Arithmetic(ArithmeticConfig),
+    Decider(DeciderConfig),
+}
+
+impl CircuitConfig {
+    pub(crate) fn describe(&self) -> String {
+        match self {
+            Self::Condition(None) => String::new(),
+            Self::Condition(Some(condition)) => {
+                format!("Enabled when: {}<br>", condition.describe())
+            }
+            Self::Constant(_) => String::new(),
+            Self::Arithmetic(config) => format!(
+                "{} {} {} -> {}<br>",
+                config.input.name(),
+                config.op.symbol(),
+                config.constant,
+                config.output.name()
+            ),
+            Self::Decider(config) => format!(
+                "If {} then {} = {}<br>",
+                config.condition.describe(),
+                config.output.name(),
+                if config.copy_count {
+                    "input count"
+                } else {
+                    "1"
+                }
+            ),
+        }
+    }
+}
+
+/// A terminal of a structure that signal wires connect to.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub(crate) struct CircuitTerminal {
+    pub id: StructureId,
+    /// Whether it is the output terminal of a combinator. Other structures have a single
+    /// terminal that both sends and receives signals.
+    pub output: bool,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub(crate) struct SignalWire(pub WireColor, pub CircuitTerminal, pub CircuitTerminal);
+
+impl SignalWire {
+    pub(crate) fn connects(
+        &self,
+        color: WireColor,
+        a: CircuitTerminal,
+        b: CircuitTerminal,
+    ) -> bool {
+        self.0 == color && (self.1 == a && self.2 == b || self.1 == b && self.2 == a)
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct CircuitNetwork {
+    pub color: WireColor,
+    pub terminals: HashSet<CircuitTerminal>,
+    /// Sum of the signals sent to the network in the last tick
+    pub signals: Signals,
+}
+
+pub(crate) fn build_circuit_networks(wires: &[SignalWire]) -> Vec<CircuitNetwork> {
+    let mut left_wires = wires.iter().collect::<HashSet<_>>();
+    let mut ret = vec![];
+
+    while let Some(first) = left_wires.iter().next().copied() {
+        left_wires.remove(first);
+        let mut terminals = HashSet::new();
+        terminals.insert(first.1);
+        terminals.insert(first.2);
+        let mut expand_list = vec![first.1, first.2];
+        while let Some(terminal) = expand_list.pop() {
+            while let Some(wire) = left_wires
+                .iter()
+                .find(|w| w.0 == first.0 && (w.1 == terminal || w.2 == terminal))
+                .copied()
+            {
+                left_wires.remove(wire);
+                for end in [wire.1, wire.2] {
+                    if terminals.insert(end) {
+                        expand_list.push(end);
+                    }
+                }
+            }
+        }
+        ret.push(CircuitNetwork {
+            color: first.0,
+            terminals,
+            signals: Signals::new(),
+        });
+    }
+    ret
+}
+
+/// Sum up the signals sent to each network and deliver them to the structures connected to it.
+///
+/// It runs after all structures have processed the tick, so that the signals reflect the
+/// latest state. Combinators compute their output from the input delivered here, which reaches
+/// the networks in the next tick.
+pub(crate) fn update_circuit_networks(
+    networks: &mut [CircuitNetwork],
+    structures: &mut StructureDynIter,
+) {
+    for network in networks.iter_mut() {
+        let mut signals = Signals::new();
+        for terminal in &network.terminals {
+            let Some(structure) = structures.get(terminal.id) else {
+                continue;
+            };
+            if terminal.output != structure.circuit_output_terminal().is_some() {
+                continue;
+            }
+            if let Some(output) = structure.circuit_signals() {
+                add_signals(&mut signals, &output);
+            }
+        }
+        network.signals = signals;
+    }
+
+    let ids: Vec<_> = structures
+        .dyn_iter_id()
+        .filter(|(_, s)| s.circuit_connectable())
+        .map(|(id, _)| id)
+        .collect();
+    for id in ids {
+        let input = CircuitTerminal { id, output: false };
+        let mut connected = networks
+            .iter()
+            .filter(|network| network.terminals.contains(&input))
+            .peekable();
+        let signals = if connected.peek().is_some() {
+            let mut signals = Signals::new();
+            for network in connected {
+                add_signals(&mut signals, &network.signals);
+            }
+            Some(signals)
+        } else {
+            None
+        };
+        if let Some(structure) = structures.get_mut(id) {
+            structure.circuit_input(signals.as_ref());
+        }
+    }
+}
+
+#[test]
+fn test_circuit() {
+    let plate = SignalId::Item(ItemType::IronPlate);
+    let gear = SignalId::Item(ItemType::Gear);
+    let signals = hash_map!(plate => 50);
+    let condition = CircuitCondition {
+        signal: plate,
+        comparator: Comparator::Less,
+        constant: 100,
+    };
+    assert!(condition.evaluate(&signals));
+    assert!(!CircuitCondition {
+        signal: gear,
+        comparator: Comparator::Greater,
+        constant: 0,
+    }
+    .evaluate(&signals));
+
+    let arithmetic = ArithmeticConfig {
+        input: plate,
+        op: ArithmeticOp::Div,
+        constant: 0,
+        output: gear,
+    };
+    assert_eq!(arithmetic.evaluate(&signals), hash_map!(gear => 0));
+
+    let decider = DeciderConfig {
+        condition,
+        output: plate,
+        copy_count: true,
+    };
+    assert_eq!(decider.evaluate(&signals), hash_map!(plate => 50));
+
+    let terminal = |id| CircuitTerminal {
+        id: StructureId { id, gen: 0 },
+        output: false,
+    };
+    let networks = build_circuit_networks(&[
+        SignalWire(WireColor::Red, terminal(0), terminal(1)),
+        SignalWire(WireColor::Red, terminal(1), terminal(2)),
+        SignalWire(WireColor::Green, terminal(1), terminal(2)),
+        SignalWire(WireColor::Red, terminal(3), terminal(4)),
+    ]);
+    assert_eq!(networks.len(), 3);
+    assert!(networks
+        .iter()
+        .any(|network| network.color == WireColor::Red && network.terminals.len() == 3));
+
+    assert_eq!(
+        serde_json::to_value(plate).unwrap(),
+        serde_json::json!("Iron Plate")
+    );
+}
@@ -0,0 +1,347 @@
+use super::{
+    circuit::{
+        describe_signals, ArithmeticConfig, ArithmeticOp, CircuitCondition, CircuitConfig,
+        Comparator, DeciderConfig, SignalId, Signals,
+    },
+    draw_direction_arrow,
+    gl::{draw_direction_arrow_gl, draw_flat_rect_gl},
+    items::ItemType,
+    structure::{BoundingBox, RotateErr, Size, Structure, StructureDynIter},
+    FactorishState, Position, Rotation, TILE_SIZE,
+};
+use serde::{Deserialize, Serialize};
+use std::mem::discriminant;
+use wasm_bindgen::prelude::*;
+use web_sys::{CanvasRenderingContext2d, WebGlRenderingContext as GL};
+
+const BODY_COLOR: [f32; 4] = [0.3, 0.3, 0.35, 1.];
+
+/// Sends the configured signals to the circuit networks connected to it.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct ConstantCombinator {
+    position: Position,
+    signals: Vec<(SignalId, i32)>,
+}
+
+impl ConstantCombinator {
+    pub(crate) fn new(position: &Position) -> Self {
+        Self {
+            position: *position,
+            signals: vec![],
+        }
+    }
+}
+
+impl Structure for ConstantCombinator {
+    fn name(&self) -> &str {
+        "Constant Combinator"
+    }
+
+    fn position(&self) -> &Position {
+        &self.position
+    }
+
+    fn draw(
+        &self,
+        _state: &FactorishState,
+        context: &CanvasRenderingContext2d,
+        depth: i32,
+        _is_toolbar: bool,
+    ) -> Result<(), JsValue> {
+        if depth != 0 {
+            return Ok(());
+        }
+        let (x, y) = (
+            self.position.x as f64 * TILE_SIZE,
+            self.position.y as f64 * TILE_SIZE,
+        );
+        context.set_fill_style(&js_str!("#4f4f5f"));
+        context.fill_rect(x + 2., y + 2., TILE_SIZE - 4., TILE_SIZE - 4.);
+        context.set_fill_style(&js_str!("#df3f3f"));
+        context.fill_rect(x + 10., y + 10., TILE_SIZE - 20., TILE_SIZE - 20.);
+        Ok(())
+    }
+
+    fn draw_gl(
+        &self,
+        state: &FactorishState,
+        gl: &GL,
+        depth: i32,
+        is_ghost: bool,
+    ) -> Result<(), JsValue> {
+        if depth != 0 {
+            return Ok(());
+        }
+        let (x, y) = (
+            self.position.x as f32 + state.viewport.x as f32,
+            self.position.y as f32 + state.viewport.y as f32,
+        );
+        let alpha = if is_ghost { 0.5 } else { 1. };
+        let [r, g, b, _] = BODY_COLOR;
+        draw_flat_rect_gl(state, gl, (x + 0.05, y + 0.05, 0.9, 0.9), &[r, g, b, alpha])?;
+        draw_flat_rect_gl(
+            state,
+            gl,
+            (x + 0.3, y + 0.3, 0.4, 0.4),
+            &[0.9, 0.25, 0.25, alpha],
+        )?;
+        Ok(())
+    }
+
+    fn desc(&self, _state: &FactorishState) -> String {
+        self.circuit_signals()
+            .map(|signals| format!("Output:<br>{}", describe_signals(&signals)))
+            .unwrap_or_default()
+    }
+
+    fn circuit_connectable(&self) -> bool {
+        true
+    }
+
+    fn circuit_signals(&self) -> Option<Signals> {
+        let mut signals = Signals::new();
+        for (signal, value) in &self.signals {
+            let entry = signals.entry(*signal).or_default();
+            *entry = entry.wrapping_add(*value);
+        }
+        Some(signals)
+    }
+
+    fn circuit_config(&self) -> Option<CircuitConfig> {
+        Some(CircuitConfig::Constant(self.signals.clone()))
+    }
+
+    fn set_circuit_config(&mut self, config: CircuitConfig) -> Result<(), JsValue> {
+        match config {
+            CircuitConfig::Constant(signals) => {
+                self.signals = signals;
+                Ok(())
+            }
+            _ => js_err!("Wrong circuit settings for a constant combinator"),
+        }
+    }
+
+    crate::serialize_impl!();
+}
+
+/// An arithmetic or decider combinator. It takes 2 tiles; the input terminal is on the back
+/// tile and the output terminal is on the front tile in the direction of the rotation.
+/// The result is sent to the output networks in the tick after the input is received.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct Combinator {
+    position: Position,
+    rotation: Rotation,
+    config: CircuitConfig,
+    #[serde(skip)]
+    output: Signals,
+}
+
+impl Combinator {
+    pub(crate) fn new_arithmetic(position: &Position, rotation: Rotation) -> Self {
+        let plate = SignalId::Item(ItemType::IronPlate);
+        Self::new(
+            position,
+            rotation,
+            CircuitConfig::Arithmetic(ArithmeticConfig {
+                input: plate,
+                op: ArithmeticOp::Mul,
+                constant: 1,
+                output: plate,
+            }),
+        )
+    }
+
+    pub(crate) fn new_decider(position: &Position, rotation: Rotation) -> Self {
+        let plate = SignalId::Item(ItemType::IronPlate);
+        Self::new(
+            position,
+            rotation,
+            CircuitConfig::Decider(DeciderConfig {
+                condition: CircuitCondition {
+                    signal: plate,
+                    comparator: Comparator::Greater,
+                    constant: 0,
+                },
+                output: plate,
+                copy_count: true,
+            }),
+        )
+    }
+
+    fn new(position: &Position, rotation: Rotation, config: CircuitConfig) -> Self {
+        Self {
+            position: *position,
+            rotation,
+            config,
+            output: Signals::new(),
+        }
+    }
+
+    fn output_position(&self) -> Position {
+        self.position.add(self.rotation.delta())
+    }
+
+    fn color(&self) -> [f32; 3] {
+        match self.config {
+            CircuitConfig::Decider(_) => [0.9, 0.75, 0.2],
+            _ => [0.3, 0.5, 0.9],
+        }
+    }
+}
+
+impl Structure for Combinator {
+    fn name(&self) -> &str {
+        match self.config {
+            CircuitConfig::Decider(_) => "Decider Combinator",
+            _ => "Arithmetic Combinator",
+        }
+    }
+
+    fn position(&self) -> &Position {
+        &self.position
+    }
+
+    fn size(&self) -> Size {
+        if self.rotation.is_horizontal() {
+            Size::new(2, 1)
+        } else {
+            Size::new(1, 2)
+        }
+    }
+
+    fn bounding_box(&self) -> BoundingBox {
+        let output = self.output_position();
+        BoundingBox {
+            x0: self.position.x.min(output.x),
+            y0: self.position.y.min(output.y),
+            x1: self.position.x.max(output.x) + 1,
+            y1: self.position.y.max(output.y) + 1,
+        }
+    }
+
+    fn draw(
+        &self,
+        state: &FactorishState,
+        context: &CanvasRenderingContext2d,
+        depth: i32,
+        is_toolbar: bool,
+    ) -> Result<(), JsValue> {
+        let (x, y) = (
+            self.position.x as f64 * TILE_SIZE,
+            self.position.y as f64 * TILE_SIZE,
+        );
+        match depth {
+            0 => {
+                let [r, g, b] = self.color();
+                context.set_fill_style(&js_str!("#4f4f5f"));
+                context.fill_rect(x + 2., y + 2., TILE_SIZE - 4., TILE_SIZE - 4.);
+                context.set_fill_style(&js_str!("rgb({}, {}, {})", r * 255., g * 255., b * 255.));
+                context.fill_rect(x + 10., y + 10., TILE_SIZE - 20., TILE_SIZE - 20.);
+            }
+            2 if !is_toolbar => draw_direction_arrow((x, y), &self.rotation, state, context)?,
+            _ => (),
+        }
+        Ok(())
+    }
+
+    fn draw_gl(
+        &self,
+        state: &FactorishState,
+        gl: &GL,
+        depth: i32,
+        is_ghost: bool,
+    ) -> Result<(), JsValue> {
+        let (x, y) = (
+            self.position.x as f32 + state.viewport.x as f32,
+            self.position.y as f32 + state.viewport.y as f32,
+        );
+        let alpha = if is_ghost { 0.5 } else { 1. };
+        match depth {
+            0 => {
+                let bb = self.bounding_box();
+                let [r, g, b, _] = BODY_COLOR;
+                draw_flat_rect_gl(
+                    state,
+                    gl,
+                    (
+                        bb.x0 as f32 + state.viewport.x as f32 + 0.05,
+                        bb.y0 as f32 + state.viewport.y as f32 + 0.05,
+                        (bb.x1 - bb.x0) as f32 - 0.1,
+                        (bb.y1 - bb.y0) as f32 - 0.1,
+                    ),
+                    &[r, g, b, alpha],
+                )?;
+                let output = self.output_position();
+                let [r, g, b] = self.color();
+                draw_flat_rect_gl(
+                    state,
+                    gl,
+                    (
+                        output.x as f32 + state.viewport.x as f32 + 0.3,
+                        output.y as f32 + state.viewport.y as f32 + 0.3,
+                        0.4,
+                        0.4,
+                    ),
+                    &[r, g, b, alpha],
+                )?;
+            }
+            2 => draw_direction_arrow_gl((x, y), &self.rotation, state, gl)?,
+            _ => (),
+        }
+        Ok(())
+    }
+
+    fn desc(&self, _state: &FactorishState) -> String {
+        format!("Output:<br>{}", describe_signals(&self.output))
+    }
+
+    fn rotate(
+        &mut self,
+        _state: &mut FactorishState,
+        _others: &StructureDynIter,
+    ) -> Result<(), RotateErr> {
+        self.rotation = self.rotation.next();
+        Ok(())
+    }
+
+    fn set_rotation(&mut self, rotation: &Rotation) -> Result<(), ()> {
+        self.rotation = *rotation;
+        Ok(())
+    }
+
+    fn circuit_connectable(&self) -> bool {
+        true
+    }
+
+    fn circuit_output_terminal(&self) -> Option<Position> {
+        Some(self.output_position())
+    }
+
+    fn circuit_signals(&self) -> Option<Signals> {
+        Some(self.output.clone())
+    }
+
+    fn circuit_input(&mut self, signals: Option<&Signals>) {
+        let empty = Signals::new();
+        let signals = signals.unwrap_or(&empty);
+        self.output = match &self.config {
+            CircuitConfig::Arithmetic(config) => config.evaluate(signals),
+            CircuitConfig::Decider(config) => config.evaluate(signals),
+            _ => Signals::new(),
+        };
+    }
+
+    fn circuit_config(&self) -> Option<CircuitConfig> {
+        Some(self.config.clone())
+    }
+
+    fn set_circuit_config(&mut self, config: CircuitConfig) -> Result<(), JsValue> {
+        if discriminant(&config) != discriminant(&self.config) {
+            return js_err!("Wrong circuit settings for {}", self.name());
+        }
+        self.config = config;
+        Ok(())
+    }
+
+    crate::serialize_impl!();
+}
@@ -0,0 +1,271 @@
+use super::{
+    items::{item_to_str, str_to_item},
+    structure::{Structure, StructureId},
+    FactorishState, InventoryTrait, ItemType, Position, PowerWire, MAX_WIRES,
+};
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+use web_sys::WebGlRenderingContext as GL;
+
+/// A structure planned to be built, drawn translucent on the map. It is built when the player
+/// has the item within reach, or when a construction robot brings the item to it.
+pub(crate) struct Ghost {
+    structure: Box<dyn Structure>,
+    /// Whether a construction robot has brought the item to build it
+    pub(crate) delivered: bool,
+    /// Positions of the structures to connect with power wires when it is built, e.g. from
+    /// a blueprint. The ones still planned as ghosts connect when they are built in turn.
+    pub(crate) wires: Vec<Position>,
+}
+
+impl Ghost {
+    pub(crate) fn position(&self) -> Position {
+        *self.structure.position()
+    }
+
+    pub(crate) fn item(&self) -> Option<ItemType> {
+        str_to_item(self.structure.name())
+    }
+}
+
+/// An entry of the construction queue for the JavaScript side
+#[derive(Serialize)]
+pub(crate) struct GhostSerial {
+    item: String,
+    position: Position,
+    delivered: bool,
+}
+
+impl From<&Ghost> for GhostSerial {
+    fn from(ghost: &Ghost) -> Self {
+        Self {
+            item: ghost
+                .item()
+                .map(|item| item_to_str(&item))
+                .unwrap_or_default(),
+            position: ghost.position(),
+            delivered: ghost.delivered,
+        }
+    }
+}
+
+impl FactorishState {
+    /// Add a ghost to the end of the construction queue.
+    /// @returns false if the site is occupied by a structure or another ghost.
+    pub(super) fn add_ghost(&mut self, structure: Box<dyn Structure>) -> bool {
+        let bbox = structure.bounding_box();
+        if !self.is_site_free(structure.as_ref())
+            || self
+                .ghosts
+                .iter()
+                .any(|ghost| ghost.structure.bounding_box().intersects(&bbox))
+        {
+            return false;
+        }
+        self.ghosts.push(Ghost {
+            structure,
+            delivered: false,
+            wires: vec![],
+        });
+        true
+    }
+
+    /// Remove the ghost at the index in the construction queue. The item brought by a robot
+    /// goes to the player.
+    /// @returns true if a ghost is removed.
+    pub(super) fn cancel_ghost(&mut self, index: usize) -> bool {
+        if self.ghosts.len() <= index {
+            return false;
+        }
+        let ghost = self.ghosts.remove(index);
+        if let Some(item) = ghost.item().filter(|_| ghost.delivered) {
+            self.player.add_item(&item, 1);
+        }
+        true
+    }
+
+    /// Remove the ghost at the position on the map.
+    /// @returns true if a ghost is removed.
+    pub(super) fn cancel_ghost_at(&mut self, position: &Position) -> bool {
+        self.ghosts
+            .iter()
+            .position(|ghost| {
+                ghost
+                    .structure
+                    .bounding_box()
+                    .intersects_position(*position)
+            })
+            .is_some_and(|index| self.cancel_ghost(index))
+    }
+
+    /// Remove the ghosts under a structure being built. The items brought by robots go to
+    /// the player.
+    pub(super) fn clear_ghosts_under(&mut self, structure: &dyn Structure) {
+        let bbox = structure.bounding_box();
+        let player = &mut self.player;
+        self.ghosts.retain(|ghost| {
+            if !ghost.structure.bounding_box().intersects(&bbox) {
+                return true;
+            }
+            if let Some(item) = ghost.item().filter(|_| ghost.delivered) {
+                player.add_item(&item, 1);
+            }
+            false
+        });
+    }
+
+    /// Build the ghosts whose items are brought by robots, and the first one in the queue
+    /// that the player can build with an item in the inventory.
+    /// @returns true if the player's inventory has changed.
+    pub(super) fn construction_step(&mut self) -> Result<bool, JsValue> {
+        let mut player_built = false;
+        let mut i = 0;
+        while i < self.ghosts.len() {
+            let ghost = &self.ghosts[i];
+            let Some(item) = ghost.item() else {
+                self.ghosts.remove(i);
+                continue;
+            };
+            let structure = ghost.structure.as_ref();
+            let by_player = !ghost.delivered
+                && !player_built
+                && 0 < self.player.inventory.count_item(&item)
+                && self.in_reach(structure.position());
+            if !(ghost.delivered || by_player)
+                || !self.is_site_free(structure)
+                || self.blocked_by_character(structure)
+            {
+                i += 1;
+                continue;
+            }
+            let ghost = self.ghosts.remove(i);
+            if by_player {
+                self.player.inventory.remove_item(&item);
+                player_built = true;
+            }
+            let id = self.place_structure(ghost.structure)?;
+            self.connect_ghost_wires(id, &ghost.wires);
+        }
+        Ok(player_built)
+    }
+
+    /// Connect the power wires of a ghost just built to the structures at the positions.
+    fn connect_ghost_wires(&mut self, id: StructureId, positions: &[Position]) {
+        let mut connected = false;
+        for position in positions {
+            let Some((other_id, _)) = self
+                .structure_id_iter()
+                .find(|(_, s)| s.position() == position && (s.power_sink() || s.power_source()))
+            else {
+                continue;
+            };
+            if self
+                .power_wires
+                .iter()
+                .any(|w| *w == PowerWire(id, other_id) || *w == PowerWire(other_id, id))
+                || MAX_WIRES <= self.wire_count(id)
+                || MAX_WIRES <= self.wire_count(other_id)
+            {
+                continue;
+            }
+            self.power_wires.push(PowerWire(id, other_id));
+            connected = true;
+        }
+        if connected {
+            self.rebuild_power_networks();
+        }
+    }
+
+    pub(super) fn serialize_ghosts(&self) -> Result<serde_json::Value, JsValue> {
+        self.ghosts
+            .iter()
+            .map(|ghost| {
+                Ok(serde_json::json!({
+                    "type": ghost.structure.name(),
+                    "payload": ghost
+                        .structure
+                        .serialize()
+                        .map_err(|e| js_str!("Serialize error: {}", e))?,
+                    "delivered": ghost.delivered,
+                    "wires": ghost.wires,
+                }))
+            })
+            .collect()
+    }
+
+    pub(super) fn deserialize_ghosts(mut value: serde_json::Value) -> Result<Vec<Ghost>, JsValue> {
+        value
+            .as_array_mut()
+            .ok_or_else(|| js_str!("ghosts in saved data is not an array"))?
+            .iter_mut()
+            .map(|value| {
+                Ok(Ghost {
+                    delivered: value
+                        .get("delivered")
+                        .and_then(|v| v.as_bool())
+                        .unwrap_or(false),
+                    wires: value
+                        .get_mut("wires")
+                        .map(|v| serde_json::from_value(v.take()))
+                        .transpose()
+                        .map_err(|e| js_str!("ghost wires deserialization error: {}", e))?
+                        .unwrap_or_default(),
+                    structure: Self::structure_from_json(value)?,
+                })
+            })
+            .collect()
+    }
+
+    pub(super) fn render_ghosts_gl(&self, gl: &GL) -> Result<(), JsValue> {
+        for depth in 0..3 {
+            for ghost in &self.ghosts {
+                ghost.structure.draw_gl(self, gl, depth, true)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_clear_ghosts_under() {
+    use super::chest::Chest;
+
+    let mut state = FactorishState::new_test();
+    let count = |state: &FactorishState| state.player.inventory.count_item(&ItemType::Chest);
+    let before = count(&state);
+    assert!(state.add_ghost(Box::new(Chest::new(&Position::new(1, 1)))));
+    assert!(state.add_ghost(Box::new(Chest::new(&Position::new(2, 1)))));
+    assert!(!state.add_ghost(Box::new(Chest::new(&Position::new(2, 1)))));
+    state.ghosts[0].delivered = true;
+
+    // The item delivered to a ghost is returned when another structure is built over it
+    state.clear_ghosts_under(&Chest::new(&Position::new(1, 1)));
+    assert_eq!(state.ghosts.len(), 1);
+    assert_eq!(count(&state), before + 1);
+    state.clear_ghosts_under(&Chest::new(&Position::new(2, 1)));
+    assert!(state.ghosts.is_empty());
+    assert_eq!(count(&state), before + 1);
+}
+
+#[test]
+fn test_ghost_wires() {
+    use super::{elect_pole::ElectPole, lamp::Lamp};
+
+    let mut state = FactorishState::new_test();
+    let lamp = state
+        .place_structure(Box::new(Lamp::new(&Position::new(20, 0))))
+        .unwrap();
+    assert!(state.add_ghost(Box::new(ElectPole::new(&Position::new(0, 0)))));
+    state.ghosts[0].delivered = true;
+    state.ghosts[0].wires = vec![Position::new(20, 0), Position::new(10, 0)];
+    state.construction_step().unwrap();
+
+    // The wire to the lamp is connected, but not the one to the empty tile
+    assert!(state.ghosts.is_empty());
+    let pole = state
+        .structure_id_iter()
+        .find(|(_, s)| s.name() == "Electric Pole")
+        .map(|(id, _)| id)
+        .unwrap();
+    assert_eq!(state.power_wires, [PowerWire(pole, lamp)]);
+}
@@ -0,0 +1,259 @@
+use super::{
+    inventory::{Inventory, InventoryTrait},
+    items::item_to_str,
+    research::TechnologyTag,
+    Recipe,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// Ratio of the hand crafting speed to the speed of an assembler
+const HAND_CRAFTING_SPEED: f64 = 1.;
+/// Maximum depth of intermediate ingredients crafted automatically, to stop at recipe cycles
+const MAX_CRAFT_DEPTH: usize = 8;
+
+/// A craft in the player's hand crafting queue. The ingredients are taken from the player's
+/// inventory when the craft starts, and returned if it is cancelled in progress.
+#[derive(Serialize, Deserialize, Clone)]
+pub(crate) struct CraftJob {
+    recipe: Recipe,
+    progress: Option<f64>,
+    /// Whether it was queued automatically to make an ingredient of a later craft
+    intermediate: bool,
+}
+
+#[derive(Serialize)]
+pub(crate) struct CraftJobSerial {
+    output: HashMap<String, usize>,
+    progress: f64,
+    intermediate: bool,
+}
+
+impl From<&CraftJob> for CraftJobSerial {
+    fn from(job: &CraftJob) -> Self {
+        Self {
+            output: job
+                .recipe
+                .output
+                .iter()
+                .map(|(item, count)| (item_to_str(item), *count))
+                .collect(),
+            progress: job.progress.unwrap_or(0.),
+            intermediate: job.intermediate,
+        }
+    }
+}
+
+/// Whether the player can craft the recipe by hand. Recipes with fluids need a machine.
+pub(crate) fn is_hand_craftable(recipe: &Recipe, unlocked: &HashSet<TechnologyTag>) -> bool {
+    recipe.input_fluid.is_none()
+        && recipe.output_fluid.is_none()
+        && !recipe.input.is_empty()
+        && recipe
+            .requires_technology
+            .iter()
+            .all(|tech| unlocked.contains(tech))
+}
+
+/// Plan `count` crafts of `recipe` with the items in `inventory`. Missing ingredients are crafted
+/// from the other `recipes` first. The planned jobs are appended to `jobs` in the order to craft,
+/// and `inventory` is updated as if they were all crafted.
+/// @returns false if the ingredients are not enough even with the intermediate crafts.
+pub(crate) fn plan_crafts(
+    recipes: &[&Recipe],
+    recipe: &Recipe,
+    count: usize,
+    inventory: &mut Inventory,
+    jobs: &mut Vec<CraftJob>,
+    intermediate: bool,
+    depth: usize,
+) -> bool {
+    for _ in 0..count {
+        for (item, amount) in &recipe.input {
+            let lacking = amount.saturating_sub(inventory.count_item(item));
+            if 0 < lacking {
+                if MAX_CRAFT_DEPTH <= depth {
+                    return false;
+                }
+                let Some(ingredient_recipe) = recipes
+                    .iter()
+                    .find(|recipe| recipe.output.contains_key(item))
+                else {
+                    return false;
+                };
+                let per_craft = ingredient_recipe.output[item];
+                let crafts = lacking.div_ceil(per_craft);
+                if !plan_crafts(
+                    recipes,
+                    ingredient_recipe,
+                    crafts,
+                    inventory,
+                    jobs,
+                    true,
+                    depth + 1,
+                ) {
+                    return false;
+                }
+            }
+            inventory.remove_items(item, *amount);
+        }
+        for (item, amount) in &recipe.output {
+            inventory.add_items(item, *amount);
+        }
+        jobs.push(CraftJob {
+            recipe: recipe.clone(),
+            progress: None,
+            intermediate,
+        });
+    }
+    true
+}
+
+/// The items expected in the inventory after all the crafts in the queue are done,
+/// so that new crafts are planned only with the items left for them.
+pub(crate) fn inventory_after_queue(
+    queue: &VecDeque<CraftJob>,
+    inventory: &Inventory,
+) -> Inventory {
+    let mut ret = inventory.clone();
+    for job in queue {
+        if job.progress.is_none() {
+            for (item, count) in &job.recipe.input {
+                ret.remove_items(item, *count);
+            }
+        }
+        for (item, count) in &job.recipe.output {
+            ret.add_items(item, *count);
+        }
+    }
+    ret
+}
+
+/// Advance the craft at the head of the queue by a tick. A craft whose ingredients are gone
+/// by the time it starts is dropped.
+/// @returns true if the player's inventory has changed.
+pub(crate) fn proceed_crafting(queue: &mut VecDeque<CraftJob>, inventory: &mut Inventory) -> bool {
+    let Some(job) = queue.front_mut() else {
+        return false;
+    };
+    let mut changed = false;
+    if job.progress.is_none() {
+        if !job
+            .recipe
+            .input
+            .iter()
+            .all(|(item, count)| *count <= inventory.count_item(item))
+        {
+            queue.pop_front();
+            return false;
+        }
+        for (item, count) in &job.recipe.input {
+            inventory.remove_items(item, *count);
+        }
+        changed = true;
+    }
+    let progress = job.progress.unwrap_or(0.) + HAND_CRAFTING_SPEED / job.recipe.recipe_time / 3.;
+    if 1. <= progress {
+        for (item, count) in &job.recipe.output {
+            inventory.add_items(item, *count);
+        }
+        queue.pop_front();
+        return true;
+    }
+    job.progress = Some(progress);
+    changed
+}
+
+/// Remove a craft from the queue, returning the ingredients if it has started.
+/// The intermediate crafts queued right before it to make its ingredients are removed with it.
+pub(crate) fn cancel_craft(
+    queue: &mut VecDeque<CraftJob>,
+    inventory: &mut Inventory,
+    position: usize,
+) -> bool {
+    let Some(job) = queue.remove(position) else {
+        return false;
+    };
+    let mut start = position;
+    if !job.intermediate {
+        while 0 < start && queue[start - 1].intermediate {
+            start -= 1;
+        }
+    }
+    for job in queue.drain(start..position).chain(std::iter::once(job)) {
+        if job.progress.is_some() {
+            inventory.merge(job.recipe.input);
+        }
+    }
+    true
+}
+
+#[test]
+fn test_plan_crafts() {
+    use super::items::ItemType;
+
+    let gear = Recipe::new(
+        hash_map!(ItemType::IronPlate => 2),
+        hash_map!(ItemType::Gear => 1),
+        20.,
+        20.,
+    );
+    let inserter = Recipe::new(
+        hash_map!(ItemType::IronPlate => 1, ItemType::Gear => 1),
+        hash_map!(ItemType::Inserter => 1),
+        20.,
+        20.,
+    );
+    let recipes = [&gear, &inserter];
+
+    let mut inventory = hash_map!(ItemType::IronPlate => 7, ItemType::Gear => 1);
+    let mut jobs = vec![];
+    assert!(plan_crafts(
+        &recipes,
+        &inserter,
+        2,
+        &mut inventory,
+        &mut jobs,
+        false,
+        0
+    ));
+    // The first inserter uses the gear in the inventory and the second one crafts a gear
+    assert_eq!(
+        jobs.iter().map(|job| job.intermediate).collect::<Vec<_>>(),
+        [false, true, false]
+    );
+    assert_eq!(inventory.count_item(&ItemType::IronPlate), 3);
+    assert_eq!(inventory.count_item(&ItemType::Inserter), 2);
+
+    let mut inventory = hash_map!(ItemType::IronPlate => 2);
+    assert!(!plan_crafts(
+        &recipes,
+        &inserter,
+        1,
+        &mut inventory,
+        &mut vec![],
+        false,
+        0
+    ));
+
+    // Cancelling the second inserter cancels the gear crafted for it, returning the plates
+    // of the gear in progress
+    let mut queue: VecDeque<_> = jobs.iter().skip(1).cloned().collect();
+    let mut inventory = hash_map!(ItemType::IronPlate => 7);
+    assert!(proceed_crafting(&mut queue, &mut inventory));
+    assert_eq!(inventory.count_item(&ItemType::IronPlate), 5);
+    assert!(cancel_craft(&mut queue, &mut inventory, 1));
+    assert!(queue.is_empty());
+    assert_eq!(inventory.count_item(&ItemType::IronPlate), 7);
+
+    let mut queue: VecDeque<_> = jobs.into();
+    let mut inventory = hash_map!(ItemType::IronPlate => 7, ItemType::Gear => 1);
+    let mut ticks = 0;
+    while !queue.is_empty() && ticks < 1000 {
+        proceed_crafting(&mut queue, &mut inventory);
+        ticks += 1;
+    }
+    assert!(queue.is_empty());
+    assert_eq!(inventory.count_item(&ItemType::Inserter), 2);
+    assert_eq!(inventory.count_item(&ItemType::IronPlate), 3);
+}
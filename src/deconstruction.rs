@@ -0,0 +1,165 @@
+use super::{
+    gl::draw_flat_rect_gl,
+    items::str_to_item,
+    structure::{BoundingBox, StructureId},
+    FactorishState, ItemType,
+};
+use wasm_bindgen::prelude::*;
+use web_sys::WebGlRenderingContext as GL;
+
+impl FactorishState {
+    /// Mark the structures overlapping the area for deconstruction, only the ones made of
+    /// the `filter` item if given.
+    pub(super) fn mark_deconstruction(&mut self, area: &BoundingBox, filter: Option<ItemType>) {
+        let ids = self
+            .structure_id_iter()
+            .filter(|(_, s)| s.bounding_box().intersects(area))
+            .filter(|(_, s)| filter.is_none() || str_to_item(s.name()) == filter)
+            .map(|(id, _)| id)
+            .filter(|id| !self.deconstruction_marks.contains(id))
+            .collect::<Vec<_>>();
+        self.deconstruction_marks.extend(ids);
+    }
+
+    /// Remove the deconstruction marks from the structures overlapping the area.
+    pub(super) fn unmark_deconstruction(&mut self, area: &BoundingBox) {
+        let marks = std::mem::take(&mut self.deconstruction_marks);
+        self.deconstruction_marks = marks
+            .into_iter()
+            .filter(|id| {
+                self.find_structure_by_id(*id)
+                    .is_some_and(|s| !s.bounding_box().intersects(area))
+            })
+            .collect();
+    }
+
+    /// Harvest the first marked structure within reach, returning its contents to the player.
+    /// @returns true if the player's inventory has changed.
+    pub(super) fn deconstruction_step(&mut self) -> Result<bool, JsValue> {
+        let Some((index, id)) = self.next_deconstruction() else {
+            return Ok(false);
+        };
+        let Some(position) = self.find_structure_by_id(id).map(|s| *s.position()) else {
+            return Ok(false);
+        };
+        let harvested = self.harvest(&position, true, false)?;
+        if self.find_structure_by_id(id).is_some() {
+            // A train on the rail was picked up first, so the rail is left for the next tick
+            self.deconstruction_marks.insert(index, id);
+        }
+        Ok(harvested)
+    }
+
+    /// Take the first marked structure within reach out of the marks, with its index.
+    /// The marks left by the structures removed in other ways are dropped.
+    fn next_deconstruction(&mut self) -> Option<(usize, StructureId)> {
+        let marks = std::mem::take(&mut self.deconstruction_marks);
+        self.deconstruction_marks = marks
+            .into_iter()
+            .filter(|id| self.find_structure_by_id(*id).is_some())
+            .collect();
+        let index = self.deconstruction_marks.iter().position(|id| {
+            self.find_structure_by_id(*id)
+                .is_some_and(|s| self.in_reach(s.position()))
+        })?;
+        Some((index, self.deconstruction_marks.remove(index)))
+    }
+
+    pub(super) fn render_deconstruction_gl(&self, gl: &GL) -> Result<(), JsValue> {
+        for id in &self.deconstruction_marks {
+            let Some(structure) = self.find_structure_by_id(*id) else {
+                continue;
+            };
+            let bbox = structure.bounding_box();
+            draw_flat_rect_gl(
+                self,
+                gl,
+                (
+                    bbox.x0 as f32 + self.viewport.x as f32,
+                    bbox.y0 as f32 + self.viewport.y as f32,
+                    (bbox.x1 - bbox.x0) as f32,
+                    (bbox.y1 - bbox.y0) as f32,
+                ),
+                &[1., 0.1, 0.1, 0.35],
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_mark_deconstruction() {
+    use super::{chest::Chest, lamp::Lamp, Position};
+
+    let mut state = FactorishState::new_test();
+    let chest1 = state
+        .place_structure(Box::new(Chest::new(&Position::new(1, 1))))
+        .unwrap();
+    let chest2 = state
+        .place_structure(Box::new(Chest::new(&Position::new(3, 1))))
+        .unwrap();
+    state
+        .place_structure(Box::new(Lamp::new(&Position::new(2, 1))))
+        .unwrap();
+    let area = BoundingBox {
+        x0: 0,
+        y0: 0,
+        x1: 5,
+        y1: 3,
+    };
+
+    // Only the chests are marked, once each
+    state.mark_deconstruction(&area, Some(ItemType::Chest));
+    state.mark_deconstruction(&area, Some(ItemType::Chest));
+    assert_eq!(state.deconstruction_marks, [chest1, chest2]);
+
+    state.unmark_deconstruction(&BoundingBox {
+        x0: 0,
+        y0: 0,
+        x1: 2,
+        y1: 2,
+    });
+    assert_eq!(state.deconstruction_marks, [chest2]);
+}
+
+#[test]
+fn test_next_deconstruction() {
+    use super::{character::Character, chest::Chest, Position};
+
+    let mut state = FactorishState::new_test();
+    state.player.character = Some(Character::new(20.5, 20.5));
+    let far = state
+        .place_structure(Box::new(Chest::new(&Position::new(1, 1))))
+        .unwrap();
+    let near = state
+        .place_structure(Box::new(Chest::new(&Position::new(18, 20))))
+        .unwrap();
+    let removed = state
+        .place_structure(Box::new(Chest::new(&Position::new(20, 18))))
+        .unwrap();
+    state.mark_deconstruction(
+        &BoundingBox {
+            x0: 0,
+            y0: 0,
+            x1: 32,
+            y1: 32,
+        },
+        None,
+    );
+    assert_eq!(state.deconstruction_marks, [far, near, removed]);
+
+    // A structure built in place of a marked one does not inherit the mark
+    let entry = &mut state.structures[removed.id as usize];
+    entry.dynamic = None;
+    entry.gen += 1;
+    let rebuilt = state
+        .place_structure(Box::new(Chest::new(&Position::new(20, 18))))
+        .unwrap();
+    assert_eq!(rebuilt.id, removed.id);
+
+    // The structure within reach is taken first, and the stale mark is dropped
+    assert_eq!(state.next_deconstruction(), Some((1, near)));
+    assert_eq!(state.deconstruction_marks, [far]);
+    assert_eq!(state.next_deconstruction(), None);
+    assert_eq!(state.deconstruction_marks, [far]);
+}
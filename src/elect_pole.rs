@@ -3,7 +3,7 @@ use super::{
         assets::WIRE_SEGMENTS,
         utils::{enable_buffer, vertex_buffer_data, Flatten},
     },
-    structure::Structure,
+    structure::{BoundingBox, Size, Structure},
     FactorishState, Position, TILE_SIZE_F, WIRE_ATTACH_X, WIRE_ATTACH_Y, WIRE_HANG,
 };
 use cgmath::{Matrix3, Matrix4, Vector3};
@@ -14,42 +14,101 @@ use web_sys::{CanvasRenderingContext2d, WebGlRenderingContext as GL};
 
 const WIRE_WIDTH: f32 = 0.5;
 
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub(crate) enum PoleTier {
+    #[default]
+    Small,
+    Medium,
+    Big,
+    Substation,
+}
+
+impl PoleTier {
+    /// Maximum distance in tiles a wire can span from this pole
+    fn wire_reach(&self) -> u32 {
+        match self {
+            Self::Small => 5,
+            Self::Medium => 9,
+            Self::Big => 30,
+            Self::Substation => 18,
+        }
+    }
+
+    /// Tiles the supply area extends beyond the footprint
+    fn supply_range(&self) -> i32 {
+        match self {
+            Self::Small => 2,
+            Self::Medium => 3,
+            Self::Big => 1,
+            Self::Substation => 8,
+        }
+    }
+
+    fn size(&self) -> i32 {
+        match self {
+            Self::Small | Self::Medium => 1,
+            Self::Big | Self::Substation => 2,
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize)]
 pub(crate) struct ElectPole {
     position: Position,
     power: f64,
+    #[serde(default)]
+    tier: PoleTier,
 }
 
 impl ElectPole {
     pub(crate) fn new(position: &Position) -> Self {
+        Self::new_tier(position, PoleTier::Small)
+    }
+
+    pub(crate) fn new_tier(position: &Position, tier: PoleTier) -> Self {
         ElectPole {
             position: *position,
             power: 0.,
+            tier,
         }
     }
 }
 
 impl Structure for ElectPole {
     fn name(&self) -> &str {
-        "Electric Pole"
+        match self.tier {
+            PoleTier::Small => "Electric Pole",
+            PoleTier::Medium => "Medium Electric Pole",
+            PoleTier::Big => "Big Electric Pole",
+            PoleTier::Substation => "Substation",
+        }
     }
 
     fn position(&self) -> &Position {
         &self.position
     }
 
+    fn size(&self) -> Size {
+        Size::new(self.tier.size(), self.tier.size())
+    }
+
     fn draw(
         &self,
         state: &FactorishState,
         context: &CanvasRenderingContext2d,
         depth: i32,
-        _is_toolbar: bool,
+        is_toolbar: bool,
     ) -> Result<(), JsValue> {
         if depth != 0 {
             return Ok(());
         };
         let position = self.position;
         let (x, y) = (position.x as f64 * 32., position.y as f64 * 32.);
+        let size = if is_toolbar {
+            32.
+        } else {
+            32. * self.tier.size() as f64
+        };
         match state.image_elect_pole.as_ref() {
             Some(img) => {
                 // let (front, mid) = state.structures.split_at_mut(i);
@@ -59,7 +118,13 @@ impl Structure for ElectPole {
 
                 // We could split and chain like above, but we don't have to, as long as we deal with immutable
                 // references.
-                context.draw_image_with_image_bitmap(&img.bitmap, x, y)?;
+                context.draw_image_with_image_bitmap_and_dw_and_dh(
+                    &img.bitmap,
+                    x,
+                    y,
+                    size,
+                    size,
+                )?;
             }
             None => return Err(JsValue::from_str("elect-pole image not available")),
         }
@@ -101,7 +166,8 @@ impl Structure for ElectPole {
             false,
             (state.get_world_transform()?
                 * Matrix4::from_scale(2.)
-                * Matrix4::from_translation(Vector3::new(x, y, 0.)))
+                * Matrix4::from_translation(Vector3::new(x, y, 0.))
+                * Matrix4::from_scale(self.tier.size() as f32))
             .flatten(),
         );
         gl.draw_arrays(GL::TRIANGLE_FAN, 0, 4);
@@ -109,6 +175,18 @@ impl Structure for ElectPole {
         Ok(())
     }
 
+    fn desc(&self, state: &FactorishState) -> String {
+        let Some(network) = state.power_network_at(&self.position) else {
+            return "Not connected".to_string();
+        };
+        format!(
+            "Demand: {:.0}kW<br>Supply: {:.0}kW<br>Satisfaction: {:.0}%",
+            network.demand * 60.,
+            network.supply * 60.,
+            network.satisfaction() * 100.
+        )
+    }
+
     fn power_sink(&self) -> bool {
         true
     }
@@ -124,7 +202,19 @@ impl Structure for ElectPole {
     }
 
     fn wire_reach(&self) -> u32 {
-        5
+        self.tier.wire_reach()
+    }
+
+    fn circuit_connectable(&self) -> bool {
+        true
+    }
+
+    fn supply_area(&self) -> Option<BoundingBox> {
+        Some(self.bounding_box().expand(self.tier.supply_range()))
+    }
+
+    fn reset_for_blueprint(&mut self) {
+        self.power = 0.;
     }
 
     crate::serialize_impl!();
@@ -6,10 +6,13 @@ use super::{
     },
     inventory::InventoryType,
     items::item_to_str,
+    modules::{
+        get_module_power_cost, get_module_progress, module_effect, proceed_productivity,
+        ModuleEffect,
+    },
     serialize_impl,
     structure::{
-        default_add_inventory, get_powered_progress, Structure, StructureDynIter, StructureId,
-        RECIPE_CAPACITY_MULTIPLIER,
+        default_add_inventory, Structure, StructureDynIter, StructureId, RECIPE_CAPACITY_MULTIPLIER,
     },
     DropItem, FactorishState, FrameProcResult, Inventory, InventoryTrait, ItemType, Position,
     Recipe, TILE_SIZE,
@@ -24,6 +27,13 @@ pub(crate) struct ElectricFurnace {
     position: Position,
     input_inventory: Inventory,
     output_inventory: Inventory,
+    #[serde(default)]
+    module_inventory: Inventory,
+    /// Accumulated productivity bonus. When it reaches 1, an extra set of outputs is produced.
+    #[serde(default)]
+    bonus_progress: f64,
+    #[serde(skip)]
+    module_effect: ModuleEffect,
     progress: Option<f64>,
     power: f64,
     max_power: f64,
@@ -36,6 +46,9 @@ impl ElectricFurnace {
             position: *position,
             input_inventory: Inventory::new(),
             output_inventory: Inventory::new(),
+            module_inventory: Inventory::new(),
+            bonus_progress: 0.,
+            module_effect: ModuleEffect::default(),
             progress: None,
             power: 0.,
             max_power: 20.,
@@ -171,15 +184,17 @@ impl Structure for ElectricFurnace {
             },
             format!("Input Items: <br>{}", self.input_inventory.describe()),
             format!("Output Items: <br>{}", self.output_inventory.describe())
-        )
+        ) + &self.module_effect.describe()
     }
 
     fn frame_proc(
         &mut self,
         me: StructureId,
         state: &mut FactorishState,
-        structures: &mut StructureDynIter,
+        _structures: &mut StructureDynIter,
     ) -> Result<FrameProcResult, ()> {
+        self.module_effect = module_effect(state, me, &self.module_inventory);
+
         if self.recipe.is_none() {
             self.recipe = RECIPES
                 .iter()
@@ -192,26 +207,6 @@ impl Structure for ElectricFurnace {
                 .cloned();
         }
         if let Some(recipe) = &self.recipe {
-            if self.power < recipe.power_cost {
-                let mut accumulated = 0.;
-                if let Some(network) = state
-                    .power_networks
-                    .iter()
-                    .find(|network| network.sinks.contains(&me))
-                {
-                    for id in network.sources.iter() {
-                        if let Some(source) = structures.get_mut(*id) {
-                            let demand = self.max_power - self.power - accumulated;
-                            if let Some(energy) = source.power_outlet(demand) {
-                                accumulated += energy;
-                                // console_log!("draining {:?}kJ of energy with {:?} demand, from {:?}, accumulated {:?}", energy, demand, structure.name(), accumulated);
-                            }
-                        }
-                    }
-                }
-                self.power += accumulated;
-            }
-
             let mut ret = FrameProcResult::None;
 
             if self.progress.is_none() {
@@ -238,18 +233,24 @@ impl Structure for ElectricFurnace {
 
             if let Some(prev_progress) = self.progress {
                 // Proceed only if we have sufficient energy in the buffer.
-                let progress = get_powered_progress(self.power, prev_progress, recipe);
+                let effect = self.module_effect;
+                let progress = get_module_progress(self.power, prev_progress, recipe, &effect);
                 if 1. <= prev_progress + progress {
                     self.progress = None;
 
-                    // Produce outputs into inventory
+                    // Produce outputs into inventory, twice if the productivity bonus is earned
+                    let sets = if proceed_productivity(&mut self.bonus_progress, &effect) {
+                        2
+                    } else {
+                        1
+                    };
                     for output_item in &recipe.output {
-                        self.output_inventory.add_item(&output_item.0);
+                        self.output_inventory.add_items(output_item.0, sets);
                     }
                     return Ok(FrameProcResult::InventoryChanged(self.position));
                 } else {
                     self.progress = Some(prev_progress + progress);
-                    self.power -= progress * recipe.power_cost;
+                    self.power -= progress * get_module_power_cost(recipe, &effect);
                 }
             }
             return Ok(ret);
@@ -311,6 +312,7 @@ impl Structure for ElectricFurnace {
         Some(match invtype {
             InventoryType::Input => &self.input_inventory,
             InventoryType::Output => &self.output_inventory,
+            InventoryType::Module => &self.module_inventory,
             _ => return None,
         })
     }
@@ -319,6 +321,7 @@ impl Structure for ElectricFurnace {
         Some(match invtype {
             InventoryType::Input => &mut self.input_inventory,
             InventoryType::Output => &mut self.output_inventory,
+            InventoryType::Module => &mut self.module_inventory,
             _ => return None,
         })
     }
@@ -326,6 +329,7 @@ impl Structure for ElectricFurnace {
     fn destroy_inventory(&mut self) -> Inventory {
         let mut ret = std::mem::take(&mut self.input_inventory);
         ret.merge(std::mem::take(&mut self.output_inventory));
+        ret.merge(std::mem::take(&mut self.module_inventory));
         // Return the ingredients if it was in the middle of processing a recipe.
         if let Some(mut recipe) = self.recipe.take() {
             if self.progress.is_some() {
@@ -351,9 +355,28 @@ impl Structure for ElectricFurnace {
         self.progress
     }
 
+    fn module_slots(&self) -> usize {
+        2
+    }
+
     fn power_sink(&self) -> bool {
         true
     }
 
+    fn power_demand(&self) -> f64 {
+        match &self.recipe {
+            Some(recipe) if self.power < recipe.power_cost => self.max_power - self.power,
+            _ => 0.,
+        }
+    }
+
+    fn power_inlet(&mut self, energy: f64) {
+        self.power += energy;
+    }
+
+    fn reset_for_blueprint(&mut self) {
+        self.power = 0.;
+    }
+
     serialize_impl!();
 }
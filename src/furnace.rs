@@ -433,6 +433,10 @@ impl Structure for Furnace {
         self.progress
     }
 
+    fn reset_for_blueprint(&mut self) {
+        self.power = 0.;
+    }
+
     fn serialize(&self) -> serde_json::Result<serde_json::Value> {
         serde_json::to_value(self)
     }
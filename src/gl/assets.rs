@@ -105,6 +105,7 @@ pub(crate) struct Assets {
     pub textured_shader: Option<ShaderBundle>,
     pub textured_instancing_shader: Option<ShaderBundle>,
     pub textured_alpha_shader: Option<ShaderBundle>,
+    pub glow_shader: Option<ShaderBundle>,
 
     pub screen_buffer: Option<WebGlBuffer>,
     pub rect_buffer: Option<WebGlBuffer>,
@@ -117,7 +118,7 @@ pub(crate) struct Assets {
 
 impl Assets {
     pub fn new(context: &GL, image_assets: js_sys::Array) -> Result<Self, JsValue> {
-        let load_texture_local = |path| -> Result<WebGlTexture, JsValue> {
+        let load_texture_local = |path: &str| -> Result<WebGlTexture, JsValue> {
             if let Some(value) = image_assets.iter().find(|value| {
                 let array = js_sys::Array::from(value);
                 array.iter().next() == Some(JsValue::from_str(path))
@@ -142,7 +143,14 @@ impl Assets {
                 Err(JsValue::from_str("Couldn't find texture"))
             }
         };
+        Self::with_textures(load_texture_local)
+    }
 
+    /// Build the assets with the textures returned by `load_texture_local` for each asset name.
+    /// The shaders and buffers are created later in `prepare`.
+    pub(crate) fn with_textures(
+        load_texture_local: impl Fn(&str) -> Result<WebGlTexture, JsValue>,
+    ) -> Result<Self, JsValue> {
         Ok(Assets {
             instanced_arrays_ext: None,
             tex_dirt: load_texture_local("dirt")?,
@@ -197,6 +205,7 @@ impl Assets {
             textured_shader: None,
             textured_instancing_shader: None,
             textured_alpha_shader: None,
+            glow_shader: None,
             screen_buffer: None,
             rect_buffer: None,
             cursor_buffer: None,
@@ -441,6 +450,38 @@ impl Assets {
             0,
         );
 
+        let vert_shader = compile_shader(
+            &gl,
+            GL::VERTEX_SHADER,
+            r#"
+            attribute vec2 vertexData;
+            uniform mat4 transform;
+            varying vec2 coords;
+            void main() {
+                gl_Position = transform * vec4(vertexData.xy, 0., 1.0);
+                coords = vertexData.xy;
+            }
+        "#,
+        )?;
+        let frag_shader = compile_shader(
+            &gl,
+            GL::FRAGMENT_SHADER,
+            r#"
+            precision mediump float;
+
+            varying vec2 coords;
+
+            uniform vec4 color;
+
+            void main() {
+                float falloff = max(0., 1. - length(coords));
+                gl_FragColor = vec4(color.rgb, color.a * falloff * falloff);
+            }
+        "#,
+        )?;
+        let program = link_program(&gl, &vert_shader, &frag_shader)?;
+        self.glow_shader = Some(ShaderBundle::new(&gl, program));
+
         self.rect_buffer = Some(gl.create_buffer().ok_or("failed to create buffer")?);
         gl.bind_buffer(GL::ARRAY_BUFFER, self.rect_buffer.as_ref());
         let rect_vertices: [f32; 8] = [1., 1., -1., 1., -1., -1., 1., -1.];
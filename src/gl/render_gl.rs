@@ -4,10 +4,15 @@ use super::{
     utils::{enable_buffer, vertex_buffer_sub_data, Flatten},
 };
 use crate::{
-    apply_bounds, elect_pole::draw_wire_gl, items::render_drop_item_gl, performance,
-    structure::Structure, Cell, FactorishState, FluidType, Ore, OreValue, Position, PowerWire,
-    Rotation, Vector2f, CHUNK_SIZE, CHUNK_SIZE_I, DROP_ITEM_SIZE, INDEX_CHUNK_SIZE,
-    ORE_HARVEST_TIME, TILE_SIZE, TILE_SIZE_F,
+    apply_bounds,
+    circuit::{CircuitTerminal, SignalWire},
+    elect_pole::draw_wire_gl,
+    items::render_drop_item_gl,
+    performance,
+    structure::Structure,
+    Cell, FactorishState, FluidType, Ore, OreValue, Position, PowerWire, Rotation, Vector2f,
+    CHUNK_SIZE, CHUNK_SIZE_I, DROP_ITEM_SIZE, INDEX_CHUNK_SIZE, ORE_HARVEST_TIME, TILE_SIZE,
+    TILE_SIZE_F,
 };
 use cgmath::{Matrix3, Matrix4, Rad, Vector2, Vector3};
 use slice_of_array::SliceFlatExt;
@@ -141,6 +146,34 @@ pub(crate) fn draw_fuel_alarm_gl(
     Ok(())
 }
 
+/// Draw a rectangle in tile coordinates with the flat shader.
+pub(crate) fn draw_flat_rect_gl(
+    state: &FactorishState,
+    gl: &GL,
+    (x, y, w, h): (f32, f32, f32, f32),
+    color: &[f32; 4],
+) -> Result<(), JsValue> {
+    let shader = state
+        .assets
+        .flat_shader
+        .as_ref()
+        .ok_or_else(|| js_str!("Shader not found"))?;
+    gl.use_program(Some(&shader.program));
+    gl.uniform4fv_with_f32_array(shader.color_loc.as_ref(), color);
+    enable_buffer(gl, &state.assets.screen_buffer, 2, shader.vertex_position);
+    gl.uniform_matrix4fv_with_f32_array(
+        shader.transform_loc.as_ref(),
+        false,
+        (state.get_world_transform()?
+            * Matrix4::from_scale(2.)
+            * Matrix4::from_translation(Vector3::new(x, y, 0.))
+            * Matrix4::from_nonuniform_scale(w, h, 1.))
+        .flatten(),
+    );
+    gl.draw_arrays(GL::TRIANGLE_FAN, 0, 4);
+    Ok(())
+}
+
 #[wasm_bindgen]
 impl FactorishState {
     pub fn render_gl_init(&mut self, gl: GL) -> Result<(), JsValue> {
@@ -202,6 +235,7 @@ impl FactorishState {
         } else {
             self.render_sprites_gl(&gl, shader)?;
         }
+        self.render_oil_gl(&gl)?;
 
         let draw_structures = |depth| -> Result<(), JsValue> {
             for structure in self.structure_iter() {
@@ -274,11 +308,78 @@ impl FactorishState {
             gl.uniform4fv_with_f32_array(shader.color_loc.as_ref(), &[0.75, 0.5, 0., 1.]);
 
             draw_wires(&self.power_wires, 1.)?;
+
+            // Preview of the wire being connected with the wire tool
+            if let Some((start, cursor)) = self
+                .wire_start
+                .and_then(|id| self.get_structure(id))
+                .zip(self.cursor.as_ref())
+            {
+                gl.uniform4fv_with_f32_array(shader.color_loc.as_ref(), &[1., 0.75, 0., 0.5]);
+                draw_wire_gl(&gl, *start.position(), Position::from(cursor), 1.)?;
+            }
+
+            let terminal_position = |terminal: &CircuitTerminal| {
+                let structure = self.get_structure(terminal.id)?;
+                if terminal.output {
+                    structure.circuit_output_terminal()
+                } else {
+                    Some(*structure.position())
+                }
+            };
+            for SignalWire(color, first, second) in &self.signal_wires {
+                if let Some((first, second)) =
+                    terminal_position(first).zip(terminal_position(second))
+                {
+                    gl.uniform4fv_with_f32_array(shader.color_loc.as_ref(), &color.color());
+                    draw_wire_gl(&gl, first, second, 0.5)?;
+                }
+            }
+
+            if let Some(((color, start), cursor)) = self
+                .signal_wire_start
+                .and_then(|(color, start)| Some((color, terminal_position(&start)?)))
+                .zip(self.cursor.as_ref())
+            {
+                let [r, g, b, _] = color.color();
+                gl.uniform4fv_with_f32_array(shader.color_loc.as_ref(), &[r, g, b, 0.5]);
+                draw_wire_gl(&gl, start, Position::from(cursor), 0.5)?;
+            }
         }
 
         draw_structures(1)?;
         draw_structures(2)?;
 
+        self.render_ghosts_gl(&gl)?;
+        self.render_deconstruction_gl(&gl)?;
+
+        self.render_logistic_robots_gl(&gl)?;
+        self.render_trains_gl(&gl)?;
+        self.render_character_gl(&gl)?;
+
+        // Darken the scene at night
+        let lighting = self.lighting();
+        if lighting < 1. {
+            if let Some(shader) = self.assets.flat_shader.as_ref() {
+                gl.use_program(Some(&shader.program));
+                gl.uniform4fv_with_f32_array(
+                    shader.color_loc.as_ref(),
+                    &[0., 0., 0.1, (1. - lighting) as f32],
+                );
+                enable_buffer(&gl, &self.assets.screen_buffer, 2, shader.vertex_position);
+                gl.uniform_matrix4fv_with_f32_array(
+                    shader.transform_loc.as_ref(),
+                    false,
+                    (Matrix4::from_translation(Vector3::new(-1., -1., 0.))
+                        * Matrix4::from_scale(2.))
+                    .flatten(),
+                );
+                gl.draw_arrays(GL::TRIANGLE_FAN, 0, 4);
+            }
+        }
+
+        self.render_lights_gl(&gl, lighting)?;
+
         // Smoke rendering
         if let Some(shader) = self.assets.textured_alpha_shader.as_ref() {
             for ent in &self.temp_ents {
@@ -412,11 +513,9 @@ impl FactorishState {
                             )?;
                             gl.uniform4fv_with_f32_array(
                                 shader.color_loc.as_ref(),
-                                match fb.type_ {
-                                    Some(FluidType::Water) => &[0., 1., 1., 1.],
-                                    Some(FluidType::Steam) => &[0.75, 0.75, 0.75, 1.],
-                                    _ => &[0.5, 0.5, 0.5, 1.],
-                                },
+                                &fb.type_
+                                    .map(|type_| type_.color())
+                                    .unwrap_or([0.5, 0.5, 0.5, 1.]),
                             );
                             gl.draw_arrays(GL::TRIANGLE_FAN, 0, 4);
 
@@ -432,12 +531,35 @@ impl FactorishState {
             }
         }
 
+        self.render_blueprint_gl(&gl)?;
+
         if let Some((ref cursor, shader)) = self.cursor.zip(self.assets.flat_shader.as_ref()) {
             let (x, y) = (cursor[0] as f32, cursor[1] as f32);
 
             if let Some((selected_tool, _)) = self.get_selected_tool_or_item_opt() {
                 if let Ok(mut tool) = self.new_structure(&selected_tool, &Position::from(cursor)) {
                     tool.set_rotation(&self.tool_rotation).ok();
+                    // Show the supply areas of the existing poles and the one being placed
+                    if let Some(tool_area) = tool.supply_area() {
+                        let areas = self
+                            .structures
+                            .iter()
+                            .filter_map(|s| s.dynamic.as_deref()?.supply_area())
+                            .chain(std::iter::once(tool_area));
+                        for area in areas {
+                            draw_flat_rect_gl(
+                                self,
+                                &gl,
+                                (
+                                    area.x0 as f32 + self.viewport.x as f32,
+                                    area.y0 as f32 + self.viewport.y as f32,
+                                    (area.x1 - area.x0) as f32,
+                                    (area.y1 - area.y0) as f32,
+                                ),
+                                &[0.2, 0.5, 1., 0.2],
+                            )?;
+                        }
+                    }
                     for depth in 0..3 {
                         tool.draw_gl(self, &gl, depth, true)?;
                     }
@@ -641,6 +763,73 @@ impl FactorishState {
         Ok(())
     }
 
+    /// Oil deposits are drawn as dark spots sized by their yield.
+    fn render_oil_gl(&self, gl: &GL) -> Result<(), JsValue> {
+        let bounds = apply_bounds(
+            &self.bounds,
+            &self.viewport,
+            self.viewport_width,
+            self.viewport_height,
+        );
+        self.render_cells(
+            |x, y, cell| {
+                if cell.oil == 0 {
+                    return Ok(());
+                }
+                let size = (0.3 + cell.oil as f32 / 200.).min(0.9);
+                draw_flat_rect_gl(
+                    self,
+                    gl,
+                    (
+                        x as f32 + (1. - size) / 2. + self.viewport.x as f32,
+                        y as f32 + (1. - size) / 2. + self.viewport.y as f32,
+                        size,
+                        size,
+                    ),
+                    &FluidType::CrudeOil.color(),
+                )
+            },
+            bounds,
+        )
+    }
+
+    /// Lights are added on top of the night overlay so that they stand out in the dark.
+    fn render_lights_gl(&self, gl: &GL, lighting: f64) -> Result<(), JsValue> {
+        const GLOW_RADIUS: f32 = 3.;
+        let shader = self
+            .assets
+            .glow_shader
+            .as_ref()
+            .ok_or_else(|| js_str!("Shader not found"))?;
+        gl.use_program(Some(&shader.program));
+        enable_buffer(gl, &self.assets.rect_buffer, 2, shader.vertex_position);
+        gl.blend_func(GL::SRC_ALPHA, GL::ONE);
+        let intensity = (0.15 + 1. - lighting) as f32;
+        for structure in self.structure_iter() {
+            let Some([r, g, b]) = structure.light_color() else {
+                continue;
+            };
+            let bb = structure.bounding_box();
+            let (x, y) = (
+                (bb.x0 + bb.x1) as f32 / 2. + self.viewport.x as f32,
+                (bb.y0 + bb.y1) as f32 / 2. + self.viewport.y as f32,
+            );
+            gl.uniform4fv_with_f32_array(shader.color_loc.as_ref(), &[r, g, b, intensity]);
+            gl.uniform_matrix4fv_with_f32_array(
+                shader.transform_loc.as_ref(),
+                false,
+                (self.get_world_transform()?
+                    * Matrix4::from_scale(2.)
+                    * Matrix4::from_translation(Vector3::new(x, y, 0.))
+                    * Matrix4::from_scale(GLOW_RADIUS))
+                .flatten(),
+            );
+            gl.draw_arrays(GL::TRIANGLE_FAN, 0, 4);
+        }
+        gl.blend_func(GL::SRC_ALPHA, GL::ONE_MINUS_SRC_ALPHA);
+        Ok(())
+    }
+
     fn render_cells(
         &self,
         mut draw: impl FnMut(i32, i32, &Cell) -> Result<(), JsValue>,
@@ -1,16 +1,19 @@
 use super::{
+    circuit::{CircuitCondition, CircuitConfig, Signals},
     draw_direction_arrow,
     drop_items::DropItem,
     gl::{
-        draw_direction_arrow_gl,
+        draw_direction_arrow_gl, draw_electricity_alarm_gl,
         utils::{enable_buffer, Flatten},
     },
     items::{render_drop_item, render_drop_item_mat_gl, ItemType},
-    structure::{RotateErr, Structure, StructureDynIter, StructureId},
-    FactorishState, FrameProcResult, Inventory, InventoryTrait, Position, Rotation,
+    structure::{get_powered_progress, RotateErr, Structure, StructureDynIter, StructureId},
+    FactorishState, FrameProcResult, Inventory, InventoryTrait, Position, Recipe, Rotation,
 };
 use cgmath::{Matrix3, Matrix4, Rad, Vector2, Vector3};
+use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use wasm_bindgen::prelude::*;
 use web_sys::{CanvasRenderingContext2d, WebGlRenderingContext as GL};
 
@@ -20,13 +23,30 @@ pub(crate) struct Inserter {
     rotation: Rotation,
     cooldown: f64,
     hold_item: Option<ItemType>,
+    /// Electric inserters draw energy from the power network for each swing,
+    /// while the plain ones operate for free.
+    #[serde(default)]
+    electric: bool,
+    #[serde(default)]
+    power: f64,
     #[serde(skip)]
     input_structure: Option<StructureId>,
     #[serde(skip)]
     output_structure: Option<StructureId>,
+    #[serde(default)]
+    circuit_condition: Option<CircuitCondition>,
+    /// Whether the circuit condition is not met in the last tick
+    #[serde(skip)]
+    circuit_disabled: bool,
 }
 
 const INSERTER_TIME: f64 = 20.;
+const INSERTER_MAX_POWER: f64 = 10.;
+
+/// A pseudo recipe that describes a half swing of an electric inserter, so that we can
+/// apply the same brownout rule as other consumers with [`get_powered_progress`].
+static SWING_RECIPE: Lazy<Recipe> =
+    Lazy::new(|| Recipe::new(HashMap::new(), HashMap::new(), 5., INSERTER_TIME));
 
 impl Inserter {
     pub(crate) fn new(x: i32, y: i32, rotation: Rotation) -> Self {
@@ -35,9 +55,39 @@ impl Inserter {
             rotation,
             cooldown: 0.,
             hold_item: None,
+            electric: false,
+            power: 0.,
             input_structure: None,
             output_structure: None,
+            circuit_condition: None,
+            circuit_disabled: false,
+        }
+    }
+
+    pub(crate) fn new_electric(x: i32, y: i32, rotation: Rotation) -> Self {
+        Inserter {
+            electric: true,
+            ..Self::new(x, y, rotation)
+        }
+    }
+
+    /// Returns how much the cooldown can proceed in this frame.
+    /// Electric inserters consume the energy supplied by the network only while the arm is moving.
+    /// `speed` is the ratio of the swing speed granted by research.
+    fn proceed_time(&mut self, speed: f64) -> f64 {
+        let delta_time = 1. / 0.05 / 60. * speed;
+        if !self.electric {
+            return delta_time;
+        }
+
+        if self.cooldown <= 0. {
+            return delta_time;
         }
+
+        // A faster swing takes the same energy in a shorter time
+        let progress = get_powered_progress(self.power / speed, 0., &SWING_RECIPE) * speed;
+        self.power -= progress * SWING_RECIPE.power_cost;
+        progress * INSERTER_TIME
     }
 
     fn get_arm_angles(&self) -> (f64, f64) {
@@ -73,7 +123,11 @@ impl Inserter {
 
 impl Structure for Inserter {
     fn name(&self) -> &str {
-        "Inserter"
+        if self.electric {
+            "Electric Inserter"
+        } else {
+            "Inserter"
+        }
     }
 
     fn position(&self) -> &Position {
@@ -278,6 +332,9 @@ impl Structure for Inserter {
                 if state.alt_mode {
                     draw_direction_arrow_gl((x, y), &self.rotation, state, gl)?
                 }
+                if !is_ghost && self.electric && self.power == 0. {
+                    draw_electricity_alarm_gl((x, y), state, gl)?;
+                }
             }
             _ => panic!("render depth not covered: {}", depth),
         }
@@ -293,7 +350,13 @@ impl Structure for Inserter {
     ) -> Result<FrameProcResult, ()> {
         let input_position = self.position.add(self.rotation.delta_inv());
         let output_position = self.position.add(self.rotation.delta());
-        let delta_time = 1. / 0.05 / 60.;
+        let delta_time = self.proceed_time(1. + state.research_modifiers.inserter_speed);
+
+        // A disabled inserter finishes the swing in progress, but does not pick up a new item.
+        if self.hold_item.is_none() && self.circuit_disabled && self.cooldown <= delta_time {
+            self.cooldown = 0.;
+            return Ok(FrameProcResult::None);
+        }
 
         if self.hold_item.is_none() {
             if self.cooldown <= delta_time {
@@ -460,15 +523,70 @@ impl Structure for Inserter {
 
     fn desc(&self, state: &FactorishState) -> String {
         format!(
-            "Input: {:?} {}<br>Output: {:?}",
+            "Input: {:?} {}<br>Output: {:?}{}",
             self.input_structure,
             self.input_structure
                 .and_then(|id| state.structures.get(id.id as usize))
                 .and_then(|s| s.dynamic.as_deref())
                 .map(|d| d.name())
                 .unwrap_or("Not found"),
-            self.output_structure
-        )
+            self.output_structure,
+            if self.electric {
+                format!(
+                    "<br>Power: {:.1}kJ / {:.1}kJ",
+                    self.power, INSERTER_MAX_POWER
+                )
+            } else {
+                "".to_string()
+            }
+        ) + &if 0. < state.research_modifiers.inserter_speed {
+            format!(
+                "<br>Speed: +{:.0}%",
+                state.research_modifiers.inserter_speed * 100.
+            )
+        } else {
+            String::new()
+        }
+    }
+
+    fn power_sink(&self) -> bool {
+        self.electric
+    }
+
+    fn circuit_connectable(&self) -> bool {
+        true
+    }
+
+    fn circuit_input(&mut self, signals: Option<&Signals>) {
+        self.circuit_disabled = signals
+            .zip(self.circuit_condition.as_ref())
+            .is_some_and(|(signals, condition)| !condition.evaluate(signals));
+    }
+
+    fn circuit_config(&self) -> Option<CircuitConfig> {
+        Some(CircuitConfig::Condition(self.circuit_condition.clone()))
+    }
+
+    fn set_circuit_config(&mut self, config: CircuitConfig) -> Result<(), JsValue> {
+        match config {
+            CircuitConfig::Condition(condition) => {
+                self.circuit_condition = condition;
+                Ok(())
+            }
+            _ => js_err!("Wrong circuit settings for an inserter"),
+        }
+    }
+
+    fn power_demand(&self) -> f64 {
+        if self.electric && self.power < SWING_RECIPE.power_cost {
+            INSERTER_MAX_POWER - self.power
+        } else {
+            0.
+        }
+    }
+
+    fn power_inlet(&mut self, energy: f64) {
+        self.power += energy;
     }
 
     fn rotate(
@@ -497,5 +615,80 @@ impl Structure for Inserter {
         ret
     }
 
+    fn reset_for_blueprint(&mut self) {
+        self.power = 0.;
+    }
+
     crate::serialize_impl!();
 }
+
+#[test]
+fn test_electric_inserter_power() {
+    use super::{
+        elect_pole::ElectPole,
+        power_network::{build_power_networks, distribute_power},
+        solar_panel::SolarPanel,
+        structure::StructureEntry,
+        PowerWire,
+    };
+
+    // Number of ticks until the inserters on a pole, with or without a solar panel, drop
+    // the items they hold
+    let swing = |electric: bool, inserters: i32, solar: bool| -> Option<usize> {
+        let mut state = FactorishState::new_test();
+        let mut structures = vec![];
+        let mut entry = |s: Box<dyn Structure>| {
+            structures.push(StructureEntry {
+                gen: 0,
+                dynamic: Some(s),
+            });
+            StructureId {
+                id: structures.len() as u32 - 1,
+                gen: 0,
+            }
+        };
+        let pole = entry(Box::new(ElectPole::new(&Position::new(0, 0))));
+        let mut wires = vec![];
+        if solar {
+            let solar = entry(Box::new(SolarPanel::new(&Position::new(1, 0))));
+            wires.push(PowerWire(pole, solar));
+        }
+        for i in 0..inserters {
+            let inserter = entry(Box::new(Inserter {
+                hold_item: Some(ItemType::IronPlate),
+                cooldown: INSERTER_TIME,
+                electric,
+                ..Inserter::new(i * 2, 4, Rotation::Bottom)
+            }));
+            wires.push(PowerWire(pole, inserter));
+        }
+
+        for tick in 0..1000 {
+            for i in 0..structures.len() {
+                let (entry, mut others) = StructureDynIter::new(&mut structures, i).unwrap();
+                let me = StructureId {
+                    id: i as u32,
+                    gen: 0,
+                };
+                if let Some(s) = entry.dynamic.as_deref_mut() {
+                    s.frame_proc(me, &mut state, &mut others).unwrap();
+                }
+            }
+            let mut s_d_iter = StructureDynIter::new_all(&mut structures);
+            let mut networks = build_power_networks(&s_d_iter, &wires);
+            distribute_power(&mut networks, &mut s_d_iter);
+            if state.drop_items.len() == inserters as usize {
+                return Some(tick);
+            }
+        }
+        None
+    };
+
+    let plain = swing(false, 1, false).unwrap();
+    // An electric inserter fed by the network swings as fast as a plain one, after the first
+    // tick to receive the energy
+    assert_eq!(swing(true, 1, true), Some(plain + 1));
+    // It slows down when the network is short, and stops without power
+    assert!(plain * 2 < swing(true, 3, true).unwrap());
+    assert_eq!(swing(true, 1, false), None);
+}
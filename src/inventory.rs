@@ -103,12 +103,40 @@ pub(crate) fn filter_inventory(
     ret
 }
 
+/// Returns how many `item_type` can be added to a storage inventory with `slots` stacks.
+///
+/// `filters` reserve slots for a specific item type from the beginning of the storage. Reserved slots
+/// cannot hold other items, while an item can spill over into unfiltered slots.
+/// Filters beyond `slots` are ignored, so that we can pass a limited number of slots (bar) with the same filters.
+pub(crate) fn storage_capacity(
+    inventory: &Inventory,
+    item_type: &ItemType,
+    slots: usize,
+    filters: &[Option<ItemType>],
+) -> usize {
+    let filters = &filters[..filters.len().min(slots)];
+    let reserved_for = |item: &ItemType| filters.iter().filter(|f| **f == Some(*item)).count();
+    let unfiltered = slots - filters.iter().filter(|f| f.is_some()).count();
+    let occupied_by_others: usize = inventory
+        .iter()
+        .filter(|(item, _)| *item != item_type)
+        .map(|(item, count)| {
+            count
+                .div_ceil(STACK_SIZE)
+                .saturating_sub(reserved_for(item))
+        })
+        .sum();
+    let usable_slots = reserved_for(item_type) + unfiltered.saturating_sub(occupied_by_others);
+    (usable_slots * STACK_SIZE).saturating_sub(inventory.count_item(item_type))
+}
+
 #[derive(PartialEq, Eq, Debug, Serialize, Deserialize, Clone, Copy)]
 pub(crate) enum InventoryType {
     Input,
     Output,
     Storage,
     Burner,
+    Module,
 }
 
 impl TryFrom<JsValue> for InventoryType {
@@ -117,3 +145,42 @@ impl TryFrom<JsValue> for InventoryType {
         from_value(value).map_err(|e| js_str!("{}", e.to_string()))
     }
 }
+
+#[test]
+fn test_storage_capacity() {
+    let mut inventory = Inventory::new();
+    inventory.add_items(&ItemType::IronOre, 60);
+    assert_eq!(
+        storage_capacity(&inventory, &ItemType::IronOre, 4, &[]),
+        4 * STACK_SIZE - 60
+    );
+    assert_eq!(
+        storage_capacity(&inventory, &ItemType::CoalOre, 4, &[]),
+        2 * STACK_SIZE
+    );
+
+    // A slot reserved for coal cannot be used by iron ore, but coal can use the rest.
+    let filters = [Some(ItemType::CoalOre), None, None, None];
+    assert_eq!(
+        storage_capacity(&inventory, &ItemType::IronOre, 4, &filters),
+        3 * STACK_SIZE - 60
+    );
+    assert_eq!(
+        storage_capacity(&inventory, &ItemType::CoalOre, 4, &filters),
+        2 * STACK_SIZE
+    );
+    assert_eq!(
+        storage_capacity(&inventory, &ItemType::CopperOre, 4, &filters),
+        STACK_SIZE
+    );
+
+    // Limiting slots (bar) also limits the effective filters.
+    assert_eq!(
+        storage_capacity(&inventory, &ItemType::IronOre, 1, &filters),
+        0
+    );
+    assert_eq!(
+        storage_capacity(&inventory, &ItemType::CoalOre, 1, &filters),
+        STACK_SIZE
+    );
+}
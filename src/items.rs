@@ -20,10 +20,14 @@ pub(crate) enum ItemType {
     SteelPlate,
     SciencePack1,
     SciencePack2,
+    LogisticRobot,
 
     TransportBelt,
     Chest,
+    IronChest,
+    SteelChest,
     Inserter,
+    ElectricInserter,
     OreMine,
     Furnace,
     ElectricFurnace,
@@ -37,7 +41,47 @@ pub(crate) enum ItemType {
     SteamEngine,
     ElectPole,
     Splitter,
+    Loader,
     UndergroundBelt,
+    FastUndergroundBelt,
+    PassiveProviderChest,
+    ActiveProviderChest,
+    StorageChest,
+    RequesterChest,
+    Roboport,
+    Rail,
+    CurvedRail,
+    RailSignal,
+    TrainStop,
+    Locomotive,
+    CargoWagon,
+    StorageTank,
+    Pump,
+    Pumpjack,
+    OilRefinery,
+    ChemicalPlant,
+    Plastic,
+    Sulfur,
+    Concrete,
+    ElectricMiningDrill,
+    SpeedModule,
+    EfficiencyModule,
+    ProductivityModule,
+    Beacon,
+    SolarPanel,
+    Accumulator,
+    MediumElectPole,
+    BigElectPole,
+    Substation,
+    RedWire,
+    GreenWire,
+    ConstantCombinator,
+    ArithmeticCombinator,
+    DeciderCombinator,
+    Lamp,
+    MilitarySciencePack,
+    ChemicalSciencePack,
+    ProductionSciencePack,
 }
 
 pub(crate) fn item_to_str(type_: &ItemType) -> String {
@@ -54,10 +98,14 @@ pub(crate) fn item_to_str(type_: &ItemType) -> String {
         ItemType::SteelPlate => "Steel Plate".to_string(),
         ItemType::SciencePack1 => "Science Pack 1".to_string(),
         ItemType::SciencePack2 => "Science Pack 2".to_string(),
+        ItemType::LogisticRobot => "Logistic Robot".to_string(),
 
         ItemType::TransportBelt => "Transport Belt".to_string(),
         ItemType::Chest => "Chest".to_string(),
+        ItemType::IronChest => "Iron Chest".to_string(),
+        ItemType::SteelChest => "Steel Chest".to_string(),
         ItemType::Inserter => "Inserter".to_string(),
+        ItemType::ElectricInserter => "Electric Inserter".to_string(),
         ItemType::OreMine => "Ore Mine".to_string(),
         ItemType::Furnace => "Furnace".to_string(),
         ItemType::ElectricFurnace => "Electric Furnace".to_string(),
@@ -71,7 +119,47 @@ pub(crate) fn item_to_str(type_: &ItemType) -> String {
         ItemType::SteamEngine => "Steam Engine".to_string(),
         ItemType::ElectPole => "Electric Pole".to_string(),
         ItemType::Splitter => "Splitter".to_string(),
+        ItemType::Loader => "Loader".to_string(),
         ItemType::UndergroundBelt => "Underground Belt".to_string(),
+        ItemType::FastUndergroundBelt => "Fast Underground Belt".to_string(),
+        ItemType::PassiveProviderChest => "Passive Provider Chest".to_string(),
+        ItemType::ActiveProviderChest => "Active Provider Chest".to_string(),
+        ItemType::StorageChest => "Storage Chest".to_string(),
+        ItemType::RequesterChest => "Requester Chest".to_string(),
+        ItemType::Roboport => "Roboport".to_string(),
+        ItemType::Rail => "Rail".to_string(),
+        ItemType::CurvedRail => "Curved Rail".to_string(),
+        ItemType::RailSignal => "Rail Signal".to_string(),
+        ItemType::TrainStop => "Train Stop".to_string(),
+        ItemType::Locomotive => "Locomotive".to_string(),
+        ItemType::CargoWagon => "Cargo Wagon".to_string(),
+        ItemType::StorageTank => "Storage Tank".to_string(),
+        ItemType::Pump => "Pump".to_string(),
+        ItemType::Pumpjack => "Pumpjack".to_string(),
+        ItemType::OilRefinery => "Oil Refinery".to_string(),
+        ItemType::ChemicalPlant => "Chemical Plant".to_string(),
+        ItemType::Plastic => "Plastic".to_string(),
+        ItemType::Sulfur => "Sulfur".to_string(),
+        ItemType::Concrete => "Concrete".to_string(),
+        ItemType::ElectricMiningDrill => "Electric Mining Drill".to_string(),
+        ItemType::SpeedModule => "Speed Module".to_string(),
+        ItemType::EfficiencyModule => "Efficiency Module".to_string(),
+        ItemType::ProductivityModule => "Productivity Module".to_string(),
+        ItemType::Beacon => "Beacon".to_string(),
+        ItemType::SolarPanel => "Solar Panel".to_string(),
+        ItemType::Accumulator => "Accumulator".to_string(),
+        ItemType::MediumElectPole => "Medium Electric Pole".to_string(),
+        ItemType::BigElectPole => "Big Electric Pole".to_string(),
+        ItemType::Substation => "Substation".to_string(),
+        ItemType::RedWire => "Red Wire".to_string(),
+        ItemType::GreenWire => "Green Wire".to_string(),
+        ItemType::ConstantCombinator => "Constant Combinator".to_string(),
+        ItemType::ArithmeticCombinator => "Arithmetic Combinator".to_string(),
+        ItemType::DeciderCombinator => "Decider Combinator".to_string(),
+        ItemType::Lamp => "Lamp".to_string(),
+        ItemType::MilitarySciencePack => "Military Science Pack".to_string(),
+        ItemType::ChemicalSciencePack => "Chemical Science Pack".to_string(),
+        ItemType::ProductionSciencePack => "Production Science Pack".to_string(),
     }
 }
 
@@ -89,10 +177,14 @@ pub(crate) fn str_to_item(name: &str) -> Option<ItemType> {
         "Steel Plate" => Some(ItemType::SteelPlate),
         "Science Pack 1" => Some(ItemType::SciencePack1),
         "Science Pack 2" => Some(ItemType::SciencePack2),
+        "Logistic Robot" => Some(ItemType::LogisticRobot),
 
         "Transport Belt" => Some(ItemType::TransportBelt),
         "Chest" => Some(ItemType::Chest),
+        "Iron Chest" => Some(ItemType::IronChest),
+        "Steel Chest" => Some(ItemType::SteelChest),
         "Inserter" => Some(ItemType::Inserter),
+        "Electric Inserter" => Some(ItemType::ElectricInserter),
         "Ore Mine" => Some(ItemType::OreMine),
         "Furnace" => Some(ItemType::Furnace),
         "Electric Furnace" => Some(ItemType::ElectricFurnace),
@@ -106,7 +198,47 @@ pub(crate) fn str_to_item(name: &str) -> Option<ItemType> {
         "Steam Engine" => Some(ItemType::SteamEngine),
         "Electric Pole" => Some(ItemType::ElectPole),
         "Splitter" => Some(ItemType::Splitter),
+        "Loader" => Some(ItemType::Loader),
         "Underground Belt" => Some(ItemType::UndergroundBelt),
+        "Fast Underground Belt" => Some(ItemType::FastUndergroundBelt),
+        "Passive Provider Chest" => Some(ItemType::PassiveProviderChest),
+        "Active Provider Chest" => Some(ItemType::ActiveProviderChest),
+        "Storage Chest" => Some(ItemType::StorageChest),
+        "Requester Chest" => Some(ItemType::RequesterChest),
+        "Roboport" => Some(ItemType::Roboport),
+        "Rail" => Some(ItemType::Rail),
+        "Curved Rail" => Some(ItemType::CurvedRail),
+        "Rail Signal" => Some(ItemType::RailSignal),
+        "Train Stop" => Some(ItemType::TrainStop),
+        "Locomotive" => Some(ItemType::Locomotive),
+        "Cargo Wagon" => Some(ItemType::CargoWagon),
+        "Storage Tank" => Some(ItemType::StorageTank),
+        "Pump" => Some(ItemType::Pump),
+        "Pumpjack" => Some(ItemType::Pumpjack),
+        "Oil Refinery" => Some(ItemType::OilRefinery),
+        "Chemical Plant" => Some(ItemType::ChemicalPlant),
+        "Plastic" => Some(ItemType::Plastic),
+        "Sulfur" => Some(ItemType::Sulfur),
+        "Concrete" => Some(ItemType::Concrete),
+        "Electric Mining Drill" => Some(ItemType::ElectricMiningDrill),
+        "Speed Module" => Some(ItemType::SpeedModule),
+        "Efficiency Module" => Some(ItemType::EfficiencyModule),
+        "Productivity Module" => Some(ItemType::ProductivityModule),
+        "Beacon" => Some(ItemType::Beacon),
+        "Solar Panel" => Some(ItemType::SolarPanel),
+        "Accumulator" => Some(ItemType::Accumulator),
+        "Medium Electric Pole" => Some(ItemType::MediumElectPole),
+        "Big Electric Pole" => Some(ItemType::BigElectPole),
+        "Substation" => Some(ItemType::Substation),
+        "Red Wire" => Some(ItemType::RedWire),
+        "Green Wire" => Some(ItemType::GreenWire),
+        "Constant Combinator" => Some(ItemType::ConstantCombinator),
+        "Arithmetic Combinator" => Some(ItemType::ArithmeticCombinator),
+        "Decider Combinator" => Some(ItemType::DeciderCombinator),
+        "Lamp" => Some(ItemType::Lamp),
+        "Military Science Pack" => Some(ItemType::MilitarySciencePack),
+        "Chemical Science Pack" => Some(ItemType::ChemicalSciencePack),
+        "Production Science Pack" => Some(ItemType::ProductionSciencePack),
 
         _ => None,
     }
@@ -151,33 +283,74 @@ pub(crate) fn render_drop_item(
         ItemType::IronOre => render16(&state.image_iron_ore),
         ItemType::CoalOre => render16(&state.image_coal_ore),
         ItemType::CopperOre => render16(&state.image_copper_ore),
-        ItemType::StoneOre => render16(&state.image_stone_ore),
+        ItemType::StoneOre | ItemType::Sulfur | ItemType::Concrete => {
+            render16(&state.image_stone_ore)
+        }
         ItemType::IronPlate => render16(&state.image_iron_plate),
         ItemType::CopperPlate => render16(&state.image_copper_plate),
         ItemType::Gear => render16(&state.image_gear),
-        ItemType::CopperWire => render16(&state.image_copper_wire),
-        ItemType::Circuit => render16(&state.image_circuit),
-        ItemType::SteelPlate => render16(&state.image_steel_plate),
+        ItemType::CopperWire | ItemType::RedWire | ItemType::GreenWire => {
+            render16(&state.image_copper_wire)
+        }
+        ItemType::Circuit
+        | ItemType::SpeedModule
+        | ItemType::EfficiencyModule
+        | ItemType::ProductivityModule
+        | ItemType::ConstantCombinator
+        | ItemType::ArithmeticCombinator
+        | ItemType::DeciderCombinator
+        | ItemType::Lamp => render16(&state.image_circuit),
+        ItemType::SteelPlate | ItemType::Plastic | ItemType::SolarPanel => {
+            render16(&state.image_steel_plate)
+        }
         ItemType::SciencePack1 => render16(&state.image_science_pack_1),
-        ItemType::SciencePack2 => render16(&state.image_science_pack_2),
+        ItemType::SciencePack2
+        | ItemType::MilitarySciencePack
+        | ItemType::ChemicalSciencePack
+        | ItemType::ProductionSciencePack => render16(&state.image_science_pack_2),
 
-        ItemType::TransportBelt => render16(&state.image_belt),
-        ItemType::Chest => render16(&state.image_chest),
-        ItemType::Inserter => render_animated32(&state.image_inserter),
-        ItemType::OreMine => render16(&state.image_mine),
+        ItemType::TransportBelt | ItemType::Loader => render16(&state.image_belt),
+        ItemType::Chest
+        | ItemType::IronChest
+        | ItemType::SteelChest
+        | ItemType::PassiveProviderChest
+        | ItemType::ActiveProviderChest
+        | ItemType::StorageChest
+        | ItemType::RequesterChest
+        | ItemType::Roboport
+        | ItemType::TrainStop
+        | ItemType::CargoWagon => render16(&state.image_chest),
+        ItemType::Inserter | ItemType::ElectricInserter | ItemType::LogisticRobot => {
+            render_animated32(&state.image_inserter)
+        }
+        ItemType::OreMine | ItemType::Pumpjack | ItemType::ElectricMiningDrill => {
+            render16(&state.image_mine)
+        }
         ItemType::Furnace => render_animated32(&state.image_furnace),
         ItemType::ElectricFurnace => render_animated32(&state.image_electric_furnace),
-        ItemType::Assembler => render16(&state.image_assembler),
+        ItemType::Assembler | ItemType::OilRefinery | ItemType::ChemicalPlant => {
+            render16(&state.image_assembler)
+        }
         ItemType::Lab => render16(&state.image_lab),
         ItemType::Boiler => render16(&state.image_boiler),
-        ItemType::WaterWell => render16(&state.image_water_well),
-        ItemType::OffshorePump => render16(&state.image_offshore_pump),
-        ItemType::Pipe => render16(&state.image_pipe),
+        ItemType::WaterWell | ItemType::StorageTank | ItemType::Accumulator => {
+            render16(&state.image_water_well)
+        }
+        ItemType::OffshorePump | ItemType::Pump => render16(&state.image_offshore_pump),
+        ItemType::Pipe | ItemType::Rail | ItemType::CurvedRail | ItemType::RailSignal => {
+            render16(&state.image_pipe)
+        }
         ItemType::UndergroundPipe => render16(&state.image_pipe),
-        ItemType::SteamEngine => render16(&state.image_steam_engine),
-        ItemType::ElectPole => render16(&state.image_elect_pole),
+        ItemType::SteamEngine | ItemType::Locomotive => render16(&state.image_steam_engine),
+        ItemType::ElectPole
+        | ItemType::MediumElectPole
+        | ItemType::BigElectPole
+        | ItemType::Substation
+        | ItemType::Beacon => render16(&state.image_elect_pole),
         ItemType::Splitter => render16(&state.image_splitter),
-        ItemType::UndergroundBelt => render16(&state.image_underground_belt_item),
+        ItemType::UndergroundBelt | ItemType::FastUndergroundBelt => {
+            render16(&state.image_underground_belt_item)
+        }
     }
 }
 
@@ -215,33 +388,76 @@ pub(crate) fn render_drop_item_mat_gl(
         ItemType::IronOre => render16(&state.assets.tex_iron_ore),
         ItemType::CoalOre => render16(&state.assets.tex_coal_ore),
         ItemType::CopperOre => render16(&state.assets.tex_copper_ore),
-        ItemType::StoneOre => render16(&state.assets.tex_stone_ore),
+        ItemType::StoneOre | ItemType::Sulfur | ItemType::Concrete => {
+            render16(&state.assets.tex_stone_ore)
+        }
         ItemType::IronPlate => render16(&state.assets.tex_iron_plate),
         ItemType::CopperPlate => render16(&state.assets.tex_copper_plate),
         ItemType::Gear => render16(&state.assets.tex_gear),
-        ItemType::CopperWire => render16(&state.assets.tex_copper_wire),
-        ItemType::Circuit => render16(&state.assets.tex_circuit),
-        ItemType::SteelPlate => render16(&state.assets.tex_steel_plate),
+        ItemType::CopperWire | ItemType::RedWire | ItemType::GreenWire => {
+            render16(&state.assets.tex_copper_wire)
+        }
+        ItemType::Circuit
+        | ItemType::SpeedModule
+        | ItemType::EfficiencyModule
+        | ItemType::ProductivityModule
+        | ItemType::ConstantCombinator
+        | ItemType::ArithmeticCombinator
+        | ItemType::DeciderCombinator
+        | ItemType::Lamp => render16(&state.assets.tex_circuit),
+        ItemType::SteelPlate | ItemType::Plastic | ItemType::SolarPanel => {
+            render16(&state.assets.tex_steel_plate)
+        }
         ItemType::SciencePack1 => render16(&state.assets.tex_science_pack_1),
-        ItemType::SciencePack2 => render16(&state.assets.tex_science_pack_2),
+        ItemType::SciencePack2
+        | ItemType::MilitarySciencePack
+        | ItemType::ChemicalSciencePack
+        | ItemType::ProductionSciencePack => render16(&state.assets.tex_science_pack_2),
 
-        ItemType::TransportBelt => render16(&state.assets.tex_belt),
-        ItemType::Chest => render16(&state.assets.tex_chest),
-        ItemType::Inserter => render_gen(&state.assets.tex_inserter, 1. / 2.),
-        ItemType::OreMine => render_gen(&state.assets.tex_ore_mine, 1. / 3.),
+        ItemType::TransportBelt | ItemType::Loader => render16(&state.assets.tex_belt),
+        ItemType::Chest
+        | ItemType::IronChest
+        | ItemType::SteelChest
+        | ItemType::PassiveProviderChest
+        | ItemType::ActiveProviderChest
+        | ItemType::StorageChest
+        | ItemType::RequesterChest
+        | ItemType::Roboport
+        | ItemType::TrainStop
+        | ItemType::CargoWagon => render16(&state.assets.tex_chest),
+        ItemType::Inserter | ItemType::ElectricInserter | ItemType::LogisticRobot => {
+            render_gen(&state.assets.tex_inserter, 1. / 2.)
+        }
+        ItemType::OreMine | ItemType::Pumpjack | ItemType::ElectricMiningDrill => {
+            render_gen(&state.assets.tex_ore_mine, 1. / 3.)
+        }
         ItemType::Furnace => render_gen(&state.assets.tex_furnace, 1. / 3.),
         ItemType::ElectricFurnace => render_gen(&state.assets.tex_electric_furnace, 1. / 3.),
-        ItemType::Assembler => render_gen(&state.assets.tex_assembler, 1. / 4.),
+        ItemType::Assembler | ItemType::OilRefinery | ItemType::ChemicalPlant => {
+            render_gen(&state.assets.tex_assembler, 1. / 4.)
+        }
         ItemType::Lab => render_gen(&state.assets.tex_lab, 1. / 4.),
         ItemType::Boiler => render_gen(&state.assets.tex_boiler, 1. / 3.),
-        ItemType::WaterWell => render16(&state.assets.tex_water_well),
-        ItemType::OffshorePump => render16(&state.assets.tex_offshore_pump),
-        ItemType::Pipe => render16(&state.assets.tex_pipe),
+        ItemType::WaterWell | ItemType::StorageTank | ItemType::Accumulator => {
+            render16(&state.assets.tex_water_well)
+        }
+        ItemType::OffshorePump | ItemType::Pump => render16(&state.assets.tex_offshore_pump),
+        ItemType::Pipe | ItemType::Rail | ItemType::CurvedRail | ItemType::RailSignal => {
+            render16(&state.assets.tex_pipe)
+        }
         ItemType::UndergroundPipe => render16(&state.assets.tex_pipe),
-        ItemType::SteamEngine => render_gen(&state.assets.tex_steam_engine, 1. / 3.),
-        ItemType::ElectPole => render16(&state.assets.tex_elect_pole),
+        ItemType::SteamEngine | ItemType::Locomotive => {
+            render_gen(&state.assets.tex_steam_engine, 1. / 3.)
+        }
+        ItemType::ElectPole
+        | ItemType::MediumElectPole
+        | ItemType::BigElectPole
+        | ItemType::Substation
+        | ItemType::Beacon => render16(&state.assets.tex_elect_pole),
         ItemType::Splitter => render16(&state.assets.tex_splitter),
-        ItemType::UndergroundBelt => render16(&state.assets.tex_underground_belt_item),
+        ItemType::UndergroundBelt | ItemType::FastUndergroundBelt => {
+            render16(&state.assets.tex_underground_belt_item)
+        }
     }
 }
 
@@ -315,32 +531,75 @@ pub(crate) fn get_item_image_url<'a>(state: &'a FactorishState, item_type: &Item
         ItemType::IronOre => &state.image_iron_ore.as_ref().unwrap().url,
         ItemType::CoalOre => &state.image_coal_ore.as_ref().unwrap().url,
         ItemType::CopperOre => &state.image_copper_ore.as_ref().unwrap().url,
-        ItemType::StoneOre => &state.image_stone_ore.as_ref().unwrap().url,
+        ItemType::StoneOre | ItemType::Sulfur | ItemType::Concrete => {
+            &state.image_stone_ore.as_ref().unwrap().url
+        }
         ItemType::IronPlate => &state.image_iron_plate.as_ref().unwrap().url,
         ItemType::CopperPlate => &state.image_copper_plate.as_ref().unwrap().url,
         ItemType::Gear => &state.image_gear.as_ref().unwrap().url,
-        ItemType::CopperWire => &state.image_copper_wire.as_ref().unwrap().url,
-        ItemType::Circuit => &state.image_circuit.as_ref().unwrap().url,
-        ItemType::SteelPlate => &state.image_steel_plate.as_ref().unwrap().url,
+        ItemType::CopperWire | ItemType::RedWire | ItemType::GreenWire => {
+            &state.image_copper_wire.as_ref().unwrap().url
+        }
+        ItemType::Circuit
+        | ItemType::SpeedModule
+        | ItemType::EfficiencyModule
+        | ItemType::ProductivityModule
+        | ItemType::ConstantCombinator
+        | ItemType::ArithmeticCombinator
+        | ItemType::DeciderCombinator
+        | ItemType::Lamp => &state.image_circuit.as_ref().unwrap().url,
+        ItemType::SteelPlate | ItemType::Plastic | ItemType::SolarPanel => {
+            &state.image_steel_plate.as_ref().unwrap().url
+        }
         ItemType::SciencePack1 => &state.image_science_pack_1.as_ref().unwrap().url,
-        ItemType::SciencePack2 => &state.image_science_pack_2.as_ref().unwrap().url,
+        ItemType::SciencePack2
+        | ItemType::MilitarySciencePack
+        | ItemType::ChemicalSciencePack
+        | ItemType::ProductionSciencePack => &state.image_science_pack_2.as_ref().unwrap().url,
 
-        ItemType::TransportBelt => &state.image_belt.as_ref().unwrap().url,
-        ItemType::Chest => &state.image_chest.as_ref().unwrap().url,
-        ItemType::Inserter => &state.image_inserter.as_ref().unwrap().url,
-        ItemType::OreMine => &state.image_mine.as_ref().unwrap().url,
+        ItemType::TransportBelt | ItemType::Loader => &state.image_belt.as_ref().unwrap().url,
+        ItemType::Chest
+        | ItemType::IronChest
+        | ItemType::SteelChest
+        | ItemType::PassiveProviderChest
+        | ItemType::ActiveProviderChest
+        | ItemType::StorageChest
+        | ItemType::RequesterChest
+        | ItemType::Roboport
+        | ItemType::TrainStop
+        | ItemType::CargoWagon => &state.image_chest.as_ref().unwrap().url,
+        ItemType::Inserter | ItemType::ElectricInserter | ItemType::LogisticRobot => {
+            &state.image_inserter.as_ref().unwrap().url
+        }
+        ItemType::OreMine | ItemType::Pumpjack | ItemType::ElectricMiningDrill => {
+            &state.image_mine.as_ref().unwrap().url
+        }
         ItemType::Furnace => &state.image_furnace.as_ref().unwrap().url,
         ItemType::ElectricFurnace => &state.image_electric_furnace.as_ref().unwrap().url,
-        ItemType::Assembler => &state.image_assembler.as_ref().unwrap().url,
+        ItemType::Assembler | ItemType::OilRefinery | ItemType::ChemicalPlant => {
+            &state.image_assembler.as_ref().unwrap().url
+        }
         ItemType::Lab => &state.image_lab.as_ref().unwrap().url,
         ItemType::Boiler => &state.image_boiler.as_ref().unwrap().url,
-        ItemType::WaterWell => &state.image_water_well.as_ref().unwrap().url,
-        ItemType::OffshorePump => &state.image_offshore_pump.as_ref().unwrap().url,
-        ItemType::Pipe => &state.image_pipe.as_ref().unwrap().url,
+        ItemType::WaterWell | ItemType::StorageTank | ItemType::Accumulator => {
+            &state.image_water_well.as_ref().unwrap().url
+        }
+        ItemType::OffshorePump | ItemType::Pump => &state.image_offshore_pump.as_ref().unwrap().url,
+        ItemType::Pipe | ItemType::Rail | ItemType::CurvedRail | ItemType::RailSignal => {
+            &state.image_pipe.as_ref().unwrap().url
+        }
         ItemType::UndergroundPipe => &state.image_pipe.as_ref().unwrap().url,
-        ItemType::SteamEngine => &state.image_steam_engine.as_ref().unwrap().url,
-        ItemType::ElectPole => &state.image_elect_pole.as_ref().unwrap().url,
+        ItemType::SteamEngine | ItemType::Locomotive => {
+            &state.image_steam_engine.as_ref().unwrap().url
+        }
+        ItemType::ElectPole
+        | ItemType::MediumElectPole
+        | ItemType::BigElectPole
+        | ItemType::Substation
+        | ItemType::Beacon => &state.image_elect_pole.as_ref().unwrap().url,
         ItemType::Splitter => &state.image_splitter.as_ref().unwrap().url,
-        ItemType::UndergroundBelt => &state.image_underground_belt_item.as_ref().unwrap().url,
+        ItemType::UndergroundBelt | ItemType::FastUndergroundBelt => {
+            &state.image_underground_belt_item.as_ref().unwrap().url
+        }
     }
 }
@@ -5,12 +5,14 @@ use super::{
         utils::{enable_buffer, Flatten},
         ShaderBundle,
     },
-    inventory::{filter_inventory, Inventory, InventoryTrait, InventoryType},
-    research::TECHNOLOGIES,
-    serialize_impl,
-    structure::{
-        default_add_inventory, get_powered_progress, Structure, StructureDynIter, StructureId,
+    inventory::{Inventory, InventoryTrait, InventoryType},
+    modules::{
+        get_module_power_cost, get_module_progress, module_effect, proceed_productivity,
+        ModuleEffect,
     },
+    research::{TechnologyTag, SCIENCE_PACKS, TECHNOLOGIES},
+    serialize_impl,
+    structure::{default_add_inventory, Structure, StructureDynIter, StructureId},
     FactorishState, FrameProcResult, ItemType, Position, Recipe, TILE_SIZE,
 };
 use cgmath::{Matrix3, Matrix4, Vector2, Vector3};
@@ -30,14 +32,29 @@ fn generate_item_image(item_image: &str, icon_size: bool, count: usize) -> Strin
     })
 }
 
+/// Number of science packs of each kind a lab can hold
+const LAB_SLOT_SIZE: usize = 10;
+
+/// A lab accepts every kind of science pack into its own slot regardless of the current research,
+/// so that switching research does not stall the supply lines.
 #[derive(Serialize, Deserialize)]
 pub(crate) struct Lab {
     position: Position,
     input_inventory: Inventory,
+    #[serde(default)]
+    module_inventory: Inventory,
+    /// Accumulated productivity bonus. When it reaches 1, an extra research step is made.
+    #[serde(default)]
+    bonus_progress: f64,
+    #[serde(skip)]
+    module_effect: ModuleEffect,
     progress: Option<f64>,
     power: f64,
     max_power: f64,
     recipe: Option<Recipe>,
+    /// The technology that `recipe` is built for
+    #[serde(skip)]
+    technology: Option<TechnologyTag>,
 }
 
 impl Lab {
@@ -45,10 +62,14 @@ impl Lab {
         Lab {
             position: *position,
             input_inventory: Inventory::new(),
+            module_inventory: Inventory::new(),
+            bonus_progress: 0.,
+            module_effect: ModuleEffect::default(),
             progress: None,
             power: 0.,
             max_power: 20.,
             recipe: None,
+            technology: None,
         }
     }
 }
@@ -189,54 +210,32 @@ impl Structure for Lab {
                 String::from("No recipe")
             },
             format!("Input Items: <br>{}", self.input_inventory.describe()),
-        )
+        ) + &self.module_effect.describe()
     }
 
     fn frame_proc(
         &mut self,
         me: StructureId,
         state: &mut FactorishState,
-        structures: &mut StructureDynIter,
+        _structures: &mut StructureDynIter,
     ) -> Result<FrameProcResult, ()> {
-        if let Some(ref research) = state.research {
-            if let Some(ref technology) = TECHNOLOGIES
-                .iter()
-                .find(|tech| tech.tag == research.technology)
-            {
-                self.recipe = Some(Recipe::new(
-                    technology.input.clone(),
-                    HashMap::new(),
-                    100.,
-                    100.,
-                ));
-            }
-        } else {
-            self.recipe = None;
+        // Lab speed researched is added to the speed bonus of the modules
+        self.module_effect = module_effect(state, me, &self.module_inventory).add(ModuleEffect {
+            speed: state.research_modifiers.lab_speed,
+            ..ModuleEffect::default()
+        });
+
+        // Each step of the research consumes the science packs in the input of the technology
+        let technology = state.research.as_ref().map(|research| research.technology);
+        if technology != self.technology || technology.is_none() {
+            self.technology = technology;
+            self.recipe = technology
+                .and_then(|tag| TECHNOLOGIES.iter().find(|tech| tech.tag == tag))
+                .map(|tech| Recipe::new(tech.input.clone(), HashMap::new(), 100., 100.));
         }
 
         if let Some(recipe) = &self.recipe {
             let mut ret = FrameProcResult::None;
-            // First, check if we need to refill the energy buffer in order to continue the current work.
-            // Refill the energy from the fuel
-            if self.power < recipe.power_cost {
-                let mut accumulated = 0.;
-                if let Some(network) = state
-                    .power_networks
-                    .iter()
-                    .find(|network| network.sinks.contains(&me))
-                {
-                    for id in network.sources.iter() {
-                        if let Some(source) = structures.get_mut(*id) {
-                            let demand = self.max_power - self.power - accumulated;
-                            if let Some(energy) = source.power_outlet(demand) {
-                                accumulated += energy;
-                                // console_log!("draining {:?}kJ of energy with {:?} demand, from {:?}, accumulated {:?}", energy, demand, structure.name(), accumulated);
-                            }
-                        }
-                    }
-                }
-                self.power += accumulated;
-            }
 
             if self.progress.is_none() {
                 // First, check if we have enough ingredients to finish this recipe.
@@ -260,24 +259,22 @@ impl Structure for Lab {
 
             if let Some(prev_progress) = self.progress {
                 // Proceed only if we have sufficient energy in the buffer.
-                let progress = get_powered_progress(self.power, prev_progress, &recipe);
+                let effect = self.module_effect;
+                let progress = get_module_progress(self.power, prev_progress, recipe, &effect);
                 if 1. <= prev_progress + progress {
                     self.progress = None;
 
-                    if let Some(research) = state.research.as_mut() {
-                        let tag = research.technology;
-                        if let Some(technology) = TECHNOLOGIES.iter().find(|tech| tech.tag == tag) {
-                            research.progress += 1;
-                            if technology.steps <= research.progress {
-                                state.research = None;
-                                state.unlocked_technologies.insert(tag);
-                            }
-                            ret = FrameProcResult::UpdateResearch;
-                        }
+                    let steps = if proceed_productivity(&mut self.bonus_progress, &effect) {
+                        2
+                    } else {
+                        1
+                    };
+                    if state.proceed_research(steps) {
+                        ret = FrameProcResult::UpdateResearch;
                     }
                 } else {
                     self.progress = Some(prev_progress + progress);
-                    self.power -= progress * recipe.power_cost;
+                    self.power -= progress * get_module_power_cost(recipe, &effect);
                 }
             }
             return Ok(ret);
@@ -286,19 +283,42 @@ impl Structure for Lab {
     }
 
     fn input(&mut self, o: &DropItem) -> Result<(), JsValue> {
-        if self.recipe.is_some() {
-            if 0 < default_add_inventory(self, InventoryType::Input, &o.type_, 1) {
-                return Ok(());
-            } else {
-                return Err(JsValue::from_str("Item is not part of recipe"));
-            }
+        if 0 < self.add_inventory(InventoryType::Input, &o.type_, 1) {
+            Ok(())
+        } else {
+            Err(JsValue::from_str(
+                "Item is not a science pack or the slot is full",
+            ))
+        }
+    }
+
+    fn can_input(&self, item_type: &ItemType) -> bool {
+        SCIENCE_PACKS.contains(item_type)
+            && self.input_inventory.count_item(item_type) < LAB_SLOT_SIZE
+    }
+
+    fn add_inventory(
+        &mut self,
+        inventory_type: InventoryType,
+        item_type: &ItemType,
+        count: isize,
+    ) -> isize {
+        if inventory_type != InventoryType::Input || count <= 0 {
+            return default_add_inventory(self, inventory_type, item_type, count);
+        }
+        if !SCIENCE_PACKS.contains(item_type) {
+            return 0;
         }
-        Err(JsValue::from_str("Recipe is not initialized"))
+        let count = count
+            .min(LAB_SLOT_SIZE.saturating_sub(self.input_inventory.count_item(item_type)) as isize);
+        self.input_inventory.add_items(item_type, count as usize);
+        count
     }
 
     fn inventory(&self, invtype: InventoryType) -> Option<&Inventory> {
         Some(match invtype {
             InventoryType::Input => &self.input_inventory,
+            InventoryType::Module => &self.module_inventory,
             _ => return None,
         })
     }
@@ -306,12 +326,14 @@ impl Structure for Lab {
     fn inventory_mut(&mut self, invtype: InventoryType) -> Option<&mut Inventory> {
         Some(match invtype {
             InventoryType::Input => &mut self.input_inventory,
+            InventoryType::Module => &mut self.module_inventory,
             _ => return None,
         })
     }
 
     fn destroy_inventory(&mut self) -> Inventory {
         let mut ret = std::mem::take(&mut self.input_inventory);
+        ret.merge(std::mem::take(&mut self.module_inventory));
         // Return the ingredients if it was in the middle of processing a recipe.
         if let Some(mut recipe) = self.recipe.take() {
             if self.progress.is_some() {
@@ -321,40 +343,6 @@ impl Structure for Lab {
         ret
     }
 
-    fn get_recipes(&self) -> std::borrow::Cow<[Recipe]> {
-        static RECIPES: once_cell::sync::Lazy<Vec<Recipe>> = once_cell::sync::Lazy::new(|| {
-            vec![Recipe::new(
-                hash_map!(ItemType::SciencePack1 => 1usize),
-                HashMap::new(),
-                50.,
-                150.,
-            )]
-        });
-
-        std::borrow::Cow::from(&RECIPES[..])
-    }
-
-    fn select_recipe(
-        &mut self,
-        index: usize,
-        player_inventory: &mut Inventory,
-    ) -> Result<bool, JsValue> {
-        let recipe = self
-            .get_recipes()
-            .get(index)
-            .ok_or_else(|| js_str!("recipes index out of bound {:?}", index))?
-            .clone();
-
-        self.input_inventory = filter_inventory(
-            std::mem::take(&mut self.input_inventory),
-            |item| recipe.input.contains_key(&item),
-            player_inventory,
-        );
-
-        self.recipe = Some(recipe);
-        Ok(true)
-    }
-
     fn get_selected_recipe(&self) -> Option<&Recipe> {
         self.recipe.as_ref()
     }
@@ -363,9 +351,75 @@ impl Structure for Lab {
         self.progress
     }
 
+    fn module_slots(&self) -> usize {
+        2
+    }
+
     fn power_sink(&self) -> bool {
         true
     }
 
+    fn power_demand(&self) -> f64 {
+        match &self.recipe {
+            Some(recipe) if self.power < recipe.power_cost => self.max_power - self.power,
+            _ => 0.,
+        }
+    }
+
+    fn power_inlet(&mut self, energy: f64) {
+        self.power += energy;
+    }
+
+    fn reset_for_blueprint(&mut self) {
+        self.power = 0.;
+    }
+
     serialize_impl!();
 }
+
+#[test]
+fn test_lab_research() {
+    use super::research::Research;
+
+    let mut state = FactorishState::new_test();
+    state.research = Some(Research {
+        technology: TechnologyTag::Transportation,
+        progress: 0,
+    });
+    let mut lab = Lab::new(&Position::new(0, 0));
+    let me = StructureId { id: 0, gen: 0 };
+
+    // Every kind of science pack is accepted into its own slot, even if the research does not
+    // consume it
+    assert_eq!(
+        lab.add_inventory(InventoryType::Input, &ItemType::SciencePack1, 15),
+        LAB_SLOT_SIZE as isize
+    );
+    assert_eq!(
+        lab.add_inventory(InventoryType::Input, &ItemType::ChemicalSciencePack, 3),
+        3
+    );
+    assert!(!lab.can_input(&ItemType::SciencePack1));
+    assert!(lab.can_input(&ItemType::SciencePack2));
+    assert!(!lab.can_input(&ItemType::IronPlate));
+
+    for _ in 0..1000 {
+        lab.power = lab.max_power;
+        lab.frame_proc(me, &mut state, &mut StructureDynIter::new_all(&mut []))
+            .unwrap();
+        if state.research.as_ref().unwrap().progress == 1 {
+            break;
+        }
+    }
+    // A step of the research consumes only the science packs of the technology
+    assert_eq!(state.research.as_ref().unwrap().progress, 1);
+    assert_eq!(
+        lab.input_inventory.count_item(&ItemType::SciencePack1),
+        LAB_SLOT_SIZE - 1
+    );
+    assert_eq!(
+        lab.input_inventory
+            .count_item(&ItemType::ChemicalSciencePack),
+        3
+    );
+}
@@ -0,0 +1,177 @@
+use super::{
+    circuit::{CircuitCondition, CircuitConfig, Signals},
+    gl::draw_flat_rect_gl,
+    structure::{Structure, StructureDynIter, StructureId},
+    FactorishState, FrameProcResult, Position, TILE_SIZE,
+};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+use web_sys::{CanvasRenderingContext2d, WebGlRenderingContext as GL};
+
+/// Energy in kilojoules consumed in a tick while lit
+const LAMP_POWER: f64 = 0.1;
+const LAMP_MAX_POWER: f64 = 1.;
+const LIGHT_COLOR: [f32; 3] = [1., 0.9, 0.6];
+
+/// A lamp lights up while it is powered and the optional circuit condition holds.
+/// The light is rendered as a glow that stands out at night.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct Lamp {
+    position: Position,
+    power: f64,
+    #[serde(default)]
+    circuit_condition: Option<CircuitCondition>,
+    #[serde(skip)]
+    circuit_disabled: bool,
+    #[serde(skip)]
+    lit: bool,
+}
+
+impl Lamp {
+    pub(crate) fn new(position: &Position) -> Self {
+        Self {
+            position: *position,
+            power: 0.,
+            circuit_condition: None,
+            circuit_disabled: false,
+            lit: false,
+        }
+    }
+}
+
+impl Structure for Lamp {
+    fn name(&self) -> &str {
+        "Lamp"
+    }
+
+    fn position(&self) -> &Position {
+        &self.position
+    }
+
+    fn draw(
+        &self,
+        _state: &FactorishState,
+        context: &CanvasRenderingContext2d,
+        depth: i32,
+        _is_toolbar: bool,
+    ) -> Result<(), JsValue> {
+        if depth != 0 {
+            return Ok(());
+        }
+        let (x, y) = (
+            self.position.x as f64 * TILE_SIZE,
+            self.position.y as f64 * TILE_SIZE,
+        );
+        context.set_fill_style(&js_str!("#4f4f5f"));
+        context.fill_rect(x + 4., y + 4., TILE_SIZE - 8., TILE_SIZE - 8.);
+        context.set_fill_style(&js_str!(if self.lit { "#ffe699" } else { "#7f7f6f" }));
+        context.fill_rect(x + 8., y + 8., TILE_SIZE - 16., TILE_SIZE - 16.);
+        Ok(())
+    }
+
+    fn draw_gl(
+        &self,
+        state: &FactorishState,
+        gl: &GL,
+        depth: i32,
+        is_ghost: bool,
+    ) -> Result<(), JsValue> {
+        if depth != 0 {
+            return Ok(());
+        }
+        let (x, y) = (
+            self.position.x as f32 + state.viewport.x as f32,
+            self.position.y as f32 + state.viewport.y as f32,
+        );
+        let alpha = if is_ghost { 0.5 } else { 1. };
+        draw_flat_rect_gl(
+            state,
+            gl,
+            (x + 0.1, y + 0.1, 0.8, 0.8),
+            &[0.3, 0.3, 0.35, alpha],
+        )?;
+        let [r, g, b] = if self.lit {
+            LIGHT_COLOR
+        } else {
+            [0.5, 0.5, 0.45]
+        };
+        draw_flat_rect_gl(state, gl, (x + 0.25, y + 0.25, 0.5, 0.5), &[r, g, b, alpha])?;
+        Ok(())
+    }
+
+    fn desc(&self, _state: &FactorishState) -> String {
+        format!(
+            "{}<br>Power: {:.1}kJ / {:.1}kJ",
+            if self.lit { "Lit" } else { "Off" },
+            self.power,
+            LAMP_MAX_POWER
+        )
+    }
+
+    fn frame_proc(
+        &mut self,
+        _me: StructureId,
+        _state: &mut FactorishState,
+        _structures: &mut StructureDynIter,
+    ) -> Result<FrameProcResult, ()> {
+        self.lit = !self.circuit_disabled && LAMP_POWER <= self.power;
+        if self.lit {
+            self.power -= LAMP_POWER;
+        }
+        Ok(FrameProcResult::None)
+    }
+
+    fn power_sink(&self) -> bool {
+        true
+    }
+
+    fn power_demand(&self) -> f64 {
+        if self.circuit_disabled {
+            0.
+        } else {
+            (LAMP_MAX_POWER - self.power).max(0.)
+        }
+    }
+
+    fn power_inlet(&mut self, energy: f64) {
+        self.power += energy;
+    }
+
+    fn light_color(&self) -> Option<[f32; 3]> {
+        if self.lit {
+            Some(LIGHT_COLOR)
+        } else {
+            None
+        }
+    }
+
+    fn circuit_connectable(&self) -> bool {
+        true
+    }
+
+    fn circuit_input(&mut self, signals: Option<&Signals>) {
+        self.circuit_disabled = signals
+            .zip(self.circuit_condition.as_ref())
+            .is_some_and(|(signals, condition)| !condition.evaluate(signals));
+    }
+
+    fn circuit_config(&self) -> Option<CircuitConfig> {
+        Some(CircuitConfig::Condition(self.circuit_condition.clone()))
+    }
+
+    fn set_circuit_config(&mut self, config: CircuitConfig) -> Result<(), JsValue> {
+        match config {
+            CircuitConfig::Condition(condition) => {
+                self.circuit_condition = condition;
+                Ok(())
+            }
+            _ => js_err!("Wrong circuit settings for a lamp"),
+        }
+    }
+
+    fn reset_for_blueprint(&mut self) {
+        self.power = 0.;
+    }
+
+    crate::serialize_impl!();
+}
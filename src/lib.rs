@@ -4,9 +4,18 @@
 #[macro_use]
 mod macros;
 
+mod accumulator;
 mod assembler;
+mod beacon;
+mod blueprint;
 mod boiler;
+mod character;
 mod chest;
+mod circuit;
+mod combinator;
+mod construction;
+mod crafting;
+mod deconstruction;
 mod drop_items;
 mod dyn_iter;
 mod elect_pole;
@@ -17,19 +26,31 @@ mod inserter;
 mod inventory;
 mod items;
 mod lab;
+mod lamp;
+mod loader;
+mod logistics;
 mod minimap;
+mod modules;
 mod offshore_pump;
 mod ore_mine;
 mod perf;
 mod perlin_noise;
 mod pipe;
 mod power_network;
+mod processing_plant;
+mod pump;
+mod pumpjack;
+mod rail;
 mod research;
+mod roboport;
 mod scenarios;
+mod solar_panel;
 mod splitter;
 mod steam_engine;
+mod storage_tank;
 mod structure;
 mod terrain;
+mod train;
 mod transport_belt;
 mod underground_belt;
 mod underground_pipe;
@@ -42,7 +63,7 @@ mod gl {
     pub(crate) mod utils;
 
     pub(crate) use render_gl::{
-        draw_direction_arrow_gl, draw_electricity_alarm_gl, draw_fuel_alarm_gl,
+        draw_direction_arrow_gl, draw_electricity_alarm_gl, draw_flat_rect_gl, draw_fuel_alarm_gl,
     };
     pub(crate) use shader_bundle::ShaderBundle;
 }
@@ -60,32 +81,61 @@ use crate::{
         TerrainParameters, CHUNK_SIZE, CHUNK_SIZE2, CHUNK_SIZE_I,
     },
 };
+use accumulator::Accumulator;
 use assembler::Assembler;
+use beacon::{collect_beacon_effects, Beacon};
+use blueprint::{AreaTool, Blueprint};
 use boiler::Boiler;
-use chest::Chest;
+use character::Character;
+use chest::{Chest, ChestTier};
+use circuit::{
+    build_circuit_networks, describe_signals, signal_names, update_circuit_networks, CircuitConfig,
+    CircuitNetwork, CircuitTerminal, SignalWire, WireColor,
+};
+use combinator::{Combinator, ConstantCombinator};
+use construction::{Ghost, GhostSerial};
+use crafting::{
+    cancel_craft, inventory_after_queue, is_hand_craftable, plan_crafts, proceed_crafting,
+    CraftJob, CraftJobSerial,
+};
 use dyn_iter::{Chained, DynIterMut, MutRef};
-use elect_pole::ElectPole;
+use elect_pole::{ElectPole, PoleTier};
 use electric_furnace::ElectricFurnace;
 use furnace::Furnace;
 use gen_set::{GenId, GenPayload, GenSet};
 use inserter::Inserter;
 use inventory::{Inventory, InventoryTrait, InventoryType, STACK_SIZE};
-use items::{item_to_str, str_to_item, ItemType};
+use items::{item_to_str, render_drop_item, str_to_item, ItemType};
 use lab::Lab;
+use lamp::Lamp;
+use loader::Loader;
+use logistics::{simulate_logistics, LogisticChestKind, LogisticRobot};
+use modules::ModuleEffect;
 use offshore_pump::OffshorePump;
 use ore_mine::OreMine;
 use perlin_noise::Xor128;
 use pipe::Pipe;
-use power_network::{build_power_networks, PowerNetwork};
-use research::{Research, ResearchSerial, TechnologySerial, TechnologyTag, TECHNOLOGIES};
+use power_network::{build_power_networks, distribute_power, supplied_by, PowerNetwork};
+use processing_plant::{PlantKind, ProcessingPlant};
+use pump::Pump;
+use pumpjack::Pumpjack;
+use rail::{Rail, RailShape, RailSignal, TrainStop};
+use research::{
+    Research, ResearchModifiers, ResearchSerial, Technology, TechnologySerial, TechnologyTag,
+    TECHNOLOGIES,
+};
+use roboport::Roboport;
+use solar_panel::{daylight, SolarPanel};
 use splitter::Splitter;
 use steam_engine::SteamEngine;
+use storage_tank::StorageTank;
 use structure::{
     FrameProcResult, ItemResponse, Position, RotateErr, Rotation, Structure, StructureBoxed,
     StructureDynIter, StructureEntry, StructureId,
 };
+use train::{simulate_trains, ScheduleEntry, Train, TrainCarKind};
 use transport_belt::TransportBelt;
-use underground_belt::{UnderDirection, UndergroundBelt};
+use underground_belt::{UnderDirection, UndergroundBelt, UndergroundBeltTier};
 use underground_pipe::UndergroundPipe;
 use water_well::{FluidType, WaterWell};
 
@@ -93,7 +143,7 @@ use serde::{Deserialize, Serialize};
 use serde_wasm_bindgen::{from_value, to_value};
 use std::hash::Hash;
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{HashMap, HashSet, VecDeque},
     convert::TryFrom,
 };
 use wasm_bindgen::prelude::*;
@@ -144,6 +194,8 @@ const WIRE_ATTACH_Y: f64 = 8.;
 const WIRE_HANG: f64 = 0.15;
 
 const SIM_DELTA_TIME: f64 = 1. / 60.;
+/// Maximum number of power wires connected to a structure
+const MAX_WIRES: usize = 5;
 
 /// Event types that can be communicated to the JavaScript code.
 /// It is serialized into a JavaScript Object through serde.
@@ -157,6 +209,7 @@ enum JSEvent {
     },
     UpdateStructureInventory(i32, i32),
     UpdateResearch,
+    UpdateBlueprint,
 }
 
 #[derive(Copy, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Debug)]
@@ -185,6 +238,9 @@ struct OreValue(Ore, u32);
 struct Cell {
     water: bool,
     ore: Option<OreValue>,
+    /// Yield of crude oil deposit in percent, which pumpjacks extract without depleting
+    #[serde(default)]
+    oil: u32,
     #[serde(skip)]
     image: u8,
     #[serde(skip)]
@@ -196,6 +252,7 @@ impl Default for Cell {
         Cell {
             water: false,
             ore: None,
+            oil: 0,
             image: 0,
             grass_image: 0,
         }
@@ -218,7 +275,7 @@ struct ToolDef {
     item_type: ItemType,
     desc: &'static str,
 }
-const tool_defs: [ToolDef; 16] = [
+const tool_defs: [ToolDef; 51] = [
     ToolDef {
         item_type: ItemType::TransportBelt,
         desc: "Transports items on ground",
@@ -227,6 +284,10 @@ const tool_defs: [ToolDef; 16] = [
         item_type: ItemType::Inserter,
         desc: "Picks items from one side and puts on the other side<br>in the direction indicated by an arrow.<br>Costs no energy to operate.",
     },
+    ToolDef {
+        item_type: ItemType::ElectricInserter,
+        desc: "Picks items from one side and puts on the other side<br>in the direction indicated by an arrow.<br>Requires electricity to operate.",
+    },
     ToolDef {
         item_type: ItemType::Splitter,
         desc: "Connects to transport belt. Splits inputs and outputs into two lanes.",
@@ -235,9 +296,21 @@ const tool_defs: [ToolDef; 16] = [
         item_type: ItemType::OreMine,
         desc: "Mines ores and puts them to adjacent ground<br>or a structure in the direction indicated by an arrow.<br>Requires coal ores to operate.",
     },
+    ToolDef {
+        item_type: ItemType::ElectricMiningDrill,
+        desc: "Mines ores in a 5x5 area and puts them to adjacent ground<br>or a structure in the direction indicated by an arrow.<br>Requires electricity to operate.",
+    },
     ToolDef {
         item_type: ItemType::Chest,
-        desc: "Can store 100 items.<br>Use inserters to automatically store/retrieve items.",
+        desc: "Can store 16 stacks of items.<br>Use inserters to automatically store/retrieve items.",
+    },
+    ToolDef {
+        item_type: ItemType::IronChest,
+        desc: "Can store 32 stacks of items.<br>Use inserters to automatically store/retrieve items.",
+    },
+    ToolDef {
+        item_type: ItemType::SteelChest,
+        desc: "Can store 48 stacks of items.<br>Use inserters to automatically store/retrieve items.",
     },
     ToolDef {
         item_type: ItemType::Furnace,
@@ -277,12 +350,136 @@ const tool_defs: [ToolDef; 16] = [
     },
     ToolDef {
         item_type: ItemType::ElectPole,
-        desc: "Electric pole.",
+        desc: "Wires reach 5 tiles.<br>Powers structures within 2 tiles without wires.",
+    },
+    ToolDef {
+        item_type: ItemType::Loader,
+        desc: "Loads items on a belt into the structure in front, or unloads them onto a belt<br>as fast as the belt moves. Rotate to switch between loading and unloading.",
     },
     ToolDef {
         item_type: ItemType::UndergroundBelt,
         desc: "Underground belt can connect transport belts without blocking other structures in between.",
     },
+    ToolDef {
+        item_type: ItemType::FastUndergroundBelt,
+        desc: "Underground belt with longer reach of 6 tiles.<br>Only pairs with another fast underground belt.",
+    },
+    ToolDef {
+        item_type: ItemType::Roboport,
+        desc: "Defines the coverage area of a logistic network and launches logistic robots in it.<br>Put logistic robots in its inventory.<br>Requires electricity to operate.",
+    },
+    ToolDef {
+        item_type: ItemType::PassiveProviderChest,
+        desc: "Offers its contents to requester chests in the logistic network.",
+    },
+    ToolDef {
+        item_type: ItemType::ActiveProviderChest,
+        desc: "Offers its contents to requester chests in the logistic network,<br>and robots take the rest away to storage chests.",
+    },
+    ToolDef {
+        item_type: ItemType::StorageChest,
+        desc: "Stores items brought by logistic robots and offers them to requester chests.",
+    },
+    ToolDef {
+        item_type: ItemType::RequesterChest,
+        desc: "Logistic robots deliver requested items to this chest.",
+    },
+    ToolDef {
+        item_type: ItemType::Rail,
+        desc: "Straight rail that trains run on.<br>Rotate to change the direction.",
+    },
+    ToolDef {
+        item_type: ItemType::CurvedRail,
+        desc: "Rail that turns by 90 degrees.<br>Rotate to change the direction.",
+    },
+    ToolDef {
+        item_type: ItemType::RailSignal,
+        desc: "Straight rail with a signal that divides the track into blocks.<br>A train waits at the signal while another train occupies the block beyond.",
+    },
+    ToolDef {
+        item_type: ItemType::TrainStop,
+        desc: "Straight rail with a station that train schedules refer to.<br>Inserters can put items into or take items from its buffer.<br>Rotate to switch between loading and unloading trains.",
+    },
+    ToolDef {
+        item_type: ItemType::Locomotive,
+        desc: "Place on a rail to start a new train heading to the tool direction.<br>Set a schedule to make the train run.",
+    },
+    ToolDef {
+        item_type: ItemType::CargoWagon,
+        desc: "Place on a rail right behind a train to couple it.<br>Carries items between train stops.",
+    },
+    ToolDef {
+        item_type: ItemType::StorageTank,
+        desc: "Stores a large amount of fluid.<br>Connects to pipes on any side.",
+    },
+    ToolDef {
+        item_type: ItemType::Pump,
+        desc: "Moves fluid from behind to the front at a fixed rate.<br>Rotate a placed pump to turn it on or off.<br>Requires electricity to operate.",
+    },
+    ToolDef {
+        item_type: ItemType::Pumpjack,
+        desc: "Extracts crude oil from an oil deposit.<br>Requires electricity to operate.",
+    },
+    ToolDef {
+        item_type: ItemType::OilRefinery,
+        desc: "Refines crude oil into petroleum gas, heavy oil or light oil.<br>Takes fluid from the back and outputs to the front.<br>Requires electricity to operate.",
+    },
+    ToolDef {
+        item_type: ItemType::ChemicalPlant,
+        desc: "Makes plastic and sulfur, or cracks oil products.<br>Takes fluid from the back and outputs to the front.<br>Requires electricity to operate.",
+    },
+    ToolDef {
+        item_type: ItemType::Beacon,
+        desc: "Transmits the effects of its modules to machines within 3 tiles.<br>Does not accept productivity modules.<br>Requires electricity to operate.",
+    },
+    ToolDef {
+        item_type: ItemType::SolarPanel,
+        desc: "Generates electricity from the sunlight without fuel.<br>The output follows the day and night cycle.",
+    },
+    ToolDef {
+        item_type: ItemType::Accumulator,
+        desc: "Stores surplus electricity of the power network<br>and discharges it when the generators cannot meet the demand.",
+    },
+    ToolDef {
+        item_type: ItemType::CopperWire,
+        desc: "Click two electric poles or structures in reach to connect them with a wire.<br>Click two connected ones to disconnect them.",
+    },
+    ToolDef {
+        item_type: ItemType::MediumElectPole,
+        desc: "Wires reach 9 tiles.<br>Powers structures within 3 tiles without wires.",
+    },
+    ToolDef {
+        item_type: ItemType::BigElectPole,
+        desc: "Wires reach 30 tiles to carry electricity over long distances.<br>Powers structures within 1 tile without wires.",
+    },
+    ToolDef {
+        item_type: ItemType::Substation,
+        desc: "Wires reach 18 tiles.<br>Powers structures within 8 tiles without wires.",
+    },
+    ToolDef {
+        item_type: ItemType::RedWire,
+        desc: "Click two structures in reach to connect them to a red circuit network.<br>Click two connected ones to disconnect them.",
+    },
+    ToolDef {
+        item_type: ItemType::GreenWire,
+        desc: "Click two structures in reach to connect them to a green circuit network.<br>Click two connected ones to disconnect them.",
+    },
+    ToolDef {
+        item_type: ItemType::ConstantCombinator,
+        desc: "Sends constant signals to the circuit networks.",
+    },
+    ToolDef {
+        item_type: ItemType::ArithmeticCombinator,
+        desc: "Calculates a signal from the input on the back<br>and sends the result from the front.",
+    },
+    ToolDef {
+        item_type: ItemType::DeciderCombinator,
+        desc: "Sends a signal from the front when the input on the back<br>meets the condition.",
+    },
+    ToolDef {
+        item_type: ItemType::Lamp,
+        desc: "Lights up while powered, or only while the circuit condition<br>is met if it is connected to a circuit network.",
+    },
 ];
 
 fn draw_direction_arrow(
@@ -311,8 +508,14 @@ type ItemSet = HashMap<ItemType, usize>;
 struct Recipe {
     input: ItemSet,
     input_fluid: Option<FluidType>,
+    /// Amount of the input fluid consumed by a craft
+    #[serde(default)]
+    input_fluid_amount: f64,
     output: ItemSet,
     output_fluid: Option<FluidType>,
+    /// Amount of the output fluid produced by a craft
+    #[serde(default)]
+    output_fluid_amount: f64,
     power_cost: f64,
     recipe_time: f64,
     #[serde(default)]
@@ -324,8 +527,10 @@ impl Recipe {
         Recipe {
             input,
             input_fluid: None,
+            input_fluid_amount: 0.,
             output,
             output_fluid: None,
+            output_fluid_amount: 0.,
             power_cost,
             recipe_time,
             requires_technology: HashSet::new(),
@@ -342,13 +547,32 @@ impl Recipe {
         Recipe {
             input,
             input_fluid: None,
+            input_fluid_amount: 0.,
             output,
             output_fluid: None,
+            output_fluid_amount: 0.,
             power_cost,
             recipe_time,
             requires_technology,
         }
     }
+
+    /// Set the fluid ingredient and product with their amounts per craft.
+    fn with_fluids(
+        mut self,
+        input_fluid: Option<(FluidType, f64)>,
+        output_fluid: Option<(FluidType, f64)>,
+    ) -> Self {
+        if let Some((fluid, amount)) = input_fluid {
+            self.input_fluid = Some(fluid);
+            self.input_fluid_amount = amount;
+        }
+        if let Some((fluid, amount)) = output_fluid {
+            self.output_fluid = Some(fluid);
+            self.output_fluid_amount = amount;
+        }
+        self
+    }
 }
 
 #[derive(Serialize)]
@@ -364,17 +588,48 @@ impl From<(usize, Recipe)> for RecipeSerial {
     fn from((index, o): (usize, Recipe)) -> Self {
         Self {
             index,
-            input: o.input.iter().map(|(k, v)| (item_to_str(k), *v)).collect(),
-            output: o.output.iter().map(|(k, v)| (item_to_str(k), *v)).collect(),
+            // Fluids are listed along with items by their names, so that the recipe selector
+            // can show them.
+            input: o
+                .input
+                .iter()
+                .map(|(k, v)| (item_to_str(k), *v))
+                .chain(
+                    o.input_fluid
+                        .map(|f| (f.name().to_string(), o.input_fluid_amount as usize)),
+                )
+                .collect(),
+            output: o
+                .output
+                .iter()
+                .map(|(k, v)| (item_to_str(k), *v))
+                .chain(
+                    o.output_fluid
+                        .map(|f| (f.name().to_string(), o.output_fluid_amount as usize)),
+                )
+                .collect(),
             power_cost: o.power_cost,
             recipe_time: o.recipe_time,
         }
     }
 }
 
+#[derive(Serialize)]
+struct StorageConfigSerial {
+    slots: usize,
+    bar: Option<usize>,
+    filters: Vec<Option<String>>,
+}
+
 #[derive(Serialize, Deserialize)]
 struct Player {
     inventory: Inventory,
+    /// Hand crafts in the order to be crafted
+    #[serde(default)]
+    crafting_queue: VecDeque<CraftJob>,
+    /// The character walking on the map, only in the character mode
+    #[serde(default)]
+    character: Option<Character>,
 }
 
 impl Player {
@@ -538,12 +793,37 @@ pub struct FactorishState {
     drop_items_index: DropItemIndex,
     tool_belt: [Option<ItemType>; 10],
     power_networks: Vec<PowerNetwork>,
+    circuit_networks: Vec<CircuitNetwork>,
+    /// Sum of the beacon effects on each machine, updated every tick
+    beacon_effects: HashMap<StructureId, ModuleEffect>,
     unlocked_technologies: HashSet<TechnologyTag>,
     pending_researches: HashMap<TechnologyTag, usize>,
     research: Option<Research>,
+    /// Technologies to be researched in order after the current research completes
+    research_queue: Vec<TechnologyTag>,
+    /// Number of completed levels of each repeatable technology
+    research_levels: HashMap<TechnologyTag, usize>,
+    /// Effects of the researched technologies, updated when a research completes
+    research_modifiers: ResearchModifiers,
+    logistic_robots: Vec<LogisticRobot>,
+    trains: Vec<Train>,
+    /// Structures planned to be built, in the order to build them
+    ghosts: Vec<Ghost>,
+    /// Structures marked for deconstruction, in the order to remove them
+    deconstruction_marks: Vec<StructureId>,
 
     selected_item: Option<SelectedItem>,
     ore_harvesting: Option<OreHarvesting>,
+    /// The structure clicked first with the wire tool
+    wire_start: Option<StructureId>,
+    /// The terminal clicked first with a signal wire tool
+    signal_wire_start: Option<(WireColor, CircuitTerminal)>,
+    /// The tool to select an area with, instead of building
+    area_tool: Option<AreaTool>,
+    /// The corner of the area where the player started dragging an area tool
+    area_start: Option<Position>,
+    /// The blueprint held by the player to paste
+    blueprint: Option<Blueprint>,
 
     tool_rotation: Rotation,
     player: Player,
@@ -558,6 +838,10 @@ pub struct FactorishState {
     on_structure_destroy: js_sys::Function,
     minimap_buffer: Vec<u8>,
     power_wires: Vec<PowerWire>,
+    /// Power wires connected with the wire tool, which return a copper wire when removed.
+    /// The ones connected automatically on construction are free.
+    hand_power_wires: HashSet<PowerWire>,
+    signal_wires: Vec<SignalWire>,
     alt_mode: bool,
     debug_bbox: bool,
     debug_fluidbox: bool,
@@ -632,14 +916,32 @@ impl FactorishState {
 
         let terrain_params: TerrainParameters = from_value(terrain_params)?;
 
+        let scenario = select_scenario(scenario, &terrain_params)?;
+
+        Self::with_assets(
+            terrain_params,
+            on_player_update,
+            on_popup_text,
+            on_structure_destroy,
+            scenario,
+            Assets::new(&context, assets)?,
+        )
+    }
+
+    fn with_assets(
+        terrain_params: TerrainParameters,
+        on_player_update: js_sys::Function,
+        on_popup_text: js_sys::Function,
+        on_structure_destroy: js_sys::Function,
+        (structures, board, drop_items): (Vec<StructureEntry>, Chunks, GenSet<DropItem>),
+        assets: Assets,
+    ) -> Result<FactorishState, JsValue> {
         let mut tool_belt = [None; 10];
         tool_belt[0] = Some(ItemType::OreMine);
         tool_belt[1] = Some(ItemType::Inserter);
         tool_belt[2] = Some(ItemType::TransportBelt);
         tool_belt[3] = Some(ItemType::Furnace);
 
-        let (structures, board, drop_items) = select_scenario(scenario, &terrain_params)?;
-
         let mut ret = FactorishState {
             delta_time: 0.1,
             sim_time: 0.0,
@@ -682,11 +984,17 @@ impl FactorishState {
                 .iter()
                 .copied()
                 .collect(),
+                crafting_queue: VecDeque::new(),
+                character: None,
             },
             info_elem: None,
             minimap_buffer: vec![],
             power_wires: vec![],
+            hand_power_wires: HashSet::new(),
             power_networks: vec![],
+            signal_wires: vec![],
+            circuit_networks: vec![],
+            beacon_effects: HashMap::new(),
             alt_mode: false,
             debug_bbox: false,
             debug_fluidbox: false,
@@ -733,18 +1041,30 @@ impl FactorishState {
             structures,
             selected_structure_inventory: None,
             ore_harvesting: None,
+            wire_start: None,
+            signal_wire_start: None,
+            area_tool: None,
+            area_start: None,
+            blueprint: None,
             drop_items,
             drop_items_index: DropItemIndex::default(),
             unlocked_technologies: hash_set!(),
             pending_researches: hash_map!(),
             research: None,
+            research_queue: vec![],
+            research_levels: hash_map!(),
+            research_modifiers: ResearchModifiers::default(),
+            logistic_robots: vec![],
+            trains: vec![],
+            ghosts: vec![],
+            deconstruction_marks: vec![],
             on_player_update,
             on_popup_text,
             on_structure_destroy,
             temp_ents: vec![],
             rng: Xor128::new(3142125),
             // on_show_inventory,
-            assets: Assets::new(&context, assets)?,
+            assets,
         };
 
         ret.update_cache()?;
@@ -833,6 +1153,27 @@ impl FactorishState {
             )
             .map_err(|e| js_str!("Serialize error: {}", e))?,
         );
+        map.insert(
+            "hand_power_wires".to_string(),
+            serde_json::to_value(
+                self.hand_power_wires
+                    .iter()
+                    .filter_map(|w| Some((id_to_index.get(&w.0)?, id_to_index.get(&w.1)?)))
+                    .collect::<Vec<_>>(),
+            )
+            .map_err(|e| js_str!("Serialize error: {}", e))?,
+        );
+        let terminal_to_index = |t: &CircuitTerminal| Some((*id_to_index.get(&t.id)?, t.output));
+        map.insert(
+            "signal_wires".to_string(),
+            serde_json::to_value(
+                self.signal_wires
+                    .iter()
+                    .filter_map(|w| Some((w.0, terminal_to_index(&w.1)?, terminal_to_index(&w.2)?)))
+                    .collect::<Vec<_>>(),
+            )
+            .map_err(|e| js_str!("Serialize error: {}", e))?,
+        );
 
         map.insert("items".to_string(), self.drop_items.serialize_json()?);
         map.insert(
@@ -852,7 +1193,9 @@ impl FactorishState {
                                 .cells
                                 .iter()
                                 .enumerate()
-                                .filter(|(_, cell)| cell.ore.is_some() || cell.water)
+                                .filter(|(_, cell)| {
+                                    cell.ore.is_some() || cell.water || 0 < cell.oil
+                                })
                                 .map(|(idx, cell)| {
                                     let mut map = serde_json::Map::new();
                                     let x = idx % self.width as usize;
@@ -893,6 +1236,43 @@ impl FactorishState {
                 .map_err(|e| js_str!("pending_researches serialize error: {:?}", e))?,
         );
 
+        map.insert(
+            "research_queue".to_string(),
+            serde_json::to_value(&self.research_queue)
+                .map_err(|e| js_str!("research_queue serialize error: {:?}", e))?,
+        );
+
+        map.insert(
+            "research_levels".to_string(),
+            serde_json::to_value(&self.research_levels)
+                .map_err(|e| js_str!("research_levels serialize error: {:?}", e))?,
+        );
+
+        map.insert(
+            "logistic_robots".to_string(),
+            serde_json::to_value(&self.logistic_robots)
+                .map_err(|e| js_str!("logistic_robots serialize error: {:?}", e))?,
+        );
+
+        map.insert(
+            "trains".to_string(),
+            serde_json::to_value(&self.trains)
+                .map_err(|e| js_str!("trains serialize error: {:?}", e))?,
+        );
+
+        map.insert("ghosts".to_string(), self.serialize_ghosts()?);
+
+        map.insert(
+            "deconstruction_marks".to_string(),
+            serde_json::to_value(
+                self.deconstruction_marks
+                    .iter()
+                    .filter_map(|id| id_to_index.get(id))
+                    .collect::<Vec<_>>(),
+            )
+            .map_err(|e| js_str!("deconstruction_marks serialize error: {:?}", e))?,
+        );
+
         serde_json::to_string(&map).map_err(|e| js_str!("Serialize error: {}", e))
     }
 
@@ -1067,6 +1447,38 @@ impl FactorishState {
         self.power_networks = build_power_networks(&s_d_iter, &self.power_wires);
         drop(s_d_iter);
 
+        self.hand_power_wires = if let Some(hand_power_wires) = json.get_mut("hand_power_wires") {
+            serde_json::from_value::<Vec<(u32, u32)>>(hand_power_wires.take())
+                .map_err(|e| js_str!("hand_power_wires deserialization error: {}", e))?
+                .into_iter()
+                .map(|w| {
+                    PowerWire(
+                        StructureId { id: w.0, gen: 0 },
+                        StructureId { id: w.1, gen: 0 },
+                    )
+                })
+                .collect()
+        } else {
+            HashSet::new()
+        };
+
+        self.signal_wires = if let Some(signal_wires) = json.get_mut("signal_wires") {
+            let terminal = |(id, output)| CircuitTerminal {
+                id: StructureId { id, gen: 0 },
+                output,
+            };
+            serde_json::from_value::<Vec<(WireColor, (u32, bool), (u32, bool))>>(
+                signal_wires.take(),
+            )
+            .map_err(|e| js_str!("signal_wires deserialization error: {}", e))?
+            .into_iter()
+            .map(|w| SignalWire(w.0, terminal(w.1), terminal(w.2)))
+            .collect()
+        } else {
+            vec![]
+        };
+        self.circuit_networks = build_circuit_networks(&self.signal_wires);
+
         self.drop_items = GenSet::deserialize_json(json_take(&mut json, "items")?)?;
 
         self.drop_items_index = build_index(&self.drop_items);
@@ -1085,12 +1497,52 @@ impl FactorishState {
             self.pending_researches = hash_map!();
         }
 
+        if let Ok(logistic_robots) = json_take(&mut json, "logistic_robots") {
+            self.logistic_robots = from_value(logistic_robots)?;
+        } else {
+            self.logistic_robots = vec![];
+        }
+
+        if let Ok(trains) = json_take(&mut json, "trains") {
+            self.trains = from_value(trains)?;
+        } else {
+            self.trains = vec![];
+        }
+
+        if let Ok(ghosts) = json_take(&mut json, "ghosts") {
+            self.ghosts = Self::deserialize_ghosts(ghosts)?;
+        } else {
+            self.ghosts = vec![];
+        }
+
+        if let Ok(deconstruction_marks) = json_take(&mut json, "deconstruction_marks") {
+            self.deconstruction_marks = from_value::<Vec<u32>>(deconstruction_marks)?
+                .into_iter()
+                .map(|id| StructureId { id, gen: 0 })
+                .collect();
+        } else {
+            self.deconstruction_marks = vec![];
+        }
+
         if let Ok(research) = json_take(&mut json, "research") {
             self.research = from_value(research)?;
         } else {
             self.research = None;
         }
 
+        if let Ok(research_queue) = json_take(&mut json, "research_queue") {
+            self.research_queue = from_value(research_queue)?;
+        } else {
+            self.research_queue = vec![];
+        }
+
+        if let Ok(research_levels) = json_take(&mut json, "research_levels") {
+            self.research_levels = from_value(research_levels)?;
+        } else {
+            self.research_levels = hash_map!();
+        }
+        self.update_research_modifiers();
+
         // Redraw minimap
         self.render_minimap_data()?;
 
@@ -1234,8 +1686,8 @@ impl FactorishState {
                         let a_con = a.1.fluid_connections();
                         let b_con = b.1.fluid_connections();
                         if let Some(((idx, mut av), mut bv)) =
-                            a.1.position()
-                                .neighbor_index(b.1.position())
+                            a.1.bounding_box()
+                                .neighbor_index(&b.1.bounding_box())
                                 .filter(|f| a_con[*f as usize] && b_con[(*f as usize + 2) % 4])
                                 .zip(a.1.fluid_box_mut())
                                 .zip(b.1.fluid_box_mut())
@@ -1314,6 +1766,22 @@ impl FactorishState {
         // we need to accumulate events during simulation and return them as an array.
         let mut events = vec![];
 
+        if proceed_crafting(&mut self.player.crafting_queue, &mut self.player.inventory) {
+            events.push(to_value(&JSEvent::UpdatePlayerInventory).unwrap());
+        }
+
+        if self.character_step() {
+            events.push(to_value(&JSEvent::UpdatePlayerInventory).unwrap());
+        }
+
+        if self.construction_step()? {
+            events.push(to_value(&JSEvent::UpdatePlayerInventory).unwrap());
+        }
+
+        if self.deconstruction_step()? {
+            events.push(to_value(&JSEvent::UpdatePlayerInventory).unwrap());
+        }
+
         let mut frame_proc_result_to_event = |result: Result<FrameProcResult, ()>| match result {
             Ok(FrameProcResult::None) => (),
             Ok(FrameProcResult::InventoryChanged(pos)) => {
@@ -1376,6 +1844,11 @@ impl FactorishState {
         // This is silly way to avoid borrow checker that temporarily move the structures
         // away from self so that they do not claim mutable borrow twice, but it works.
         let mut structures = std::mem::take(&mut self.structures);
+        self.beacon_effects = collect_beacon_effects(&structures);
+        distribute_power(
+            &mut self.power_networks,
+            &mut StructureDynIter::new_all(&mut structures),
+        );
         for i in 0..structures.len() {
             let (center, mut dyn_iter) = StructureDynIter::new(&mut structures, i)?;
             if let Some(dynamic) = center.dynamic.as_deref_mut() {
@@ -1391,6 +1864,18 @@ impl FactorishState {
                 );
             }
         }
+        update_circuit_networks(
+            &mut self.circuit_networks,
+            &mut StructureDynIter::new_all(&mut structures),
+        );
+        for result in
+            simulate_logistics(&mut self.logistic_robots, &mut structures, &mut self.ghosts)
+        {
+            frame_proc_result_to_event(Ok(result));
+        }
+        for result in simulate_trains(&mut self.trains, &mut structures, delta_time) {
+            frame_proc_result_to_event(Ok(result));
+        }
         self.perf_structures
             .add(performance().now() - start_structures);
 
@@ -1489,6 +1974,107 @@ impl FactorishState {
         }
     }
 
+    /// The power network that the structure at the given position belongs to
+    fn power_network_at(&self, position: &Position) -> Option<&PowerNetwork> {
+        let (id, _) = self
+            .structure_id_iter()
+            .find(|(_, s)| s.position() == position)?;
+        self.power_networks
+            .iter()
+            .find(|network| network.sinks.contains(&id) || network.sources.contains(&id))
+    }
+
+    /// Brightness of the scene by the day and night cycle, used to darken the rendering at night
+    fn lighting(&self) -> f64 {
+        0.35 + 0.65 * daylight(self.sim_time)
+    }
+
+    /// Ratio of ores mined without depleting the deposit, granted by research
+    fn mining_productivity(&self) -> f64 {
+        self.research_modifiers.mining_productivity
+    }
+
+    fn update_research_modifiers(&mut self) {
+        self.research_modifiers = ResearchModifiers::new(|tag| self.technology_level(tag));
+    }
+
+    /// Number of completed levels of a technology. Non-repeatable technologies have at most 1.
+    fn technology_level(&self, tag: TechnologyTag) -> usize {
+        self.research_levels.get(&tag).copied().unwrap_or_else(|| {
+            if self.unlocked_technologies.contains(&tag) {
+                1
+            } else {
+                0
+            }
+        })
+    }
+
+    /// An item is unlocked if any recipe producing it is unlocked, or no recipe produces it.
+    fn is_item_unlocked(&self, item: &ItemType) -> bool {
+        let recipes = Assembler::get_recipes();
+        let mut producers = recipes
+            .iter()
+            .chain(furnace::RECIPES.iter())
+            .filter(|recipe| recipe.output.contains_key(item))
+            .peekable();
+        producers.peek().is_none()
+            || producers.any(|recipe| {
+                recipe
+                    .requires_technology
+                    .iter()
+                    .all(|tech| self.unlocked_technologies.contains(tech))
+            })
+    }
+
+    fn can_research(&self, tech: &Technology) -> bool {
+        tech.repeatable || !self.unlocked_technologies.contains(&tech.tag)
+    }
+
+    /// Add research steps made by a lab to the current research. When it completes, the technology
+    /// is unlocked and the next one in the queue is started.
+    /// @returns true if there is a research in progress.
+    fn proceed_research(&mut self, steps: usize) -> bool {
+        let Some(tag) = self.research.as_ref().map(|research| research.technology) else {
+            return false;
+        };
+        let level = self.technology_level(tag);
+        let Some(technology) = TECHNOLOGIES.iter().find(|tech| tech.tag == tag) else {
+            return false;
+        };
+        if let Some(research) = self.research.as_mut() {
+            research.progress += steps;
+            if research.progress < technology.steps_at(level) {
+                return true;
+            }
+        }
+        if technology.repeatable {
+            self.research_levels.insert(tag, level + 1);
+        }
+        self.unlocked_technologies.insert(tag);
+        self.update_research_modifiers();
+        self.pending_researches.remove(&tag);
+        self.research = None;
+        self.start_next_research();
+        true
+    }
+
+    /// Start the first technology in the queue that can still be researched.
+    fn start_next_research(&mut self) {
+        while !self.research_queue.is_empty() {
+            let tag = self.research_queue.remove(0);
+            if TECHNOLOGIES
+                .iter()
+                .any(|tech| tech.tag == tag && self.can_research(tech))
+            {
+                self.research = Some(Research {
+                    technology: tag,
+                    progress: self.pending_researches.remove(&tag).unwrap_or(0),
+                });
+                return;
+            }
+        }
+    }
+
     fn tile_at_mut(&mut self, tile: &Position) -> Option<&mut Cell> {
         let (chunk_pos, mp) = tile.div_mod(CHUNK_SIZE as i32);
         let chunk = self.board.get_mut(&chunk_pos)?;
@@ -1616,28 +2202,33 @@ impl FactorishState {
     fn update_info(&self) {
         if let Some(cursor) = self.cursor {
             if let Some(ref elem) = self.info_elem {
-                elem.set_inner_html(
-                    &if let Some(structure) = self.find_structure_tile(&cursor) {
-                        format!(r#"Type: {}<br>{}"#, structure.name(), structure.desc(&self))
-                    } else {
-                        let (chunk_pos, mp) =
-                            Position::new(cursor[0], cursor[1]).div_mod(CHUNK_SIZE as i32);
-                        if let Some(chunk) = self.board.get(&chunk_pos) {
-                            let cell = &chunk.cells[mp.x as usize + mp.y as usize * CHUNK_SIZE];
-                            format!(
-                                r#"Empty tile<br>
+                elem.set_inner_html(&if let Some((id, structure)) =
+                    self.find_structure_tile_id(&cursor)
+                {
+                    format!(
+                        r#"Type: {}<br>{}<br>{}"#,
+                        structure.name(),
+                        structure.desc(self),
+                        self.circuit_desc(id, structure)
+                    )
+                } else {
+                    let (chunk_pos, mp) =
+                        Position::new(cursor[0], cursor[1]).div_mod(CHUNK_SIZE as i32);
+                    if let Some(chunk) = self.board.get(&chunk_pos) {
+                        let cell = &chunk.cells[mp.x as usize + mp.y as usize * CHUNK_SIZE];
+                        format!(
+                            r#"Empty tile<br>
                                 {}<br>"#,
-                                if let Some(ore) = cell.ore.as_ref() {
-                                    format!("{:?}: {}", ore.0, ore.1)
-                                } else {
-                                    "No ore".to_string()
-                                }
-                            )
-                        } else {
-                            "Empty tile".to_string()
-                        }
-                    },
-                );
+                            if let Some(ore) = cell.ore.as_ref() {
+                                format!("{:?}: {}", ore.0, ore.1)
+                            } else {
+                                "No ore".to_string()
+                            }
+                        )
+                    } else {
+                        "Empty tile".to_string()
+                    }
+                });
             }
         }
     }
@@ -1674,7 +2265,8 @@ impl FactorishState {
     }
 
     /// Insert an object on the board.  It could fail if there's already some object at the position.
-    fn new_object(&mut self, pos: &Position, type_: ItemType) -> Result<(), NewObjectErr> {
+    /// Check if an item can be dropped at the position, without dropping it.
+    fn can_place_object(&self, pos: &Position) -> Result<(), NewObjectErr> {
         let cell = self.tile_at(pos).ok_or(NewObjectErr::OutOfMap)?;
         if cell.water {
             return Err(NewObjectErr::OnWater);
@@ -1689,11 +2281,18 @@ impl FactorishState {
                 return Err(NewObjectErr::BlockedByStructure);
             }
         }
-        let item = DropItem::new(type_, pos.x, pos.y);
-        // return board[c + r * ysize].structure.input(obj);
-        if hit_check(&self.drop_items, item.x, item.y, None) {
+        let x = pos.x as f64 * TILE_SIZE + TILE_SIZE / 2.;
+        let y = pos.y as f64 * TILE_SIZE + TILE_SIZE / 2.;
+        if hit_check(&self.drop_items, x, y, None) {
             return Err(NewObjectErr::BlockedByItem);
         }
+        Ok(())
+    }
+
+    fn new_object(&mut self, pos: &Position, type_: ItemType) -> Result<(), NewObjectErr> {
+        self.can_place_object(pos)?;
+        let item = DropItem::new(type_, pos.x, pos.y);
+        // return board[c + r * ysize].structure.input(obj);
         let (x, y) = (item.x, item.y);
         let id = self.drop_items.add(item);
         add_index(&mut self.drop_items_index, id, x, y);
@@ -1747,10 +2346,29 @@ impl FactorishState {
             self.structures = structures;
 
             let position = *structure.position();
-            self.power_wires = std::mem::take(&mut self.power_wires)
-                .into_iter()
-                .filter(|power_wire| power_wire.0.id != i as u32 && power_wire.1.id != i as u32)
-                .collect();
+            let (removed_wires, power_wires): (Vec<_>, Vec<_>) =
+                std::mem::take(&mut self.power_wires)
+                    .into_iter()
+                    .partition(|wire| wire.0.id == i as u32 || wire.1.id == i as u32);
+            self.power_wires = power_wires;
+            // Only the power wires placed by hand are returned, like the signal wires below
+            for wire in removed_wires {
+                if self.hand_power_wires.remove(&wire) {
+                    popup_text += &format!("+1 {}\n", item_to_str(&ItemType::CopperWire));
+                    self.player.add_item(&ItemType::CopperWire, 1);
+                }
+            }
+            // Signal wires are placed by hand, so they are returned to the player
+            let (removed_wires, signal_wires): (Vec<_>, Vec<_>) =
+                std::mem::take(&mut self.signal_wires)
+                    .into_iter()
+                    .partition(|wire| wire.1.id.id == i as u32 || wire.2.id.id == i as u32);
+            self.signal_wires = signal_wires;
+            for wire in removed_wires {
+                popup_text += &format!("+1 {}\n", item_to_str(&wire.0.item()));
+                self.player.add_item(&wire.0.item(), 1);
+            }
+            self.circuit_networks = build_circuit_networks(&self.signal_wires);
             let destroyed_id = StructureId { id: i as u32, gen };
             structure.on_construction_self(
                 destroyed_id,
@@ -1791,10 +2409,23 @@ impl FactorishState {
         clear_structure: bool,
         clear_item: bool,
     ) -> Result<bool, JsValue> {
-        let (harvested_structure, mut popup_text) = if clear_structure {
-            self.harvest_structure(position)?
+        let train_cargo = if clear_structure {
+            self.harvest_train(position)
         } else {
-            (false, String::new())
+            None
+        };
+        let (harvested_structure, mut popup_text) = if let Some(cargo) = train_cargo {
+            // Pick up a train before the rail under it
+            let mut popup_text = String::new();
+            for (item_type, count) in cargo {
+                self.player.add_item(&item_type, count);
+                popup_text += &format!("+{} {}\n", count, &item_to_str(&item_type));
+            }
+            (true, popup_text)
+        } else if clear_structure {
+            self.harvest_structure(position)?
+        } else {
+            (false, String::new())
         };
 
         let mut harvested_items = false;
@@ -1944,6 +2575,82 @@ impl FactorishState {
         ))
     }
 
+    /// Returns the recipes that the player can craft by hand
+    pub fn get_hand_craft_recipes(&self) -> Result<JsValue, JsValue> {
+        to_value(
+            &Assembler::get_recipes()
+                .iter()
+                .enumerate()
+                .filter(|(_, recipe)| is_hand_craftable(recipe, &self.unlocked_technologies))
+                .map(|(i, recipe)| RecipeSerial::from((i, recipe.clone())))
+                .collect::<Vec<_>>(),
+        )
+        .map_err(|e| js_str!("Error: {:?}", e))
+    }
+
+    /// Queue hand crafts of the recipe at `index` in the list of assembler recipes. Missing
+    /// ingredients are crafted first if the player has enough materials for them.
+    /// @returns false if the player does not have enough materials.
+    pub fn queue_craft(&mut self, index: usize, count: usize) -> Result<bool, JsValue> {
+        let all_recipes = Assembler::get_recipes();
+        let recipe = all_recipes
+            .get(index)
+            .ok_or_else(|| js_str!("recipes index out of bound {:?}", index))?;
+        let recipes: Vec<_> = all_recipes
+            .iter()
+            .filter(|recipe| is_hand_craftable(recipe, &self.unlocked_technologies))
+            .collect();
+        if !recipes.iter().any(|r| std::ptr::eq(*r, recipe)) {
+            return js_err!("The recipe cannot be crafted by hand");
+        }
+        let mut inventory =
+            inventory_after_queue(&self.player.crafting_queue, &self.player.inventory);
+        let mut jobs = vec![];
+        if !plan_crafts(&recipes, recipe, count, &mut inventory, &mut jobs, false, 0) {
+            return Ok(false);
+        }
+        self.player.crafting_queue.extend(jobs);
+        Ok(true)
+    }
+
+    /// Cancel the hand craft at the given position in the queue.
+    pub fn cancel_craft(&mut self, position: usize) -> bool {
+        cancel_craft(
+            &mut self.player.crafting_queue,
+            &mut self.player.inventory,
+            position,
+        )
+    }
+
+    pub fn get_crafting_queue(&self) -> Result<JsValue, JsValue> {
+        to_value(
+            &self
+                .player
+                .crafting_queue
+                .iter()
+                .map(CraftJobSerial::from)
+                .collect::<Vec<_>>(),
+        )
+        .map_err(|e| js_str!("Error: {:?}", e))
+    }
+
+    /// Returns the ghosts waiting to be built, in the order to build them.
+    pub fn get_construction_queue(&self) -> Result<JsValue, JsValue> {
+        to_value(
+            &self
+                .ghosts
+                .iter()
+                .map(GhostSerial::from)
+                .collect::<Vec<_>>(),
+        )
+        .map_err(|e| js_str!("Error: {:?}", e))
+    }
+
+    /// Cancel the ghost at the given position in the construction queue.
+    pub fn cancel_construction(&mut self, position: usize) -> bool {
+        self.cancel_ghost(position)
+    }
+
     /// Returns [[itemName, itemCount]*, selectedItemName]
     pub fn get_player_inventory(&self) -> Result<js_sys::Array, JsValue> {
         self.inventory_to_js(
@@ -2057,6 +2764,47 @@ impl FactorishState {
             .and_then(|structure| structure.get_progress())
     }
 
+    /// Number of module slots of the structure at the given tile, 0 if it does not accept modules.
+    pub fn get_structure_module_slots(&self, c: i32, r: i32) -> usize {
+        self.find_structure_tile(&[c, r])
+            .map(|structure| structure.module_slots())
+            .unwrap_or(0)
+    }
+
+    /// Returns the circuit settings of the structure at the given tile as an object,
+    /// or `null` if it has none.
+    pub fn get_structure_circuit_config(&self, c: i32, r: i32) -> Result<JsValue, JsValue> {
+        match self
+            .find_structure_tile(&[c, r])
+            .and_then(|structure| structure.circuit_config())
+        {
+            Some(config) => Ok(to_value(&config)?),
+            None => Ok(JsValue::null()),
+        }
+    }
+
+    pub fn set_structure_circuit_config(
+        &mut self,
+        c: i32,
+        r: i32,
+        config: JsValue,
+    ) -> Result<(), JsValue> {
+        let config: CircuitConfig = from_value(config)?;
+        let idx = self
+            .find_structure_tile_idx(&[c, r])
+            .ok_or_else(|| js_str!("structure is not found"))?;
+        self.structures[idx]
+            .dynamic
+            .as_deref_mut()
+            .ok_or_else(|| js_str!("structure is not found"))?
+            .set_circuit_config(config)
+    }
+
+    /// Returns the names of the signals that can be used in the circuit settings
+    pub fn get_signal_names(&self) -> js_sys::Array {
+        signal_names().into_iter().map(JsValue::from).collect()
+    }
+
     pub fn get_structure_burner_energy(&self, c: i32, r: i32) -> Option<js_sys::Array> {
         self.find_structure_tile(&[c, r]).and_then(|structure| {
             let (current, max) = structure.burner_energy()?;
@@ -2067,6 +2815,150 @@ impl FactorishState {
         })
     }
 
+    /// Returns the coordinates of the underground belt paired with the one at the given tile
+    /// in 2-array, or `null` if it is not paired.
+    pub fn get_underground_belt_pair(&self, c: i32, r: i32) -> Result<JsValue, JsValue> {
+        let structure = self
+            .find_structure_tile(&[c, r])
+            .ok_or_else(|| js_str!("structure is not found"))?;
+        if let Some(pos) = structure
+            .underground_pair()
+            .and_then(|id| self.get_structure(id))
+            .map(|s| s.position())
+        {
+            return Ok(JsValue::from(js_sys::Array::of2(
+                &JsValue::from(pos.x),
+                &JsValue::from(pos.y),
+            )));
+        }
+        Ok(JsValue::null())
+    }
+
+    /// Returns storage configuration of the structure at the given tile as an object
+    /// `{ slots, bar, filters }`, or `null` if the structure has no storage inventory.
+    /// `filters` is an array of item names or `null` for unfiltered slots.
+    pub fn get_structure_storage_config(&self, c: i32, r: i32) -> Result<JsValue, JsValue> {
+        let structure = self
+            .find_structure_tile(&[c, r])
+            .ok_or_else(|| js_str!("structure is not found"))?;
+        if structure.inventory(InventoryType::Storage).is_none() {
+            return Ok(JsValue::null());
+        }
+        let filters = structure.slot_filters();
+        to_value(&StorageConfigSerial {
+            slots: structure.storage_slots(),
+            bar: structure.inventory_bar(),
+            filters: (0..structure.storage_slots())
+                .map(|i| filters.get(i).copied().flatten().map(|f| item_to_str(&f)))
+                .collect(),
+        })
+        .map_err(JsValue::from)
+    }
+
+    /// Set the number of storage slots that inserters can fill. `undefined` removes the limit.
+    pub fn set_structure_inventory_bar(
+        &mut self,
+        c: i32,
+        r: i32,
+        bar: Option<usize>,
+    ) -> Result<(), JsValue> {
+        let idx = self
+            .find_structure_tile_idx(&[c, r])
+            .ok_or_else(|| js_str!("structure is not found"))?;
+        self.structures[idx]
+            .dynamic
+            .as_deref_mut()
+            .ok_or_else(|| js_str!("structure is not found"))?
+            .set_inventory_bar(bar)
+    }
+
+    /// Set the item filter of a storage slot. `undefined` item name clears the filter.
+    pub fn set_structure_slot_filter(
+        &mut self,
+        c: i32,
+        r: i32,
+        slot: usize,
+        item: Option<String>,
+    ) -> Result<(), JsValue> {
+        let item = item
+            .map(|name| str_to_item(&name).ok_or_else(|| js_str!("Item name not valid: {}", name)))
+            .transpose()?;
+        let idx = self
+            .find_structure_tile_idx(&[c, r])
+            .ok_or_else(|| js_str!("structure is not found"))?;
+        self.structures[idx]
+            .dynamic
+            .as_deref_mut()
+            .ok_or_else(|| js_str!("structure is not found"))?
+            .set_slot_filter(slot, item)
+    }
+
+    /// Returns logistic requests of the requester chest at the given tile as an object
+    /// of item names to counts, or `null` if the structure is not a requester chest.
+    pub fn get_structure_logistic_requests(&self, c: i32, r: i32) -> Result<JsValue, JsValue> {
+        let structure = self
+            .find_structure_tile(&[c, r])
+            .ok_or_else(|| js_str!("structure is not found"))?;
+        if let Some(requests) = structure.logistic_requests() {
+            return to_value(
+                &requests
+                    .iter()
+                    .map(|(item, count)| (item_to_str(item), *count))
+                    .collect::<HashMap<_, _>>(),
+            )
+            .map_err(JsValue::from);
+        }
+        Ok(JsValue::null())
+    }
+
+    /// Request the logistic network to keep `count` of the item in the requester chest.
+    /// Zero count clears the request.
+    pub fn set_structure_logistic_request(
+        &mut self,
+        c: i32,
+        r: i32,
+        item: String,
+        count: usize,
+    ) -> Result<(), JsValue> {
+        let item = str_to_item(&item).ok_or_else(|| js_str!("Item name not valid: {}", item))?;
+        let idx = self
+            .find_structure_tile_idx(&[c, r])
+            .ok_or_else(|| js_str!("structure is not found"))?;
+        self.structures[idx]
+            .dynamic
+            .as_deref_mut()
+            .ok_or_else(|| js_str!("structure is not found"))?
+            .set_logistic_request(item, count)
+    }
+
+    /// Set the schedule of the train at the tile. The schedule is an array of objects like
+    /// `{station: "Stop 1,2", condition: "Full"}`, where the condition is one of
+    /// `"Full"`, `"Empty"` or `{Time: seconds}`.
+    pub fn set_train_schedule(&mut self, c: i32, r: i32, schedule: JsValue) -> Result<(), JsValue> {
+        let schedule: Vec<ScheduleEntry> = from_value(schedule)?;
+        self.set_train_schedule_at(&Position::new(c, r), schedule)
+    }
+
+    /// Returns the schedule of the train at the tile, or null if there is no train.
+    pub fn get_train_schedule(&self, c: i32, r: i32) -> Result<JsValue, JsValue> {
+        Ok(self
+            .train_schedule_at(&Position::new(c, r))
+            .map(to_value)
+            .transpose()?
+            .unwrap_or_else(JsValue::null))
+    }
+
+    pub fn set_train_stop_name(&mut self, c: i32, r: i32, name: String) -> Result<(), JsValue> {
+        let idx = self
+            .find_structure_tile_idx(&[c, r])
+            .ok_or_else(|| js_str!("structure is not found"))?;
+        self.structures[idx]
+            .dynamic
+            .as_deref_mut()
+            .ok_or_else(|| js_str!("structure is not found"))?
+            .set_train_stop_name(&name)
+    }
+
     /// Select an item in a structure inventory at index `idx`. If a structure was not previously selected, ignored.
     pub fn select_structure_inventory(
         &mut self,
@@ -2165,9 +3057,15 @@ impl FactorishState {
                 .iter()
                 .find(|tech| tech.tag == research.technology)
             {
+                let level = self.technology_level(tech.tag);
                 to_value(&ResearchSerial {
                     technology: research.technology,
-                    progress: research.progress as f64 / tech.steps as f64,
+                    progress: research.progress as f64 / tech.steps_at(level) as f64,
+                    level: if tech.repeatable {
+                        Some(level + 1)
+                    } else {
+                        None
+                    },
                 })
                 .map_err(|e| js_str!("Error: {:?}", e))
             } else {
@@ -2180,7 +3078,7 @@ impl FactorishState {
 
     pub fn select_research(&mut self, index: usize) -> Result<bool, JsValue> {
         if let Some(tech) = TECHNOLOGIES.get(index) {
-            if !self.unlocked_technologies.contains(&tech.tag) {
+            if self.can_research(tech) {
                 if let Some(ref research) = self.research {
                     self.pending_researches
                         .insert(research.technology, research.progress);
@@ -2200,6 +3098,45 @@ impl FactorishState {
         Ok(false)
     }
 
+    /// Append a technology to the research queue, or start it right away if nothing is researched.
+    /// Repeatable technologies can be queued more than once to research several levels.
+    pub fn queue_research(&mut self, index: usize) -> Result<bool, JsValue> {
+        let Some(tech) = TECHNOLOGIES.get(index) else {
+            return Ok(false);
+        };
+        if !self.can_research(tech) {
+            return Ok(false);
+        }
+        if !tech.repeatable
+            && (self.research_queue.contains(&tech.tag)
+                || self
+                    .research
+                    .as_ref()
+                    .is_some_and(|research| research.technology == tech.tag))
+        {
+            return Ok(false);
+        }
+        self.research_queue.push(tech.tag);
+        if self.research.is_none() {
+            self.start_next_research();
+        }
+        Ok(true)
+    }
+
+    /// Remove the technology at the given position in the research queue.
+    pub fn dequeue_research(&mut self, position: usize) -> bool {
+        if position < self.research_queue.len() {
+            self.research_queue.remove(position);
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn get_research_queue(&self) -> Result<JsValue, JsValue> {
+        to_value(&self.research_queue).map_err(|e| js_str!("Error: {:?}", e))
+    }
+
     pub fn set_alt_mode(&mut self, value: bool) {
         self.alt_mode = value;
     }
@@ -2229,6 +3166,12 @@ impl FactorishState {
                 InventoryType::Input,
                 InventoryType::Storage,
             ]
+        } else if modules::is_module(item) {
+            [
+                InventoryType::Module,
+                InventoryType::Input,
+                InventoryType::Storage,
+            ]
         } else {
             [
                 InventoryType::Input,
@@ -2370,6 +3313,168 @@ impl FactorishState {
         Ok(false)
     }
 
+    /// Build a structure of the tool item at the cursor from the player's inventory, or plan it
+    /// as a ghost if the player has none or it is out of reach.
+    /// @returns true if the structure is built.
+    fn place_tool(&mut self, item: &ItemType, cursor: &Position) -> Result<bool, JsValue> {
+        let new_s = if let Ok(s) = self.new_structure(item, cursor) {
+            s
+        } else {
+            return Ok(false);
+        };
+        if self.blocked_by_character(new_s.as_ref()) {
+            self.new_popup_text(
+                "Blocked by the character".to_string(),
+                cursor.x as f64 * TILE_SIZE,
+                cursor.y as f64 * TILE_SIZE,
+            )?;
+            return Ok(false);
+        }
+        if self.player.inventory.count_item(item) == 0 || !self.in_reach(cursor) {
+            // Plan the structure to be built when the item is available
+            if !self.add_ghost(new_s) {
+                self.new_popup_text(
+                    "Site is occupied".to_string(),
+                    cursor.x as f64 * TILE_SIZE,
+                    cursor.y as f64 * TILE_SIZE,
+                )?;
+            }
+            return Ok(false);
+        }
+        self.place_structure(new_s)?;
+
+        if let Some(count) = self.player.inventory.get_mut(item) {
+            *count -= 1;
+        }
+        Ok(true)
+    }
+
+    /// Put a new structure on the map, clearing the structures and items under it and
+    /// connecting it to the power, fluid and belt networks around.
+    fn place_structure(&mut self, mut new_s: Box<dyn Structure>) -> Result<StructureId, JsValue> {
+        let position = *new_s.position();
+        let bbox = new_s.bounding_box();
+        self.clear_ghosts_under(new_s.as_ref());
+        for y in bbox.y0..bbox.y1 {
+            for x in bbox.x0..bbox.x1 {
+                self.harvest(&Position { x, y }, true, !new_s.movable())?;
+            }
+        }
+        // let connections = new_s.connection(self, &Ref(&self.structures));
+        // console_log!(
+        //     "Connection recalculated for self {:?}: {:?}",
+        //     new_s.position(),
+        //     connections
+        // );
+        // if let Some(fluid_boxes) = new_s.fluid_box_mut() {
+        //     for fbox in fluid_boxes {
+        //         fbox.connect_to = connections;
+        //     }
+        // }
+
+        // First, find an empty slot
+        let id = self
+            .structures
+            .iter()
+            .enumerate()
+            .find(|(_, s)| s.dynamic.is_none())
+            .map(|(i, slot)| StructureId {
+                id: i as u32,
+                gen: slot.gen,
+            })
+            .unwrap_or_else(|| StructureId {
+                id: self.structures.len() as u32,
+                gen: 0,
+            });
+
+        for (other_id, structure) in self.structures.iter().enumerate().filter_map(|(i, s)| {
+            Some((
+                StructureId {
+                    id: i as u32,
+                    gen: s.gen,
+                },
+                s.dynamic.as_deref()?,
+            ))
+        }) {
+            if (new_s.power_sink() && structure.power_source()
+                || new_s.power_source() && structure.power_sink())
+                && new_s.bounding_box().distance(&structure.bounding_box())
+                    <= new_s.wire_reach().min(structure.wire_reach()) as i32
+                && !supplied_by(new_s.as_ref(), structure)
+                && !supplied_by(structure, new_s.as_ref())
+            {
+                let new_power_wire = PowerWire(id, other_id);
+                if self.power_wires.contains(&new_power_wire)
+                    || MAX_WIRES <= self.wire_count(id)
+                    || MAX_WIRES <= self.wire_count(other_id)
+                {
+                    continue;
+                }
+                console_log!("power_wires: {}", self.power_wires.len());
+                self.power_wires.push(new_power_wire);
+            }
+        }
+
+        new_s.on_construction_self(id, &StructureDynIter::new_all(&mut self.structures), true)?;
+
+        // Notify structures after a slot has been decided
+        let mut structures = std::mem::take(&mut self.structures);
+        for i in 0..structures.len() {
+            let (structure, others) = StructureDynIter::new(&mut structures, i)?;
+            if let Some(s) = structure.dynamic.as_deref_mut() {
+                match s.on_construction(id, new_s.as_mut(), &others, true) {
+                    Ok(()) => (),
+                    Err(s) => {
+                        drop(others);
+                        self.structures = structures;
+                        return Err(s);
+                    }
+                }
+            }
+        }
+        self.structures = structures;
+
+        if id.id < self.structures.len() as u32 {
+            self.structures[id.id as usize].dynamic = Some(new_s);
+
+            console_log!(
+                "Inserted to an empty slot: {}/{}, id: {:?}",
+                self.structures
+                    .iter()
+                    .filter(|s| s.dynamic.is_none())
+                    .count(),
+                self.structures.len(),
+                id
+            );
+        } else {
+            self.structures.push(StructureEntry {
+                gen: 0,
+                dynamic: Some(new_s),
+            });
+            console_log!(
+                "Pushed to the end: {}/{}",
+                self.structures
+                    .iter()
+                    .filter(|s| s.dynamic.is_none())
+                    .count(),
+                self.structures.len()
+            );
+        }
+
+        self.power_networks = build_power_networks(
+            &StructureDynIter::new_all(&mut self.structures),
+            &self.power_wires,
+        );
+
+        self.update_fluid_connections(&position)?;
+
+        let mut chunks = std::mem::take(&mut self.board);
+        self.render_minimap_data_pixel(&mut chunks, &position);
+        self.board = chunks;
+
+        Ok(id)
+    }
+
     fn new_structure(
         &self,
         tool: &ItemType,
@@ -2380,12 +3485,40 @@ impl FactorishState {
                 Box::new(TransportBelt::new(cursor.x, cursor.y, self.tool_rotation))
             }
             ItemType::Inserter => Box::new(Inserter::new(cursor.x, cursor.y, self.tool_rotation)),
+            ItemType::ElectricInserter => Box::new(Inserter::new_electric(
+                cursor.x,
+                cursor.y,
+                self.tool_rotation,
+            )),
             ItemType::Splitter => Box::new(Splitter::new(cursor.x, cursor.y, self.tool_rotation)),
+            ItemType::Loader => Box::new(Loader::new(cursor.x, cursor.y, self.tool_rotation)),
             ItemType::OreMine => Box::new(OreMine::new(cursor.x, cursor.y, self.tool_rotation)),
+            ItemType::ElectricMiningDrill => Box::new(OreMine::new_electric(
+                cursor.x,
+                cursor.y,
+                self.tool_rotation,
+            )),
             ItemType::Chest => Box::new(Chest::new(cursor)),
+            ItemType::IronChest => Box::new(Chest::new_tier(cursor, ChestTier::Iron)),
+            ItemType::SteelChest => Box::new(Chest::new_tier(cursor, ChestTier::Steel)),
+            ItemType::PassiveProviderChest => Box::new(Chest::new_logistic(
+                cursor,
+                LogisticChestKind::PassiveProvider,
+            )),
+            ItemType::ActiveProviderChest => Box::new(Chest::new_logistic(
+                cursor,
+                LogisticChestKind::ActiveProvider,
+            )),
+            ItemType::StorageChest => {
+                Box::new(Chest::new_logistic(cursor, LogisticChestKind::Storage))
+            }
+            ItemType::RequesterChest => {
+                Box::new(Chest::new_logistic(cursor, LogisticChestKind::Requester))
+            }
+            ItemType::Roboport => Box::new(Roboport::new(cursor)),
             ItemType::Furnace => Box::new(Furnace::new(cursor)),
             ItemType::ElectricFurnace => Box::new(ElectricFurnace::new(cursor)),
-            ItemType::Assembler => Box::new(Assembler::new(cursor)),
+            ItemType::Assembler => Box::new(Assembler::new(cursor, self.tool_rotation)),
             ItemType::Lab => Box::new(Lab::new(cursor)),
             ItemType::Boiler => Box::new(Boiler::new(cursor)),
             ItemType::WaterWell => Box::new(WaterWell::new(cursor)),
@@ -2396,12 +3529,52 @@ impl FactorishState {
             }
             ItemType::SteamEngine => Box::new(SteamEngine::new(cursor)),
             ItemType::ElectPole => Box::new(ElectPole::new(cursor)),
+            ItemType::MediumElectPole => Box::new(ElectPole::new_tier(cursor, PoleTier::Medium)),
+            ItemType::BigElectPole => Box::new(ElectPole::new_tier(cursor, PoleTier::Big)),
+            ItemType::Substation => Box::new(ElectPole::new_tier(cursor, PoleTier::Substation)),
+            ItemType::ConstantCombinator => Box::new(ConstantCombinator::new(cursor)),
+            ItemType::ArithmeticCombinator => {
+                Box::new(Combinator::new_arithmetic(cursor, self.tool_rotation))
+            }
+            ItemType::DeciderCombinator => {
+                Box::new(Combinator::new_decider(cursor, self.tool_rotation))
+            }
+            ItemType::Lamp => Box::new(Lamp::new(cursor)),
             ItemType::UndergroundBelt => Box::new(UndergroundBelt::new(
                 cursor.x,
                 cursor.y,
                 self.tool_rotation,
                 UnderDirection::ToGround,
             )),
+            ItemType::FastUndergroundBelt => Box::new(UndergroundBelt::new_tier(
+                cursor.x,
+                cursor.y,
+                self.tool_rotation,
+                UnderDirection::ToGround,
+                UndergroundBeltTier::Fast,
+            )),
+            ItemType::Rail => Box::new(Rail::new(cursor, self.tool_rotation, RailShape::Straight)),
+            ItemType::CurvedRail => {
+                Box::new(Rail::new(cursor, self.tool_rotation, RailShape::Curve))
+            }
+            ItemType::RailSignal => Box::new(RailSignal::new(cursor, self.tool_rotation)),
+            ItemType::TrainStop => Box::new(TrainStop::new(cursor, self.tool_rotation)),
+            ItemType::StorageTank => Box::new(StorageTank::new(cursor)),
+            ItemType::Pump => Box::new(Pump::new(cursor, self.tool_rotation)),
+            ItemType::Pumpjack => Box::new(Pumpjack::new(cursor)),
+            ItemType::Beacon => Box::new(Beacon::new(cursor)),
+            ItemType::SolarPanel => Box::new(SolarPanel::new(cursor)),
+            ItemType::Accumulator => Box::new(Accumulator::new(cursor)),
+            ItemType::OilRefinery => Box::new(ProcessingPlant::new(
+                cursor,
+                self.tool_rotation,
+                PlantKind::OilRefinery,
+            )),
+            ItemType::ChemicalPlant => Box::new(ProcessingPlant::new(
+                cursor,
+                self.tool_rotation,
+                PlantKind::ChemicalPlant,
+            )),
             _ => return js_err!("Can't make a structure from {:?}", tool),
         })
     }
@@ -2434,10 +3607,24 @@ impl FactorishState {
             ItemType::TransportBelt => {
                 Box::new(map_err(serde_json::from_value::<TransportBelt>(payload))?)
             }
-            ItemType::Inserter => Box::new(map_err(serde_json::from_value::<Inserter>(payload))?),
+            ItemType::Inserter | ItemType::ElectricInserter => {
+                Box::new(map_err(serde_json::from_value::<Inserter>(payload))?)
+            }
             ItemType::Splitter => Box::new(map_err(serde_json::from_value::<Splitter>(payload))?),
-            ItemType::OreMine => Box::new(map_err(serde_json::from_value::<OreMine>(payload))?),
-            ItemType::Chest => Box::new(map_err(serde_json::from_value::<Chest>(payload))?),
+            ItemType::Loader => Box::new(map_err(serde_json::from_value::<Loader>(payload))?),
+            ItemType::OreMine | ItemType::ElectricMiningDrill => {
+                Box::new(map_err(serde_json::from_value::<OreMine>(payload))?)
+            }
+            ItemType::Chest
+            | ItemType::IronChest
+            | ItemType::SteelChest
+            | ItemType::PassiveProviderChest
+            | ItemType::ActiveProviderChest
+            | ItemType::StorageChest
+            | ItemType::RequesterChest => {
+                Box::new(map_err(serde_json::from_value::<Chest>(payload))?)
+            }
+            ItemType::Roboport => Box::new(map_err(serde_json::from_value::<Roboport>(payload))?),
             ItemType::Furnace => Box::new(map_err(serde_json::from_value::<Furnace>(payload))?),
             ItemType::ElectricFurnace => {
                 Box::new(map_err(serde_json::from_value::<ElectricFurnace>(payload))?)
@@ -2456,10 +3643,44 @@ impl FactorishState {
             ItemType::SteamEngine => {
                 Box::new(map_err(serde_json::from_value::<SteamEngine>(payload))?)
             }
-            ItemType::ElectPole => Box::new(map_err(serde_json::from_value::<ElectPole>(payload))?),
-            ItemType::UndergroundBelt => {
+            ItemType::ElectPole
+            | ItemType::MediumElectPole
+            | ItemType::BigElectPole
+            | ItemType::Substation => {
+                Box::new(map_err(serde_json::from_value::<ElectPole>(payload))?)
+            }
+            ItemType::ConstantCombinator => Box::new(map_err(serde_json::from_value::<
+                ConstantCombinator,
+            >(payload))?),
+            ItemType::ArithmeticCombinator | ItemType::DeciderCombinator => {
+                Box::new(map_err(serde_json::from_value::<Combinator>(payload))?)
+            }
+            ItemType::Lamp => Box::new(map_err(serde_json::from_value::<Lamp>(payload))?),
+            ItemType::UndergroundBelt | ItemType::FastUndergroundBelt => {
                 Box::new(map_err(serde_json::from_value::<UndergroundBelt>(payload))?)
             }
+            ItemType::Rail | ItemType::CurvedRail => {
+                Box::new(map_err(serde_json::from_value::<Rail>(payload))?)
+            }
+            ItemType::RailSignal => {
+                Box::new(map_err(serde_json::from_value::<RailSignal>(payload))?)
+            }
+            ItemType::TrainStop => Box::new(map_err(serde_json::from_value::<TrainStop>(payload))?),
+            ItemType::StorageTank => {
+                Box::new(map_err(serde_json::from_value::<StorageTank>(payload))?)
+            }
+            ItemType::Pump => Box::new(map_err(serde_json::from_value::<Pump>(payload))?),
+            ItemType::Pumpjack => Box::new(map_err(serde_json::from_value::<Pumpjack>(payload))?),
+            ItemType::Beacon => Box::new(map_err(serde_json::from_value::<Beacon>(payload))?),
+            ItemType::SolarPanel => {
+                Box::new(map_err(serde_json::from_value::<SolarPanel>(payload))?)
+            }
+            ItemType::Accumulator => {
+                Box::new(map_err(serde_json::from_value::<Accumulator>(payload))?)
+            }
+            ItemType::OilRefinery | ItemType::ChemicalPlant => {
+                Box::new(map_err(serde_json::from_value::<ProcessingPlant>(payload))?)
+            }
             _ => return js_err!("Can't make a structure from {:?}", type_str),
         })
     }
@@ -2474,7 +3695,13 @@ impl FactorishState {
         };
 
         console_log!("mouse_down: {}, {}, button: {}", cursor.x, cursor.y, button);
-        if button == 2 {
+        let mut events = vec![];
+        if button == 0 && self.area_tool.is_some() {
+            self.area_start = Some(cursor);
+        }
+        if button == 2 && self.cancel_ghost_at(&cursor) {
+            events.push(to_value(&JSEvent::UpdatePlayerInventory).unwrap());
+        } else if button == 2 && self.in_reach(&cursor) {
             self.harvest(&cursor, false, true)?;
             if self.find_structure_tile(&[cursor.x, cursor.y]).is_none()
             // Let the player pick up drop items before harvesting ore below.
@@ -2494,7 +3721,7 @@ impl FactorishState {
             }
         }
         self.update_info();
-        Ok(JsValue::from(js_sys::Array::new()))
+        Ok(JsValue::from(events.iter().collect::<js_sys::Array>()))
     }
 
     pub fn mouse_up(&mut self, pos: &[f64], button: i32, ctrl: bool) -> Result<JsValue, JsValue> {
@@ -2518,138 +3745,90 @@ impl FactorishState {
                 }
                 return Ok(JsValue::from(events.iter().collect::<js_sys::Array>()));
             }
-            if let Some((selected_tool, _)) = self.get_selected_tool_or_item_opt() {
-                let cell = self.tile_at(&cursor);
-                if let Some((count, cell)) =
-                    self.player.inventory.get(&selected_tool).zip(cell.as_ref())
-                {
-                    if 1 <= *count && cell.water ^ (selected_tool != ItemType::OffshorePump) {
-                        let mut new_s = if let Ok(s) = self.new_structure(&selected_tool, &cursor) {
-                            s
-                        } else {
-                            return Ok(JsValue::UNDEFINED);
-                        };
-                        let bbox = new_s.bounding_box();
-                        for y in bbox.y0..bbox.y1 {
-                            for x in bbox.x0..bbox.x1 {
-                                self.harvest(&Position { x, y }, true, !new_s.movable())?;
+            if let Some(area_tool) = self.area_tool {
+                if let Some(area) = self.selected_area() {
+                    self.area_start = None;
+                    match area_tool {
+                        AreaTool::Blueprint => {
+                            let blueprint = self.capture_blueprint(&area)?;
+                            if !blueprint.is_empty() {
+                                self.blueprint = Some(blueprint);
+                                self.area_tool = None;
                             }
+                            events.push(to_value(&JSEvent::UpdateBlueprint).unwrap());
                         }
-                        // let connections = new_s.connection(self, &Ref(&self.structures));
-                        // console_log!(
-                        //     "Connection recalculated for self {:?}: {:?}",
-                        //     new_s.position(),
-                        //     connections
-                        // );
-                        // if let Some(fluid_boxes) = new_s.fluid_box_mut() {
-                        //     for fbox in fluid_boxes {
-                        //         fbox.connect_to = connections;
-                        //     }
-                        // }
-
-                        // First, find an empty slot
-                        let id = self
-                            .structures
-                            .iter()
-                            .enumerate()
-                            .find(|(_, s)| s.dynamic.is_none())
-                            .map(|(i, slot)| StructureId {
-                                id: i as u32,
-                                gen: slot.gen,
-                            })
-                            .unwrap_or_else(|| StructureId {
-                                id: self.structures.len() as u32,
-                                gen: 0,
-                            });
-
-                        for (other_id, structure) in
-                            self.structures.iter().enumerate().filter_map(|(i, s)| {
-                                Some((
-                                    StructureId {
-                                        id: i as u32,
-                                        gen: s.gen,
-                                    },
-                                    s.dynamic.as_deref()?,
-                                ))
-                            })
-                        {
-                            if (new_s.power_sink() && structure.power_source()
-                                || new_s.power_source() && structure.power_sink())
-                                && new_s.bounding_box().distance(&structure.bounding_box())
-                                    <= new_s.wire_reach().min(structure.wire_reach()) as i32
-                            {
-                                let new_power_wire = PowerWire(id, other_id);
-                                if self.power_wires.iter().any(|p| *p == new_power_wire) {
-                                    continue;
-                                }
-                                console_log!("power_wires: {}", self.power_wires.len());
-                                self.power_wires.push(new_power_wire);
-                            }
+                        AreaTool::Deconstruct(filter) => {
+                            self.mark_deconstruction(&area, filter);
                         }
-
-                        new_s.on_construction_self(
-                            id,
-                            &StructureDynIter::new_all(&mut self.structures),
-                            true,
-                        )?;
-
-                        // Notify structures after a slot has been decided
-                        let mut structures = std::mem::take(&mut self.structures);
-                        for i in 0..structures.len() {
-                            let (structure, others) = StructureDynIter::new(&mut structures, i)?;
-                            if let Some(s) = structure.dynamic.as_deref_mut() {
-                                match s.on_construction(id, new_s.as_mut(), &others, true) {
-                                    Ok(()) => (),
-                                    Err(s) => {
-                                        drop(others);
-                                        self.structures = structures;
-                                        return Err(s);
-                                    }
-                                }
-                            }
+                        AreaTool::CancelDeconstruct => {
+                            self.unmark_deconstruction(&area);
                         }
-                        self.structures = structures;
-
-                        if id.id < self.structures.len() as u32 {
-                            self.structures[id.id as usize].dynamic = Some(new_s);
-
-                            console_log!(
-                                "Inserted to an empty slot: {}/{}, id: {:?}",
-                                self.structures
-                                    .iter()
-                                    .filter(|s| s.dynamic.is_none())
-                                    .count(),
-                                self.structures.len(),
-                                id
-                            );
-                        } else {
-                            self.structures.push(StructureEntry {
-                                gen: 0,
-                                dynamic: Some(new_s),
-                            });
-                            console_log!(
-                                "Pushed to the end: {}/{}",
-                                self.structures
-                                    .iter()
-                                    .filter(|s| s.dynamic.is_none())
-                                    .count(),
-                                self.structures.len()
-                            );
+                    }
+                }
+                return Ok(JsValue::from(events.iter().collect::<js_sys::Array>()));
+            }
+            if self.blueprint.is_some() {
+                if self.paste_blueprint(&cursor)? {
+                    events.push(to_value(&JSEvent::UpdatePlayerInventory).unwrap());
+                }
+                return Ok(JsValue::from(events.iter().collect::<js_sys::Array>()));
+            }
+            let selected_tool = self.get_selected_tool_or_item_opt();
+            if selected_tool.map(|(item, _)| item) != Some(ItemType::CopperWire) {
+                self.wire_start = None;
+            }
+            let wire_color = selected_tool.and_then(|(item, _)| WireColor::from_item(&item));
+            if let Some(color) = wire_color {
+                if self.click_signal_wire_tool(&cursor, color)? {
+                    events.push(to_value(&JSEvent::UpdatePlayerInventory).unwrap());
+                }
+                return Ok(JsValue::from(events.iter().collect::<js_sys::Array>()));
+            }
+            self.signal_wire_start = None;
+            if let Some((selected_tool, _)) = selected_tool {
+                let count = self.player.inventory.count_item(&selected_tool);
+                let in_reach = self.in_reach(&cursor);
+                // Structures can be planned as ghosts out of reach, but wires and train cars cannot
+                if !in_reach
+                    && (selected_tool == ItemType::CopperWire
+                        || TrainCarKind::from_item(&selected_tool).is_some())
+                {
+                    self.new_popup_text(
+                        "Out of reach".to_string(),
+                        cursor.x as f64 * TILE_SIZE,
+                        cursor.y as f64 * TILE_SIZE,
+                    )?;
+                    return Ok(JsValue::UNDEFINED);
+                }
+                if selected_tool == ItemType::CopperWire {
+                    if self.click_wire_tool(&cursor)? {
+                        events.push(to_value(&JSEvent::UpdatePlayerInventory).unwrap());
+                    }
+                    return Ok(JsValue::from(events.iter().collect::<js_sys::Array>()));
+                }
+                if let Some(cell) = self.tile_at(&cursor) {
+                    if cell.water ^ (selected_tool != ItemType::OffshorePump) {
+                        // Train cars run on rails instead of occupying tiles as structures
+                        if let Some(kind) = TrainCarKind::from_item(&selected_tool) {
+                            if count == 0 {
+                                return Ok(JsValue::UNDEFINED);
+                            }
+                            if let Err(e) = self.place_train_car(kind, cursor) {
+                                self.new_popup_text(
+                                    e.as_string().unwrap_or_default(),
+                                    cursor.x as f64 * TILE_SIZE,
+                                    cursor.y as f64 * TILE_SIZE,
+                                )?;
+                                return Ok(JsValue::UNDEFINED);
+                            }
+                            if let Some(count) = self.player.inventory.get_mut(&selected_tool) {
+                                *count -= 1;
+                            }
+                            events.push(to_value(&JSEvent::UpdatePlayerInventory).unwrap());
+                            return Ok(JsValue::from(events.iter().collect::<js_sys::Array>()));
                         }
-
-                        self.power_networks = build_power_networks(
-                            &StructureDynIter::new_all(&mut self.structures),
-                            &self.power_wires,
-                        );
-
-                        self.update_fluid_connections(&cursor)?;
-
-                        let mut chunks = std::mem::take(&mut self.board);
-                        self.render_minimap_data_pixel(&mut chunks, &cursor);
-                        self.board = chunks;
-
-                        if let Some(count) = self.player.inventory.get_mut(&selected_tool) {
-                            *count -= 1;
+                        if !self.place_tool(&selected_tool, &cursor)? {
+                            return Ok(JsValue::UNDEFINED);
                         }
                         self.on_player_update
                             .call1(&window(), &JsValue::from(self.get_player_inventory()?))
@@ -2662,6 +3841,7 @@ impl FactorishState {
                     || structure.inventory(InventoryType::Output).is_some()
                     || structure.inventory(InventoryType::Storage).is_some()
                     || structure.inventory(InventoryType::Burner).is_some()
+                    || structure.circuit_config().is_some()
                 {
                     // Select clicked structure
                     console_log!("opening inventory at {:?}", cursor);
@@ -2682,7 +3862,7 @@ impl FactorishState {
         } else if button == 2 {
             if self.ore_harvesting.is_some() {
                 self.ore_harvesting = None;
-            } else {
+            } else if self.in_reach(&cursor) {
                 // Right click means explicit cleanup, so we pick up items no matter what.
                 self.harvest(&cursor, true, true)?;
                 events.push(to_value(&JSEvent::UpdatePlayerInventory).unwrap());
@@ -2751,6 +3931,11 @@ impl FactorishState {
 
     /// Keyboard event handler. Returns true if re-rendering is necessary to update internal state.
     pub fn on_key_down(&mut self, key_code: i32) -> Result<JsValue, JsValue> {
+        if let Some(character) = self.player.character.as_mut() {
+            if character.set_key(key_code, true) {
+                return Ok(JsValue::from_bool(true));
+            }
+        }
         match key_code {
             // 'r'
             82 => match self.rotate() {
@@ -2795,7 +3980,9 @@ impl FactorishState {
             }
             81 => {
                 // 'q'
-                if self.selected_item.is_some() {
+                if self.area_tool.is_some() || self.blueprint.is_some() {
+                    self.clear_area_tools();
+                } else if self.selected_item.is_some() {
                     self.selected_item = None;
                 } else if let Some(cursor) = self.cursor {
                     if let Some(structure) = self
@@ -2823,6 +4010,94 @@ impl FactorishState {
         }
     }
 
+    /// Keyboard event handler for releasing keys held down to control the character.
+    pub fn on_key_up(&mut self, key_code: i32) -> bool {
+        self.player
+            .character
+            .as_mut()
+            .is_some_and(|character| character.set_key(key_code, false))
+    }
+
+    /// Switch between the character mode, where the player walks around with a character that
+    /// can only build and mine within its reach, and the free mode without a character.
+    pub fn set_character_mode(&mut self, enabled: bool) {
+        if !enabled {
+            self.player.character = None;
+        } else if self.player.character.is_none() {
+            self.spawn_character();
+        }
+    }
+
+    pub fn is_character_mode(&self) -> bool {
+        self.player.character.is_some()
+    }
+
+    /// Start selecting an area to capture into a blueprint by dragging on the map.
+    pub fn start_blueprint_selection(&mut self) {
+        self.clear_area_tools();
+        self.selected_item = None;
+        self.area_tool = Some(AreaTool::Blueprint);
+    }
+
+    /// Start selecting an area to mark the structures in it for deconstruction.
+    ///
+    /// @param filter the name of the item of the structures to mark, or all structures if undefined
+    pub fn start_deconstruction_selection(
+        &mut self,
+        filter: Option<String>,
+    ) -> Result<(), JsValue> {
+        let filter = filter
+            .map(|name| str_to_item(&name).ok_or_else(|| js_str!("Unknown item: {}", name)))
+            .transpose()?;
+        self.clear_area_tools();
+        self.selected_item = None;
+        self.area_tool = Some(AreaTool::Deconstruct(filter));
+        Ok(())
+    }
+
+    /// Start selecting an area to cancel the deconstruction marks in it.
+    pub fn start_cancel_deconstruction_selection(&mut self) {
+        self.clear_area_tools();
+        self.selected_item = None;
+        self.area_tool = Some(AreaTool::CancelDeconstruct);
+    }
+
+    /// Cancel all the deconstruction marks.
+    pub fn clear_deconstruction_marks(&mut self) {
+        self.deconstruction_marks.clear();
+    }
+
+    pub fn is_area_tool_active(&self) -> bool {
+        self.area_tool.is_some()
+    }
+
+    /// @returns the blueprint held by the player as a text string to share, or undefined.
+    pub fn get_blueprint_string(&self) -> Result<Option<String>, JsValue> {
+        self.blueprint
+            .as_ref()
+            .map(|blueprint| blueprint.to_blueprint_string())
+            .transpose()
+    }
+
+    /// Hold the blueprint given by a text string to paste on the map.
+    pub fn import_blueprint(&mut self, s: &str) -> Result<(), JsValue> {
+        let blueprint = Blueprint::from_blueprint_string(s)?;
+        if blueprint.is_empty() {
+            return js_err!("The blueprint is empty");
+        }
+        self.clear_area_tools();
+        self.selected_item = None;
+        self.blueprint = Some(blueprint);
+        Ok(())
+    }
+
+    /// Put away the area tool or the blueprint held by the player.
+    pub fn clear_area_tools(&mut self) {
+        self.area_tool = None;
+        self.area_start = None;
+        self.blueprint = None;
+    }
+
     fn color_of_cell(cell: &Cell) -> [u8; 3] {
         if cell.water {
             [0x00, 0x00, 0xff]
@@ -2832,6 +4107,7 @@ impl FactorishState {
                 Some(OreValue(Ore::Coal, _)) => [0x1f, 0x1f, 0x1f],
                 Some(OreValue(Ore::Copper, _)) => [0x7f, 0x3f, 0x00],
                 Some(OreValue(Ore::Stone, _)) => [0x5f, 0x5f, 0x5f],
+                _ if 0 < cell.oil => [0x3f, 0x1f, 0x3f],
                 _ => [0xaf, 0x7f, 0x3f],
             }
         }
@@ -2921,9 +4197,14 @@ impl FactorishState {
         Ok(())
     }
 
+    /// Returns the tools whose buildings are unlocked by research or held by the player
     pub fn tool_defs(&self) -> Result<js_sys::Array, JsValue> {
         Ok(tool_defs
             .iter()
+            .filter(|tool| {
+                self.is_item_unlocked(&tool.item_type)
+                    || 0 < self.player.inventory.count_item(&tool.item_type)
+            })
             .map(|tool| {
                 js_sys::Array::of2(
                     &JsValue::from_str(&item_to_str(&tool.item_type)),
@@ -2986,10 +4267,14 @@ impl FactorishState {
                 context.set_fill_style(&js_str!("#00ffff"));
                 context.fill_rect(0., 0., 32., 32.);
             }
-            let mut tool = self.new_structure(item, &Position { x: 0, y: 0 })?;
-            tool.set_rotation(&self.tool_rotation).ok();
-            for depth in 0..3 {
-                tool.draw(self, context, depth, true)?;
+            if let Ok(mut tool) = self.new_structure(item, &Position { x: 0, y: 0 }) {
+                tool.set_rotation(&self.tool_rotation).ok();
+                for depth in 0..3 {
+                    tool.draw(self, context, depth, true)?;
+                }
+            } else {
+                // Tools that are not structures, e.g. train cars
+                render_drop_item(self, context, item, 16, 16)?;
             }
         }
         Ok(())
@@ -3059,6 +4344,7 @@ impl FactorishState {
     /// @param tool the index of the tool item, [0,9]
     /// @returns whether the tool bar item should be re-rendered
     pub fn select_tool(&mut self, tool: i32) -> Result<JsValue, JsValue> {
+        self.clear_area_tools();
         if let Some(SelectedItem::PlayerInventory(item, _)) = self.selected_item {
             // We allow only items in tool_defs to present on the tool belt
             // This behavior is different from Factorio, maybe we can allow it
@@ -3203,6 +4489,179 @@ impl FactorishState {
         Ok(())
     }
 
+    fn wire_count(&self, id: StructureId) -> usize {
+        self.power_wires
+            .iter()
+            .filter(|wire| wire.0 == id || wire.1 == id)
+            .count()
+    }
+
+    /// Handles a click with the wire tool. The first click selects a structure and the second
+    /// click connects it to another structure, or disconnects them if they are already connected.
+    ///
+    /// @returns whether the player inventory has changed
+    fn click_wire_tool(&mut self, cursor: &Position) -> Result<bool, JsValue> {
+        let Some(id) = self
+            .structure_id_iter()
+            .find(|(_, s)| {
+                (s.power_sink() || s.power_source())
+                    && s.bounding_box().intersects_position(*cursor)
+            })
+            .map(|(id, _)| id)
+        else {
+            self.wire_start = None;
+            return Ok(false);
+        };
+        let Some(start_id) = self.wire_start.take() else {
+            self.wire_start = Some(id);
+            return Ok(false);
+        };
+        if start_id == id {
+            return Ok(false);
+        }
+        let (x, y) = (cursor.x as f64 * TILE_SIZE, cursor.y as f64 * TILE_SIZE);
+
+        if let Some(idx) = self
+            .power_wires
+            .iter()
+            .position(|wire| *wire == PowerWire(start_id, id) || *wire == PowerWire(id, start_id))
+        {
+            let wire = self.power_wires.remove(idx);
+            let refunded = self.hand_power_wires.remove(&wire);
+            if refunded {
+                self.player.add_item(&ItemType::CopperWire, 1);
+            }
+            self.rebuild_power_networks();
+            self.new_popup_text("Disconnected".to_string(), x, y)?;
+            return Ok(refunded);
+        }
+
+        let (Some(start), Some(structure)) = (self.get_structure(start_id), self.get_structure(id))
+        else {
+            return Ok(false);
+        };
+        let error = if !(start.power_sink() && structure.power_source()
+            || start.power_source() && structure.power_sink())
+        {
+            Some("Cannot connect these structures")
+        } else if (start.wire_reach().min(structure.wire_reach()) as i32)
+            < start.bounding_box().distance(&structure.bounding_box())
+        {
+            Some("Out of wire reach")
+        } else if MAX_WIRES <= self.wire_count(start_id) || MAX_WIRES <= self.wire_count(id) {
+            Some("Too many wires")
+        } else if self.player.inventory.count_item(&ItemType::CopperWire) == 0 {
+            Some("No copper wire")
+        } else {
+            None
+        };
+        if let Some(error) = error {
+            self.new_popup_text(error.to_string(), x, y)?;
+            return Ok(false);
+        }
+
+        self.power_wires.push(PowerWire(start_id, id));
+        self.hand_power_wires.insert(PowerWire(start_id, id));
+        self.player.inventory.remove_item(&ItemType::CopperWire);
+        self.rebuild_power_networks();
+        Ok(true)
+    }
+
+    /// Handles a click with a signal wire tool, like [`Self::click_wire_tool`].
+    /// Clicking the front tile of a combinator selects its output terminal.
+    ///
+    /// @returns whether the player inventory has changed
+    fn click_signal_wire_tool(
+        &mut self,
+        cursor: &Position,
+        color: WireColor,
+    ) -> Result<bool, JsValue> {
+        let Some(terminal) = self
+            .structure_id_iter()
+            .find(|(_, s)| s.circuit_connectable() && s.bounding_box().intersects_position(*cursor))
+            .map(|(id, s)| CircuitTerminal {
+                id,
+                output: s.circuit_output_terminal() == Some(*cursor),
+            })
+        else {
+            self.signal_wire_start = None;
+            return Ok(false);
+        };
+        let start = match self.signal_wire_start.take() {
+            Some((start_color, start)) if start_color == color => start,
+            _ => {
+                self.signal_wire_start = Some((color, terminal));
+                return Ok(false);
+            }
+        };
+        if start == terminal {
+            return Ok(false);
+        }
+        let (x, y) = (cursor.x as f64 * TILE_SIZE, cursor.y as f64 * TILE_SIZE);
+
+        if let Some(idx) = self
+            .signal_wires
+            .iter()
+            .position(|wire| wire.connects(color, start, terminal))
+        {
+            self.signal_wires.remove(idx);
+            self.player.add_item(&color.item(), 1);
+            self.circuit_networks = build_circuit_networks(&self.signal_wires);
+            self.new_popup_text("Disconnected".to_string(), x, y)?;
+            return Ok(true);
+        }
+
+        let (Some(start_s), Some(structure)) = (
+            self.get_structure(start.id),
+            self.get_structure(terminal.id),
+        ) else {
+            return Ok(false);
+        };
+        let error = if (start_s.wire_reach().min(structure.wire_reach()) as i32)
+            < start_s.bounding_box().distance(&structure.bounding_box())
+        {
+            Some("Out of wire reach")
+        } else if self.player.inventory.count_item(&color.item()) == 0 {
+            Some("No wire")
+        } else {
+            None
+        };
+        if let Some(error) = error {
+            self.new_popup_text(error.to_string(), x, y)?;
+            return Ok(false);
+        }
+
+        self.signal_wires.push(SignalWire(color, start, terminal));
+        self.player.inventory.remove_item(&color.item());
+        self.circuit_networks = build_circuit_networks(&self.signal_wires);
+        Ok(true)
+    }
+
+    /// Description of the circuit settings and the signals of the networks connected to the structure
+    fn circuit_desc(&self, id: StructureId, structure: &dyn Structure) -> String {
+        let mut ret = structure
+            .circuit_config()
+            .map(|config| config.describe())
+            .unwrap_or_default();
+        for network in &self.circuit_networks {
+            if network.terminals.iter().any(|terminal| terminal.id == id) {
+                ret += &format!(
+                    "{:?} network:<br>{}",
+                    network.color,
+                    describe_signals(&network.signals)
+                );
+            }
+        }
+        ret
+    }
+
+    fn rebuild_power_networks(&mut self) {
+        self.power_networks = build_power_networks(
+            &StructureDynIter::new_all(&mut self.structures),
+            &self.power_wires,
+        );
+    }
+
     /// Returns an iterator over valid structures
     fn structure_iter(&self) -> impl Iterator<Item = &dyn Structure> {
         self.structures.iter().filter_map(|s| s.dynamic.as_deref())
@@ -3257,3 +4716,66 @@ impl FactorishState {
         Ok(())
     }
 }
+
+#[cfg(test)]
+impl FactorishState {
+    /// A state with an empty map and no graphics for unit tests. It must not call the JavaScript
+    /// callbacks, which are not available in native tests.
+    pub(crate) fn new_test() -> Self {
+        let terrain_params = TerrainParameters {
+            width: 32,
+            height: 32,
+            unlimited: false,
+            terrain_seed: 0,
+            water_noise_threshold: 2.,
+            resource_amount: 0.,
+            resource_distance_factor: 0.,
+            noise_scale: 1.,
+            noise_threshold: 0.,
+            noise_octaves: 1,
+        };
+        let board = terrain::gen_terrain(&terrain_params);
+        Self::with_assets(
+            terrain_params,
+            JsValue::UNDEFINED.unchecked_into(),
+            JsValue::UNDEFINED.unchecked_into(),
+            JsValue::UNDEFINED.unchecked_into(),
+            (vec![], board, GenSet::new()),
+            Assets::with_textures(|_| Ok(JsValue::UNDEFINED.unchecked_into())).unwrap(),
+        )
+        .unwrap()
+    }
+}
+
+#[test]
+fn test_place_starting_belts() {
+    let mut state = FactorishState::new_test();
+    assert!(state.unlocked_technologies.is_empty());
+    assert_eq!(state.tool_belt[2], Some(ItemType::TransportBelt));
+    let belts = state.player.inventory.count_item(&ItemType::TransportBelt);
+    let undergrounds = state
+        .player
+        .inventory
+        .count_item(&ItemType::UndergroundBelt);
+    assert!(0 < belts && 0 < undergrounds);
+
+    // The belts in the starting inventory can be placed before researching them
+    assert!(state
+        .place_tool(&ItemType::TransportBelt, &Position::new(1, 1))
+        .unwrap());
+    assert!(state
+        .place_tool(&ItemType::UndergroundBelt, &Position::new(2, 1))
+        .unwrap());
+    assert_eq!(
+        state.player.inventory.count_item(&ItemType::TransportBelt),
+        belts - 1
+    );
+    assert_eq!(
+        state
+            .player
+            .inventory
+            .count_item(&ItemType::UndergroundBelt),
+        undergrounds - 1
+    );
+    assert_eq!(state.structure_iter().count(), 2);
+}
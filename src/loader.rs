@@ -0,0 +1,377 @@
+use super::{
+    draw_direction_arrow,
+    drop_items::DropItem,
+    gl::{draw_direction_arrow_gl, utils::Flatten},
+    structure::{
+        ItemResponse, ItemResponseResult, RotateErr, Structure, StructureDynIter, StructureId,
+    },
+    transport_belt::TransportBelt,
+    FactorishState, FrameProcResult, Position, Rotation, TILE_SIZE,
+};
+use cgmath::{Matrix3, Matrix4, Rad, Vector3};
+use rotate_enum::RotateEnum;
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+use web_sys::{CanvasRenderingContext2d, WebGlRenderingContext as GL};
+
+/// `Load` takes items from the belt behind and puts them into the structure in front.
+/// `Unload` takes items from the structure behind and puts them onto the belt in front.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Copy, Debug, RotateEnum)]
+pub(crate) enum LoaderMode {
+    Load,
+    Unload,
+}
+
+/// A loader sits at an end of a belt and moves items between the belt and a structure
+/// as fast as the belt delivers them. Items always flow in the direction of `rotation`.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct Loader {
+    position: Position,
+    rotation: Rotation,
+    mode: LoaderMode,
+    #[serde(skip)]
+    target: Option<StructureId>,
+}
+
+impl Loader {
+    pub(crate) fn new(x: i32, y: i32, rotation: Rotation) -> Self {
+        Self {
+            position: Position { x, y },
+            rotation,
+            mode: LoaderMode::Load,
+            target: None,
+        }
+    }
+
+    /// The position of the structure that we load into or unload from.
+    fn target_position(&self) -> Position {
+        match self.mode {
+            LoaderMode::Load => self.position.add(self.rotation.delta()),
+            LoaderMode::Unload => self.position.add(self.rotation.delta_inv()),
+        }
+    }
+
+    fn on_construction_common(
+        &mut self,
+        other_id: StructureId,
+        other: &dyn Structure,
+        construct: bool,
+    ) -> Result<(), JsValue> {
+        if other
+            .bounding_box()
+            .intersects_position(self.target_position())
+        {
+            self.target = if construct { Some(other_id) } else { None };
+        }
+        Ok(())
+    }
+
+    fn load(
+        &mut self,
+        state: &mut FactorishState,
+        structures: &mut StructureDynIter,
+    ) -> Result<FrameProcResult, ()> {
+        let (id, type_) = if let Some((id, item)) = state.find_item(&self.position) {
+            (id, item.type_)
+        } else {
+            return Ok(FrameProcResult::None);
+        };
+        if let Some(structure) = self.target.and_then(|id| structures.get_mut(id)) {
+            if structure.can_input(&type_)
+                && structure
+                    .input(&DropItem::new(type_, self.position.x, self.position.y))
+                    .is_ok()
+            {
+                state.remove_item(id);
+                return Ok(FrameProcResult::InventoryChanged(*structure.position()));
+            }
+        }
+        Ok(FrameProcResult::None)
+    }
+
+    fn unload(
+        &mut self,
+        state: &mut FactorishState,
+        structures: &mut StructureDynIter,
+    ) -> Result<FrameProcResult, ()> {
+        let type_ = if let Some((Some(structure), structures)) =
+            self.target.and_then(|id| structures.exclude_id(id).ok())
+        {
+            let mut items = structure
+                .can_output(&structures)
+                .into_iter()
+                .collect::<Vec<_>>();
+            // Pick items in a deterministic order
+            items.sort();
+            if let Some((type_, _)) = items.into_iter().next() {
+                type_
+            } else {
+                return Ok(FrameProcResult::None);
+            }
+        } else {
+            return Ok(FrameProcResult::None);
+        };
+
+        // The belt speed and the item size limit the throughput, since we cannot put a new item
+        // until the previous one moves away.
+        if state.can_place_object(&self.position).is_err() {
+            return Ok(FrameProcResult::None);
+        }
+        let Some(structure) = self.target.and_then(|id| structures.get_mut(id)) else {
+            return Ok(FrameProcResult::None);
+        };
+        // Take the item out of the structure first, so that a failure cannot duplicate it
+        structure.output(state, &type_)?;
+        let position = *structure.position();
+        state.new_object(&self.position, type_).map_err(|_| ())?;
+        Ok(FrameProcResult::InventoryChanged(position))
+    }
+}
+
+impl Structure for Loader {
+    fn name(&self) -> &str {
+        "Loader"
+    }
+
+    fn position(&self) -> &Position {
+        &self.position
+    }
+
+    fn rotation(&self) -> Option<Rotation> {
+        Some(self.rotation)
+    }
+
+    fn draw(
+        &self,
+        state: &FactorishState,
+        context: &CanvasRenderingContext2d,
+        depth: i32,
+        _is_toolbar: bool,
+    ) -> Result<(), JsValue> {
+        let (x, y) = (self.position.x as f64 * 32., self.position.y as f64 * 32.);
+        match depth {
+            0 => match state.image_belt.as_ref() {
+                Some(img) => {
+                    context.save();
+                    context.translate(x + 16., y + 16.)?;
+                    context.rotate(self.rotation.angle_rad())?;
+                    context.translate(-(x + 16.), -(y + 16.))?;
+                    context
+                        .draw_image_with_image_bitmap_and_sw_and_sh_and_dx_and_dy_and_dw_and_dh(
+                            &img.bitmap,
+                            0.,
+                            0.,
+                            32.,
+                            32.,
+                            x,
+                            y,
+                            32.,
+                            32.,
+                        )?;
+                    context.restore();
+                }
+                None => return Err(JsValue::from_str("belt image not available")),
+            },
+            2 => draw_direction_arrow((x, y), &self.rotation, state, context)?,
+            _ => (),
+        }
+        Ok(())
+    }
+
+    fn draw_gl(
+        &self,
+        state: &FactorishState,
+        gl: &GL,
+        depth: i32,
+        is_ghost: bool,
+    ) -> Result<(), JsValue> {
+        let (x, y) = (
+            self.position.x as f32 + state.viewport.x as f32,
+            self.position.y as f32 + state.viewport.y as f32,
+        );
+        match depth {
+            0 => {
+                let shader = state
+                    .assets
+                    .textured_shader
+                    .as_ref()
+                    .ok_or_else(|| js_str!("Shader not found"))?;
+                gl.use_program(Some(&shader.program));
+                gl.uniform1f(shader.alpha_loc.as_ref(), if is_ghost { 0.5 } else { 1. });
+                TransportBelt::belt_texture_gl(gl, state, shader, |scroll| {
+                    scroll * Matrix3::from_angle_z(Rad(-self.rotation.angle_rad() as f32))
+                })?;
+
+                gl.uniform_matrix4fv_with_f32_array(
+                    shader.transform_loc.as_ref(),
+                    false,
+                    (state.get_world_transform()?
+                        * Matrix4::from_scale(2.)
+                        * Matrix4::from_translation(Vector3::new(x, y, 0.)))
+                    .flatten(),
+                );
+                gl.draw_arrays(GL::TRIANGLE_FAN, 0, 4);
+            }
+            // Always show the direction to tell loaders from belts
+            2 => draw_direction_arrow_gl((x, y), &self.rotation, state, gl)?,
+            _ => (),
+        }
+        Ok(())
+    }
+
+    fn frame_proc(
+        &mut self,
+        _me: StructureId,
+        state: &mut FactorishState,
+        structures: &mut StructureDynIter,
+    ) -> Result<FrameProcResult, ()> {
+        match self.mode {
+            LoaderMode::Load => self.load(state, structures),
+            LoaderMode::Unload => self.unload(state, structures),
+        }
+    }
+
+    fn movable(&self) -> bool {
+        true
+    }
+
+    /// Rotating a loader toggles between loading and unloading, like underground belts.
+    fn rotate(
+        &mut self,
+        _state: &mut FactorishState,
+        others: &StructureDynIter,
+    ) -> Result<(), RotateErr> {
+        self.mode = self.mode.next();
+        self.target = None;
+        for (id, s) in others.dyn_iter_id() {
+            self.on_construction_common(id, s, true)
+                .map_err(RotateErr::Other)?;
+        }
+        Ok(())
+    }
+
+    fn set_rotation(&mut self, rotation: &Rotation) -> Result<(), ()> {
+        self.rotation = *rotation;
+        Ok(())
+    }
+
+    fn item_response(&mut self, item: &DropItem) -> Result<ItemResponseResult, ()> {
+        let result = TransportBelt::transport_item(self.rotation, item)?;
+        if self.mode == LoaderMode::Unload {
+            return Ok(result);
+        }
+        // Hold the items at the center of the tile until they are loaded into the target.
+        if let (ItemResponse::Move(moved_x, moved_y), _) = result {
+            let center_x = (self.position.x as f64 + 0.5) * TILE_SIZE;
+            let center_y = (self.position.y as f64 + 0.5) * TILE_SIZE;
+            let (dx, dy) = self.rotation.delta();
+            let moved_x = match dx {
+                1 => moved_x.min(center_x),
+                -1 => moved_x.max(center_x),
+                _ => moved_x,
+            };
+            let moved_y = match dy {
+                1 => moved_y.min(center_y),
+                -1 => moved_y.max(center_y),
+                _ => moved_y,
+            };
+            return Ok((ItemResponse::Move(moved_x, moved_y), None));
+        }
+        Ok(result)
+    }
+
+    fn on_construction(
+        &mut self,
+        other_id: StructureId,
+        other: &dyn Structure,
+        _others: &StructureDynIter,
+        construct: bool,
+    ) -> Result<(), JsValue> {
+        self.on_construction_common(other_id, other, construct)
+    }
+
+    fn on_construction_self(
+        &mut self,
+        _self_id: StructureId,
+        others: &StructureDynIter,
+        construct: bool,
+    ) -> Result<(), JsValue> {
+        for (id, s) in others.dyn_iter_id() {
+            self.on_construction_common(id, s, construct)?;
+        }
+        Ok(())
+    }
+
+    fn desc(&self, state: &FactorishState) -> String {
+        format!(
+            "Mode: {:?}<br>Target: {}",
+            self.mode,
+            self.target
+                .and_then(|id| state.get_structure(id))
+                .map(|s| s.name())
+                .unwrap_or("None"),
+        )
+    }
+
+    crate::serialize_impl!();
+}
+
+#[test]
+fn test_load_unload() {
+    use super::{
+        chest::Chest, inventory::InventoryType, structure::StructureEntry, InventoryTrait, ItemType,
+    };
+
+    let mut state = FactorishState::new_test();
+    let mut source = Chest::new(&Position::new(0, 0));
+    source
+        .inventory_mut(InventoryType::Storage)
+        .unwrap()
+        .add_items(&ItemType::IronPlate, 2);
+    let mut structures = vec![
+        StructureEntry {
+            gen: 0,
+            dynamic: Some(Box::new(source)),
+        },
+        StructureEntry {
+            gen: 0,
+            dynamic: Some(Box::new(Loader::new(1, 0, Rotation::Right))),
+        },
+        StructureEntry {
+            gen: 0,
+            dynamic: Some(Box::new(Chest::new(&Position::new(2, 0)))),
+        },
+    ];
+    let count = |structures: &[StructureEntry], i: usize| {
+        structures[i]
+            .dynamic
+            .as_deref()
+            .and_then(|s| s.inventory(InventoryType::Storage))
+            .map_or(0, |inv| inv.count_item(&ItemType::IronPlate))
+    };
+
+    let (loader, mut others) = StructureDynIter::new(&mut structures, 1).unwrap();
+    let loader = loader.dynamic.as_deref_mut().unwrap();
+    loader.rotate(&mut state, &others).unwrap();
+    loader
+        .frame_proc(StructureId { id: 1, gen: 0 }, &mut state, &mut others)
+        .unwrap();
+    // The next item waits until the one on the loader moves away
+    loader
+        .frame_proc(StructureId { id: 1, gen: 0 }, &mut state, &mut others)
+        .unwrap();
+    drop(others);
+    assert_eq!(count(&structures, 0), 1);
+    assert_eq!(state.drop_items.items().count(), 1);
+
+    let (loader, mut others) = StructureDynIter::new(&mut structures, 1).unwrap();
+    let loader = loader.dynamic.as_deref_mut().unwrap();
+    loader.rotate(&mut state, &others).unwrap();
+    loader
+        .frame_proc(StructureId { id: 1, gen: 0 }, &mut state, &mut others)
+        .unwrap();
+    drop(others);
+    assert_eq!(state.drop_items.items().count(), 0);
+    assert_eq!(count(&structures, 0), 1);
+    assert_eq!(count(&structures, 2), 1);
+}
@@ -0,0 +1,730 @@
+use super::{
+    construction::Ghost,
+    gl::utils::{enable_buffer, Flatten},
+    inventory::{storage_capacity, InventoryType},
+    items::render_drop_item_gl,
+    structure::{BoundingBox, Structure, StructureEntry},
+    FactorishState, FrameProcResult, Inventory, InventoryTrait, ItemType, Position, TILE_SIZE,
+};
+use cgmath::{Matrix4, Vector3};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+use web_sys::WebGlRenderingContext as GL;
+
+/// Distance in tiles that a robot flies in a tick
+const ROBOT_SPEED: f64 = 0.1;
+/// Maximum number of items a robot can carry at once
+const ROBOT_CAPACITY: usize = 4;
+/// Energy in kilojoules that a robot consumes to fly a tile
+const ROBOT_ENERGY_PER_TILE: f64 = 0.5;
+
+/// The role of a logistic chest in the logistic network.
+///
+/// * `PassiveProvider` offers its contents to requester chests.
+/// * `ActiveProvider` offers its contents to requester chests and pushes the rest to storage chests.
+/// * `Storage` keeps items from active providers and offers them to requester chests.
+/// * `Requester` asks the network for the configured amount of items.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum LogisticChestKind {
+    PassiveProvider,
+    ActiveProvider,
+    Storage,
+    Requester,
+}
+
+impl LogisticChestKind {
+    /// Preference of providers when delivering to requesters, lower is preferred.
+    fn provider_priority(&self) -> Option<u32> {
+        match self {
+            Self::ActiveProvider => Some(0),
+            Self::Storage => Some(1),
+            Self::PassiveProvider => Some(2),
+            Self::Requester => None,
+        }
+    }
+
+    pub(crate) fn color(&self) -> [f32; 4] {
+        match self {
+            Self::PassiveProvider => [1., 0.25, 0.25, 1.],
+            Self::ActiveProvider => [0.75, 0.25, 1., 1.],
+            Self::Storage => [1., 0.85, 0.25, 1.],
+            Self::Requester => [0.25, 0.5, 1., 1.],
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+enum RobotPhase {
+    Fetching,
+    Delivering,
+    Returning,
+}
+
+/// A logistic robot in flight. Docked robots are kept as items in roboports.
+///
+/// Robots refer to the structures by positions rather than StructureIds, so that they can survive
+/// save and load, and they do not break when the structures are removed.
+#[derive(Serialize, Deserialize, Debug)]
+pub(crate) struct LogisticRobot {
+    /// Position in tiles
+    position: (f64, f64),
+    home: Position,
+    source: Position,
+    destination: Position,
+    item: ItemType,
+    /// Number of items to pick up while flying to the source, or carried items afterwards.
+    count: usize,
+    phase: RobotPhase,
+    /// Whether the destination is a ghost to build instead of a logistic chest
+    #[serde(default)]
+    construction: bool,
+}
+
+fn tile_center(position: &Position) -> (f64, f64) {
+    (position.x as f64 + 0.5, position.y as f64 + 0.5)
+}
+
+fn distance(a: (f64, f64), b: (f64, f64)) -> f64 {
+    (a.0 - b.0).hypot(a.1 - b.1)
+}
+
+fn find_structure_mut(
+    structures: &mut [StructureEntry],
+    position: Position,
+) -> Option<&mut (dyn Structure + 'static)> {
+    structures
+        .iter_mut()
+        .filter_map(|s| s.dynamic.as_deref_mut())
+        .find(|s| s.bounding_box().intersects_position(position))
+}
+
+/// Find the nearest storage chest that can accept the item, preferring the ones already having it.
+fn find_storage(
+    structures: &[StructureEntry],
+    item: &ItemType,
+    from: (f64, f64),
+) -> Option<Position> {
+    structures
+        .iter()
+        .filter_map(|s| s.dynamic.as_deref())
+        .filter(|s| s.logistic_chest() == Some(LogisticChestKind::Storage))
+        .filter_map(|s| {
+            let inventory = s.inventory(InventoryType::Storage)?;
+            if storage_capacity(inventory, item, s.storage_slots(), s.slot_filters()) == 0 {
+                return None;
+            }
+            let position = *s.position();
+            Some((
+                inventory.count_item(item) == 0,
+                distance(from, tile_center(&position)),
+                position,
+            ))
+        })
+        .min_by(|a, b| (a.0, a.1).partial_cmp(&(b.0, b.1)).unwrap())
+        .map(|(_, _, position)| position)
+}
+
+impl LogisticRobot {
+    fn target(&self) -> Position {
+        match self.phase {
+            RobotPhase::Fetching => self.source,
+            RobotPhase::Delivering => self.destination,
+            RobotPhase::Returning => self.home,
+        }
+    }
+
+    /// Fly a tick and interact with the structure on arrival.
+    /// Returns true if the robot has docked to a roboport and should be removed from the world.
+    fn proceed(
+        &mut self,
+        structures: &mut [StructureEntry],
+        ghosts: &mut [Ghost],
+        results: &mut Vec<FrameProcResult>,
+    ) -> bool {
+        let target = tile_center(&self.target());
+        let dist = distance(self.position, target);
+        if ROBOT_SPEED < dist {
+            self.position.0 += (target.0 - self.position.0) / dist * ROBOT_SPEED;
+            self.position.1 += (target.1 - self.position.1) / dist * ROBOT_SPEED;
+            return false;
+        }
+        self.position = target;
+
+        match self.phase {
+            RobotPhase::Fetching => {
+                self.count = if let Some(s) = find_structure_mut(structures, self.source)
+                    .filter(|s| s.logistic_chest().is_some())
+                {
+                    let taken = -s.add_inventory(
+                        InventoryType::Storage,
+                        &self.item,
+                        -(self.count as isize),
+                    );
+                    if 0 < taken {
+                        results.push(FrameProcResult::InventoryChanged(*s.position()));
+                    }
+                    taken as usize
+                } else {
+                    0
+                };
+                self.phase = if 0 < self.count {
+                    RobotPhase::Delivering
+                } else {
+                    RobotPhase::Returning
+                };
+            }
+            RobotPhase::Delivering => {
+                if self.construction {
+                    if let Some(ghost) = ghosts.iter_mut().find(|g| {
+                        !g.delivered
+                            && g.position() == self.destination
+                            && g.item() == Some(self.item)
+                    }) {
+                        ghost.delivered = true;
+                        self.count -= 1;
+                    }
+                    // If the ghost is gone, the item is handled like an ordinary delivery below
+                    self.construction = false;
+                } else if let Some(s) = find_structure_mut(structures, self.destination)
+                    .filter(|s| s.logistic_chest().is_some())
+                {
+                    let added =
+                        s.add_inventory(InventoryType::Storage, &self.item, self.count as isize);
+                    if 0 < added {
+                        self.count -= added as usize;
+                        results.push(FrameProcResult::InventoryChanged(*s.position()));
+                    }
+                }
+                if self.count == 0 {
+                    self.phase = RobotPhase::Returning;
+                } else if let Some(storage) = find_storage(structures, &self.item, self.position) {
+                    // The destination is full or gone. Bring the rest to a storage chest.
+                    self.destination = storage;
+                }
+                // If there is no storage at all, hover until one becomes available.
+            }
+            RobotPhase::Returning => {
+                if let Some(s) = find_structure_mut(structures, self.home) {
+                    if s.dock_robot() {
+                        return true;
+                    }
+                }
+                // The home roboport is gone or full. Find another one to dock.
+                if let Some(home) = structures
+                    .iter()
+                    .filter_map(|s| s.dynamic.as_deref())
+                    .filter(|s| s.logistic_range().is_some())
+                    .map(|s| *s.position())
+                    .filter(|position| *position != self.home)
+                    .min_by(|a, b| {
+                        distance(self.position, tile_center(a))
+                            .partial_cmp(&distance(self.position, tile_center(b)))
+                            .unwrap()
+                    })
+                {
+                    self.home = home;
+                }
+            }
+        }
+        false
+    }
+}
+
+/// Coverage of a roboport: its bounding box extended by the range
+fn coverage(bbox: &BoundingBox, range: i32) -> BoundingBox {
+    BoundingBox {
+        x0: bbox.x0 - range,
+        y0: bbox.y0 - range,
+        x1: bbox.x1 + range,
+        y1: bbox.y1 + range,
+    }
+}
+
+fn overlaps(a: &BoundingBox, b: &BoundingBox) -> bool {
+    a.x0 < b.x1 && b.x0 < a.x1 && a.y0 < b.y1 && b.y0 < a.y1
+}
+
+struct RoboportInfo {
+    idx: usize,
+    position: Position,
+    coverage: BoundingBox,
+    network: usize,
+}
+
+struct ChestInfo {
+    position: Position,
+    kind: LogisticChestKind,
+    inventory: Inventory,
+    requests: Inventory,
+    slots: usize,
+    filters: Vec<Option<ItemType>>,
+    network: usize,
+}
+
+/// Group roboports into logistic networks whose coverage areas overlap with each other.
+fn build_logistic_networks(structures: &[StructureEntry]) -> Vec<RoboportInfo> {
+    let mut roboports = structures
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, s)| {
+            let s = s.dynamic.as_deref()?;
+            Some(RoboportInfo {
+                idx,
+                position: *s.position(),
+                coverage: coverage(&s.bounding_box(), s.logistic_range()?),
+                network: idx,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    // Propagate the smallest network label until it converges
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for i in 0..roboports.len() {
+            for j in 0..roboports.len() {
+                if roboports[j].network < roboports[i].network
+                    && overlaps(&roboports[i].coverage, &roboports[j].coverage)
+                {
+                    roboports[i].network = roboports[j].network;
+                    changed = true;
+                }
+            }
+        }
+    }
+    roboports
+}
+
+/// Number of items that robots in flight are going to bring to the destination.
+fn incoming(robots: &[LogisticRobot], destination: Position, item: &ItemType) -> usize {
+    robots
+        .iter()
+        .filter(|r| {
+            r.phase != RobotPhase::Returning && r.destination == destination && r.item == *item
+        })
+        .map(|r| r.count)
+        .sum()
+}
+
+/// Number of items that robots in flight are going to pick up from the source.
+fn outgoing(robots: &[LogisticRobot], source: Position, item: &ItemType) -> usize {
+    robots
+        .iter()
+        .filter(|r| r.phase == RobotPhase::Fetching && r.source == source && r.item == *item)
+        .map(|r| r.count)
+        .sum()
+}
+
+/// Find a delivery in the network as (source, destination, item, count).
+/// Requests are served first, then active providers are emptied into storage chests.
+fn find_job(
+    network: usize,
+    chests: &[ChestInfo],
+    robots: &[LogisticRobot],
+) -> Option<(Position, Position, ItemType, usize)> {
+    let available = |chest: &ChestInfo, item: &ItemType| {
+        chest
+            .inventory
+            .count_item(item)
+            .saturating_sub(outgoing(robots, chest.position, item))
+    };
+
+    for requester in chests
+        .iter()
+        .filter(|c| c.network == network && c.kind == LogisticChestKind::Requester)
+    {
+        let mut requests = requester.requests.iter().collect::<Vec<_>>();
+        requests.sort();
+        for (item, &requested) in requests {
+            let deficit = requested.saturating_sub(
+                requester.inventory.count_item(item) + incoming(robots, requester.position, item),
+            );
+            if deficit == 0 {
+                continue;
+            }
+            let (x, y) = (requester.position.x, requester.position.y);
+            if let Some((provider, count)) = chests
+                .iter()
+                .filter(|c| c.network == network)
+                .filter_map(|c| Some((c, c.kind.provider_priority()?, available(c, item))))
+                .filter(|(_, _, count)| 0 < *count)
+                .min_by_key(|(c, priority, _)| {
+                    (
+                        *priority,
+                        (c.position.x - x).pow(2) + (c.position.y - y).pow(2),
+                    )
+                })
+                .map(|(c, _, count)| (c, count))
+            {
+                return Some((
+                    provider.position,
+                    requester.position,
+                    *item,
+                    deficit.min(count).min(ROBOT_CAPACITY),
+                ));
+            }
+        }
+    }
+
+    for provider in chests
+        .iter()
+        .filter(|c| c.network == network && c.kind == LogisticChestKind::ActiveProvider)
+    {
+        let mut items = provider.inventory.keys().collect::<Vec<_>>();
+        items.sort();
+        for item in items {
+            let count = available(provider, item);
+            if count == 0 {
+                continue;
+            }
+            let (x, y) = (provider.position.x, provider.position.y);
+            if let Some(storage) = chests
+                .iter()
+                .filter(|c| c.network == network && c.kind == LogisticChestKind::Storage)
+                .filter(|c| {
+                    incoming(robots, c.position, item)
+                        < storage_capacity(&c.inventory, item, c.slots, &c.filters)
+                })
+                .min_by_key(|c| {
+                    (
+                        c.inventory.count_item(item) == 0,
+                        (c.position.x - x).pow(2) + (c.position.y - y).pow(2),
+                    )
+                })
+            {
+                return Some((
+                    provider.position,
+                    storage.position,
+                    *item,
+                    count.min(ROBOT_CAPACITY),
+                ));
+            }
+        }
+    }
+    None
+}
+
+/// Find a provider in the network to bring the item for a ghost waiting for construction,
+/// as (source, destination, item).
+fn find_construction_job(
+    network: usize,
+    chests: &[ChestInfo],
+    ghosts: &[GhostInfo],
+    robots: &[LogisticRobot],
+) -> Option<(Position, Position, ItemType)> {
+    ghosts
+        .iter()
+        .filter(|g| g.network == network && incoming(robots, g.position, &g.item) == 0)
+        .find_map(|ghost| {
+            let (x, y) = (ghost.position.x, ghost.position.y);
+            let provider = chests
+                .iter()
+                .filter(|c| c.network == network)
+                .filter_map(|c| Some((c, c.kind.provider_priority()?)))
+                .filter(|(c, _)| {
+                    outgoing(robots, c.position, &ghost.item) < c.inventory.count_item(&ghost.item)
+                })
+                .min_by_key(|(c, priority)| {
+                    (
+                        *priority,
+                        (c.position.x - x).pow(2) + (c.position.y - y).pow(2),
+                    )
+                })?
+                .0;
+            Some((provider.position, ghost.position, ghost.item))
+        })
+}
+
+struct GhostInfo {
+    position: Position,
+    item: ItemType,
+    network: usize,
+}
+
+/// Move the robots in flight and dispatch idle robots from roboports to deliver items.
+/// Bringing items to ghosts for construction takes priority over the deliveries between chests.
+/// It should be called while the structures are moved out of the state.
+pub(crate) fn simulate_logistics(
+    robots: &mut Vec<LogisticRobot>,
+    structures: &mut [StructureEntry],
+    ghosts: &mut [Ghost],
+) -> Vec<FrameProcResult> {
+    let mut results = vec![];
+    robots.retain_mut(|robot| !robot.proceed(structures, ghosts, &mut results));
+
+    let roboports = build_logistic_networks(structures);
+    if roboports.is_empty() {
+        return results;
+    }
+
+    let chests = structures
+        .iter()
+        .filter_map(|s| {
+            let s = s.dynamic.as_deref()?;
+            let kind = s.logistic_chest()?;
+            let position = *s.position();
+            let network = roboports
+                .iter()
+                .find(|r| r.coverage.intersects_position(position))?
+                .network;
+            Some(ChestInfo {
+                position,
+                kind,
+                inventory: s.inventory(InventoryType::Storage)?.clone(),
+                requests: s.logistic_requests().cloned().unwrap_or_default(),
+                slots: s.storage_slots(),
+                filters: s.slot_filters().to_vec(),
+                network,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let ghosts = ghosts
+        .iter()
+        .filter(|g| !g.delivered)
+        .filter_map(|g| {
+            let position = g.position();
+            Some(GhostInfo {
+                position,
+                item: g.item()?,
+                network: roboports
+                    .iter()
+                    .find(|r| r.coverage.intersects_position(position))?
+                    .network,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    // Each roboport launches at most one robot per tick
+    for roboport in &roboports {
+        let Some((source, destination, item, count, construction)) =
+            find_construction_job(roboport.network, &chests, &ghosts, robots)
+                .map(|(source, destination, item)| (source, destination, item, 1, true))
+                .or_else(|| {
+                    find_job(roboport.network, &chests, robots).map(
+                        |(source, destination, item, count)| {
+                            (source, destination, item, count, false)
+                        },
+                    )
+                })
+        else {
+            continue;
+        };
+        let home = tile_center(&roboport.position);
+        let (src, dst) = (tile_center(&source), tile_center(&destination));
+        let energy = (distance(home, src) + distance(src, dst) + distance(dst, home))
+            * ROBOT_ENERGY_PER_TILE;
+        if let Some(s) = structures[roboport.idx].dynamic.as_deref_mut() {
+            if s.launch_robot(energy) {
+                results.push(FrameProcResult::InventoryChanged(roboport.position));
+                robots.push(LogisticRobot {
+                    position: home,
+                    home: roboport.position,
+                    source,
+                    destination,
+                    item,
+                    count,
+                    phase: RobotPhase::Fetching,
+                    construction,
+                });
+            }
+        }
+    }
+    results
+}
+
+impl FactorishState {
+    pub(super) fn render_logistic_robots_gl(&self, gl: &GL) -> Result<(), JsValue> {
+        const ROBOT_SIZE: f32 = 0.4;
+        let shader = self
+            .assets
+            .flat_shader
+            .as_ref()
+            .ok_or_else(|| js_str!("Shader not found"))?;
+        for robot in &self.logistic_robots {
+            let (x, y) = (
+                robot.position.0 as f32 + self.viewport.x as f32,
+                robot.position.1 as f32 + self.viewport.y as f32,
+            );
+            gl.use_program(Some(&shader.program));
+            gl.uniform4fv_with_f32_array(shader.color_loc.as_ref(), &[0.3, 0.3, 0.35, 1.]);
+            enable_buffer(gl, &self.assets.screen_buffer, 2, shader.vertex_position);
+            gl.uniform_matrix4fv_with_f32_array(
+                shader.transform_loc.as_ref(),
+                false,
+                (self.get_world_transform()?
+                    * Matrix4::from_scale(2.)
+                    * Matrix4::from_translation(Vector3::new(
+                        x - ROBOT_SIZE / 2.,
+                        y - ROBOT_SIZE / 2.,
+                        0.,
+                    ))
+                    * Matrix4::from_scale(ROBOT_SIZE))
+                .flatten(),
+            );
+            gl.draw_arrays(GL::TRIANGLE_FAN, 0, 4);
+
+            if robot.phase == RobotPhase::Delivering {
+                render_drop_item_gl(
+                    self,
+                    gl,
+                    &robot.item,
+                    robot.position.0 * TILE_SIZE,
+                    robot.position.1 * TILE_SIZE,
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Draw a small colored square at the corner of a tile to tell the kind of a logistic structure.
+pub(crate) fn draw_logistic_marker_gl(
+    (x, y): (f32, f32),
+    color: &[f32; 4],
+    state: &FactorishState,
+    gl: &GL,
+) -> Result<(), JsValue> {
+    const MARKER_SIZE: f32 = 0.3;
+    let shader = state
+        .assets
+        .flat_shader
+        .as_ref()
+        .ok_or_else(|| js_str!("Shader not found"))?;
+    gl.use_program(Some(&shader.program));
+    gl.uniform4fv_with_f32_array(shader.color_loc.as_ref(), color);
+    enable_buffer(gl, &state.assets.screen_buffer, 2, shader.vertex_position);
+    gl.uniform_matrix4fv_with_f32_array(
+        shader.transform_loc.as_ref(),
+        false,
+        (state.get_world_transform()?
+            * Matrix4::from_scale(2.)
+            * Matrix4::from_translation(Vector3::new(
+                x + 1. - MARKER_SIZE,
+                y + 1. - MARKER_SIZE,
+                0.,
+            ))
+            * Matrix4::from_scale(MARKER_SIZE))
+        .flatten(),
+    );
+    gl.draw_arrays(GL::TRIANGLE_FAN, 0, 4);
+    Ok(())
+}
+
+/// Draw the outline of the logistic coverage area of a roboport.
+pub(crate) fn draw_logistic_coverage_gl(
+    bbox: &BoundingBox,
+    range: i32,
+    state: &FactorishState,
+    gl: &GL,
+) -> Result<(), JsValue> {
+    let shader = state
+        .assets
+        .flat_shader
+        .as_ref()
+        .ok_or_else(|| js_str!("Shader not found"))?;
+    let area = coverage(bbox, range);
+    gl.use_program(Some(&shader.program));
+    gl.uniform4fv_with_f32_array(shader.color_loc.as_ref(), &[1., 0.6, 0., 0.75]);
+    enable_buffer(gl, &state.assets.screen_buffer, 2, shader.vertex_position);
+    gl.uniform_matrix4fv_with_f32_array(
+        shader.transform_loc.as_ref(),
+        false,
+        (state.get_world_transform()?
+            * Matrix4::from_scale(2.)
+            * Matrix4::from_translation(Vector3::new(
+                area.x0 as f32 + state.viewport.x as f32,
+                area.y0 as f32 + state.viewport.y as f32,
+                0.,
+            ))
+            * Matrix4::from_nonuniform_scale(
+                (area.x1 - area.x0) as f32,
+                (area.y1 - area.y0) as f32,
+                1.,
+            ))
+        .flatten(),
+    );
+    gl.draw_arrays(GL::LINE_LOOP, 0, 4);
+    Ok(())
+}
+
+#[test]
+fn test_find_job() {
+    let chest =
+        |x: i32, kind: LogisticChestKind, inventory: Inventory, requests: Inventory| ChestInfo {
+            position: Position::new(x, 0),
+            kind,
+            inventory,
+            requests,
+            slots: 48,
+            filters: vec![],
+            network: 0,
+        };
+    let chests = vec![
+        chest(
+            0,
+            LogisticChestKind::Requester,
+            hash_map!(ItemType::Gear => 8),
+            hash_map!(ItemType::Gear => 10),
+        ),
+        chest(
+            1,
+            LogisticChestKind::PassiveProvider,
+            hash_map!(ItemType::Gear => 10),
+            Inventory::new(),
+        ),
+        chest(
+            5,
+            LogisticChestKind::ActiveProvider,
+            hash_map!(ItemType::Gear => 1),
+            Inventory::new(),
+        ),
+    ];
+
+    // Active providers are preferred over nearer passive providers
+    assert_eq!(
+        find_job(0, &chests, &[]),
+        Some((Position::new(5, 0), Position::new(0, 0), ItemType::Gear, 1))
+    );
+
+    // Items already on the way are taken into account
+    let robot = LogisticRobot {
+        position: (0., 0.),
+        home: Position::new(0, 0),
+        source: Position::new(5, 0),
+        destination: Position::new(0, 0),
+        item: ItemType::Gear,
+        count: 1,
+        phase: RobotPhase::Fetching,
+        construction: false,
+    };
+    assert_eq!(
+        find_job(0, &chests, &[robot]),
+        Some((Position::new(1, 0), Position::new(0, 0), ItemType::Gear, 1))
+    );
+
+    // Ghosts take an item from the preferred provider, once at a time
+    let ghosts = [GhostInfo {
+        position: Position::new(3, 0),
+        item: ItemType::Gear,
+        network: 0,
+    }];
+    assert_eq!(
+        find_construction_job(0, &chests, &ghosts, &[]),
+        Some((Position::new(5, 0), Position::new(3, 0), ItemType::Gear))
+    );
+    let robot = LogisticRobot {
+        position: (0., 0.),
+        home: Position::new(0, 0),
+        source: Position::new(5, 0),
+        destination: Position::new(3, 0),
+        item: ItemType::Gear,
+        count: 1,
+        phase: RobotPhase::Fetching,
+        construction: true,
+    };
+    assert_eq!(find_construction_job(0, &chests, &ghosts, &[robot]), None);
+
+    // Other networks are not served
+    assert_eq!(find_job(1, &chests, &[]), None);
+}
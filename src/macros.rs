@@ -26,7 +26,10 @@ extern "C" {
 /// Another option is to use js_sys::console, but it does not have variadic macros and it's too annoying to
 /// write like `log_1(&format("...", a, b, ...))` everytime.
 pub(crate) fn log_wrapper(s: &str) {
-    log(s);
+    // Native unit tests have no console to call
+    if cfg!(target_arch = "wasm32") {
+        log(s);
+    }
 }
 
 /// format-like macro that returns js_sys::String
@@ -0,0 +1,158 @@
+use super::{
+    inventory::Inventory, items::ItemType, structure::StructureId, FactorishState, Recipe,
+};
+
+/// Lowest ratio that speed or energy consumption can be reduced to by modules
+const MIN_RATIO: f64 = 0.2;
+
+/// Combined effects of modules on a machine, as ratios added to the base rates.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) struct ModuleEffect {
+    pub speed: f64,
+    pub consumption: f64,
+    pub productivity: f64,
+}
+
+impl ModuleEffect {
+    /// Returns the effect of a single module, or `None` if the item is not a module.
+    pub(crate) fn of_item(item: &ItemType) -> Option<Self> {
+        Some(match item {
+            ItemType::SpeedModule => Self {
+                speed: 0.2,
+                consumption: 0.5,
+                productivity: 0.,
+            },
+            ItemType::EfficiencyModule => Self {
+                speed: 0.,
+                consumption: -0.3,
+                productivity: 0.,
+            },
+            ItemType::ProductivityModule => Self {
+                speed: -0.05,
+                consumption: 0.4,
+                productivity: 0.04,
+            },
+            _ => return None,
+        })
+    }
+
+    /// Sum of the effects of all modules in the inventory
+    pub(crate) fn from_inventory(inventory: &Inventory) -> Self {
+        inventory
+            .iter()
+            .filter_map(|(item, count)| Some(Self::of_item(item)?.scale(*count as f64)))
+            .fold(Self::default(), Self::add)
+    }
+
+    pub(crate) fn add(self, other: Self) -> Self {
+        Self {
+            speed: self.speed + other.speed,
+            consumption: self.consumption + other.consumption,
+            productivity: self.productivity + other.productivity,
+        }
+    }
+
+    pub(crate) fn scale(self, factor: f64) -> Self {
+        Self {
+            speed: self.speed * factor,
+            consumption: self.consumption * factor,
+            productivity: self.productivity * factor,
+        }
+    }
+
+    pub(crate) fn speed_ratio(&self) -> f64 {
+        (1. + self.speed).max(MIN_RATIO)
+    }
+
+    pub(crate) fn consumption_ratio(&self) -> f64 {
+        (1. + self.consumption).max(MIN_RATIO)
+    }
+
+    pub(crate) fn describe(&self) -> String {
+        if *self == Self::default() {
+            return String::new();
+        }
+        format!(
+            "Speed: {:+.0}%<br>Energy: {:+.0}%<br>Productivity: {:+.0}%<br>",
+            (self.speed_ratio() - 1.) * 100.,
+            (self.consumption_ratio() - 1.) * 100.,
+            self.productivity * 100.
+        )
+    }
+}
+
+/// Effect on the machine `me` by its own modules and the beacons around it
+pub(crate) fn module_effect(
+    state: &FactorishState,
+    me: StructureId,
+    modules: &Inventory,
+) -> ModuleEffect {
+    let effect = ModuleEffect::from_inventory(modules);
+    state
+        .beacon_effects
+        .get(&me)
+        .map_or(effect, |beacon| effect.add(*beacon))
+}
+
+pub(crate) fn is_module(item: &ItemType) -> bool {
+    ModuleEffect::of_item(item).is_some()
+}
+
+/// [`get_powered_progress`](crate::structure::get_powered_progress) with module effects applied.
+/// The energy to deduct for the returned progress is `progress * get_module_power_cost(recipe, effect)`.
+pub(crate) fn get_module_progress(
+    power: f64,
+    progress: f64,
+    recipe: &Recipe,
+    effect: &ModuleEffect,
+) -> f64 {
+    (power / get_module_power_cost(recipe, effect))
+        .min(effect.speed_ratio() / recipe.recipe_time / 3.)
+        .min(1. - progress)
+}
+
+pub(crate) fn get_module_power_cost(recipe: &Recipe, effect: &ModuleEffect) -> f64 {
+    recipe.power_cost * effect.consumption_ratio()
+}
+
+/// Accumulate the productivity bonus of a finished craft and returns whether
+/// a bonus craft is earned.
+pub(crate) fn proceed_productivity(bonus_progress: &mut f64, effect: &ModuleEffect) -> bool {
+    *bonus_progress += effect.productivity;
+    if 1. <= *bonus_progress {
+        *bonus_progress -= 1.;
+        true
+    } else {
+        false
+    }
+}
+
+#[test]
+fn test_module_progress() {
+    use super::inventory::InventoryTrait;
+
+    let recipe = Recipe::new(
+        hash_map!(ItemType::IronPlate => 1),
+        hash_map!(ItemType::Gear => 1),
+        20.,
+        50.,
+    );
+    let mut inventory = Inventory::new();
+    inventory.add_items(&ItemType::SpeedModule, 2);
+    let effect = ModuleEffect::from_inventory(&inventory);
+    assert!((effect.speed_ratio() - 1.4).abs() < 1e-6);
+    assert!((effect.consumption_ratio() - 2.).abs() < 1e-6);
+    let base = get_module_progress(1000., 0., &recipe, &ModuleEffect::default());
+    let fast = get_module_progress(1000., 0., &recipe, &effect);
+    assert!((fast / base - 1.4).abs() < 1e-6);
+
+    let mut bonus = 0.;
+    let productivity = ModuleEffect {
+        productivity: 0.25,
+        ..ModuleEffect::default()
+    };
+    let bonuses = (0..8)
+        .filter(|_| proceed_productivity(&mut bonus, &productivity))
+        .count();
+    assert_eq!(bonuses, 2);
+}
@@ -1,15 +1,16 @@
-use crate::structure::Size;
+use crate::structure::{BoundingBox, Size};
 
 use super::{
     draw_direction_arrow,
     drop_items::hit_check,
     gl::{
-        draw_direction_arrow_gl,
+        draw_direction_arrow_gl, draw_electricity_alarm_gl,
         utils::{enable_buffer, Flatten},
     },
     inventory::{Inventory, InventoryTrait, InventoryType},
     items::ItemType,
-    structure::{get_powered_progress, RotateErr, Structure, StructureDynIter, StructureId},
+    modules::{get_module_power_cost, get_module_progress, module_effect, ModuleEffect},
+    structure::{default_add_inventory, RotateErr, Structure, StructureDynIter, StructureId},
     DropItem, FactorishState, FrameProcResult, Position, Recipe, Rotation, TempEnt, COAL_POWER,
     TILE_SIZE,
 };
@@ -21,15 +22,34 @@ use web_sys::{CanvasRenderingContext2d, WebGlRenderingContext as GL};
 
 const FUEL_CAPACITY: usize = 10;
 
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub(crate) enum MineKind {
+    /// 2x2 mine burning coal, mining the tiles under itself
+    #[default]
+    Burner,
+    /// 3x3 drill powered by electricity, mining a 5x5 area
+    Electric,
+}
+
 #[derive(Serialize, Deserialize)]
 pub(crate) struct OreMine {
     position: Position,
     rotation: Rotation,
+    #[serde(default)]
+    kind: MineKind,
+    /// Accumulated mining productivity bonus. When it reaches 1, an ore is mined without
+    /// depleting the deposit.
+    #[serde(default)]
+    bonus_progress: f64,
     progress: f64,
     power: f64,
     max_power: f64,
     recipe: Option<Recipe>,
     input_inventory: Inventory,
+    #[serde(default)]
+    module_inventory: Inventory,
+    #[serde(skip)]
+    module_effect: ModuleEffect,
     #[serde(skip)]
     output_structure: Option<StructureId>,
     #[serde(skip)]
@@ -41,16 +61,38 @@ impl OreMine {
         OreMine {
             position: Position { x, y },
             rotation,
+            kind: MineKind::Burner,
+            bonus_progress: 0.,
             progress: 0.,
             power: 25., // TODO: Have some initial energy for debugging, should be zero
             max_power: 25.,
             recipe: None,
             input_inventory: Inventory::new(),
+            module_inventory: Inventory::new(),
+            module_effect: ModuleEffect::default(),
             output_structure: None,
             digging: false,
         }
     }
 
+    pub(crate) fn new_electric(x: i32, y: i32, rotation: Rotation) -> Self {
+        OreMine {
+            kind: MineKind::Electric,
+            power: 0.,
+            max_power: 20.,
+            ..Self::new(x, y, rotation)
+        }
+    }
+
+    /// The area of tiles this mine digs ores from
+    fn mining_area(&self) -> BoundingBox {
+        let bbox = self.bounding_box();
+        match self.kind {
+            MineKind::Burner => bbox,
+            MineKind::Electric => bbox.expand(1),
+        }
+    }
+
     fn on_construction_common(
         &mut self,
         other_id: StructureId,
@@ -78,6 +120,14 @@ impl OreMine {
     fn output_port_pos(&self) -> Position {
         let x = self.position.x;
         let y = self.position.y;
+        if self.kind == MineKind::Electric {
+            // The center of the facing edge
+            let (dx, dy) = self.rotation.delta();
+            return Position {
+                x: x + 1 + dx,
+                y: y + 1 + dy,
+            };
+        }
         let x = if matches!(self.rotation, Rotation::Right | Rotation::Bottom) {
             x + 1
         } else {
@@ -94,7 +144,10 @@ impl OreMine {
 
 impl Structure for OreMine {
     fn name(&self) -> &str {
-        "Ore Mine"
+        match self.kind {
+            MineKind::Burner => "Ore Mine",
+            MineKind::Electric => "Electric Mining Drill",
+        }
     }
 
     fn position(&self) -> &Position {
@@ -102,7 +155,10 @@ impl Structure for OreMine {
     }
 
     fn size(&self) -> Size {
-        Size::new(2, 2)
+        match self.kind {
+            MineKind::Burner => Size::new(2, 2),
+            MineKind::Electric => Size::new(3, 3),
+        }
     }
 
     fn draw(
@@ -211,7 +267,7 @@ impl Structure for OreMine {
                     (state.get_world_transform()?
                         * Matrix4::from_scale(2.)
                         * Matrix4::from_translation(Vector3::new(x, y, 0.))
-                        * Matrix4::from_scale(2.))
+                        * Matrix4::from_scale(self.size().width as f32))
                     .flatten(),
                 );
                 gl.bind_texture(GL::TEXTURE_2D, Some(&state.assets.tex_ore_mine));
@@ -243,7 +299,14 @@ impl Structure for OreMine {
                     draw_direction_arrow_gl(output_pos_f32, &self.rotation, state, gl)?;
                 }
                 if !is_ghost {
-                    crate::draw_fuel_alarm_gl_impl!(self, state, gl);
+                    match self.kind {
+                        MineKind::Burner => crate::draw_fuel_alarm_gl_impl!(self, state, gl),
+                        MineKind::Electric => {
+                            if self.recipe.is_some() && self.power == 0. {
+                                draw_electricity_alarm_gl((x, y), state, gl)?;
+                            }
+                        }
+                    }
                 }
             }
             _ => (),
@@ -257,7 +320,7 @@ impl Structure for OreMine {
         };
 
         let expected_outputs = self
-            .bounding_box()
+            .mining_area()
             .iter_tiles()
             .filter_map(|p| state.tile_at(&p).and_then(|t| t.ore))
             .fold(
@@ -274,7 +337,7 @@ impl Structure for OreMine {
             .fold("".to_string(), |acc, cur| acc + &cur);
 
         // Progress bar
-        format!("{}{}{}{}{}",
+        format!("{}{}{}{}{}{}",
             format!("Progress: {:.0}%<br>", self.progress * 100.),
             "<div style='position: relative; width: 100px; height: 10px; background-color: #001f1f; margin: 2px; border: 1px solid #3f3f3f'>",
             format!("<div style='position: absolute; width: {}px; height: 10px; background-color: #ff00ff'></div></div>",
@@ -283,7 +346,9 @@ impl Structure for OreMine {
                 <div style='position: absolute; width: {}px; height: 10px; background-color: #ff00ff'></div></div>"#,
                 self.power,
                 if 0. < self.max_power { (self.power) / self.max_power * 100. } else { 0. }),
-            format!("Expected output:<br>{}", if expected_output_fmt.is_empty() { "None" } else { &expected_output_fmt }))
+            format!("Expected output:<br>{}", if expected_output_fmt.is_empty() { "None" } else { &expected_output_fmt }),
+            format!("Mining productivity: +{:.0}%<br>{}", state.mining_productivity() * 100.,
+                self.module_effect.describe()))
         // getHTML(generateItemImage("time", true, this.recipe.time), true) + "<br>" +
         // "Outputs: <br>" +
         // getHTML(generateItemImage(this.recipe.output, true, 1), true) + "<br>";
@@ -291,15 +356,16 @@ impl Structure for OreMine {
 
     fn frame_proc(
         &mut self,
-        _me: StructureId,
+        me: StructureId,
         state: &mut FactorishState,
         structures: &mut StructureDynIter,
     ) -> Result<FrameProcResult, ()> {
         let mut ret = FrameProcResult::None;
+        self.module_effect = module_effect(state, me, &self.module_inventory);
 
         if self.recipe.is_none() {
             for tile in self
-                .bounding_box()
+                .mining_area()
                 .iter_tiles()
                 .filter_map(|p| state.tile_at(&p))
             {
@@ -324,17 +390,23 @@ impl Structure for OreMine {
             //         this.removeItem("Coal Ore");
             //     }
             // }
-            if let Some(amount) = self.input_inventory.get_mut(&ItemType::CoalOre) {
-                if 0 < *amount && self.power == 0. {
-                    self.input_inventory.remove_item(&ItemType::CoalOre);
-                    self.power += COAL_POWER;
-                    self.max_power = self.max_power.max(self.power);
-                    ret = FrameProcResult::InventoryChanged(self.position);
+            if self.kind == MineKind::Burner {
+                if let Some(amount) = self.input_inventory.get_mut(&ItemType::CoalOre) {
+                    if 0 < *amount && self.power == 0. {
+                        self.input_inventory.remove_item(&ItemType::CoalOre);
+                        self.power += COAL_POWER;
+                        self.max_power = self.max_power.max(self.power);
+                        ret = FrameProcResult::InventoryChanged(self.position);
+                    }
                 }
             }
 
             let recipe_ore = recipe.output.iter().next().map(|(ore, _)| ore).ok_or(())?;
-            let bbox = self.bounding_box();
+            let bbox = self.mining_area();
+            let effect = self.module_effect;
+            let productivity = state.mining_productivity() + effect.productivity;
+            // A productivity bonus mines an ore without depleting the deposit
+            let deplete = self.bonus_progress < 1.;
 
             let remove_ore_from_tile = |state: &mut FactorishState, pos: &Position| {
                 let tile = state.tile_at_mut(&pos)?;
@@ -344,7 +416,9 @@ impl Structure for OreMine {
                 }
                 let val = &mut ore.1;
                 if 0 < *val {
-                    *val -= 1;
+                    if deplete {
+                        *val -= 1;
+                    }
                     let ret = *val;
                     if ret == 0 {
                         tile.ore = None;
@@ -365,7 +439,7 @@ impl Structure for OreMine {
             };
 
             // Proceed only if we have sufficient energy in the buffer.
-            let progress = get_powered_progress(self.power, self.progress, recipe);
+            let progress = get_module_progress(self.power, self.progress, recipe, &effect);
             if 1. <= self.progress + progress {
                 let output_position = self.output_pos();
                 let output_pixels = output_position.to_pixels();
@@ -383,6 +457,11 @@ impl Structure for OreMine {
                                         y: output_pixels.y as f64,
                                     })
                                     .map_err(|_| ())?;
+                                self.bonus_progress = if deplete {
+                                    self.bonus_progress + productivity
+                                } else {
+                                    self.bonus_progress - 1.
+                                };
                                 if val == 0 {
                                     self.recipe = None;
                                 }
@@ -414,6 +493,11 @@ impl Structure for OreMine {
                         if let Err(_code) = state.new_object(&output_position, *item.0) {
                             // console_log!("Failed to create object: {:?}", code);
                         } else if let Some(val) = remove_ore_from_tiles(state) {
+                            self.bonus_progress = if deplete {
+                                self.bonus_progress + productivity
+                            } else {
+                                self.bonus_progress - 1.
+                            };
                             if val == 0 {
                                 self.recipe = None;
                             }
@@ -430,12 +514,12 @@ impl Structure for OreMine {
                 }
             } else {
                 self.progress += progress;
-                self.power -= progress * recipe.power_cost;
+                self.power -= progress * get_module_power_cost(recipe, &effect);
                 self.digging = 0. < progress;
             }
 
             // Show smoke if there was some progress
-            if state.rng.next() < progress * 5. {
+            if self.kind == MineKind::Burner && state.rng.next() < progress * 5. {
                 state.temp_ents.push(TempEnt::new_float(
                     &mut state.rng,
                     (self.position.x as f64 + 1., self.position.y as f64 + 0.5),
@@ -468,7 +552,8 @@ impl Structure for OreMine {
 
     fn input(&mut self, item: &DropItem) -> Result<(), JsValue> {
         // Fuels are always welcome.
-        if item.type_ == ItemType::CoalOre
+        if self.kind == MineKind::Burner
+            && item.type_ == ItemType::CoalOre
             && self.input_inventory.count_item(&ItemType::CoalOre) < FUEL_CAPACITY
         {
             self.input_inventory.add_item(&ItemType::CoalOre);
@@ -478,7 +563,8 @@ impl Structure for OreMine {
     }
 
     fn can_input(&self, item_type: &ItemType) -> bool {
-        *item_type == ItemType::CoalOre
+        self.kind == MineKind::Burner
+            && *item_type == ItemType::CoalOre
             && self.input_inventory.count_item(&ItemType::CoalOre) < FUEL_CAPACITY
     }
 
@@ -510,7 +596,10 @@ impl Structure for OreMine {
         item_type: &ItemType,
         amount: isize,
     ) -> isize {
-        if inventory_type != InventoryType::Burner {
+        if inventory_type == InventoryType::Module {
+            return default_add_inventory(self, inventory_type, item_type, amount);
+        }
+        if inventory_type != InventoryType::Burner || self.kind != MineKind::Burner {
             return 0;
         }
         if amount < 0 {
@@ -531,33 +620,66 @@ impl Structure for OreMine {
     }
 
     fn burner_energy(&self) -> Option<(f64, f64)> {
-        Some((self.power, self.max_power))
+        match self.kind {
+            MineKind::Burner => Some((self.power, self.max_power)),
+            MineKind::Electric => None,
+        }
     }
 
     fn inventory(&self, invtype: InventoryType) -> Option<&Inventory> {
-        Some(match invtype {
-            InventoryType::Burner => &self.input_inventory,
+        Some(match (invtype, self.kind) {
+            (InventoryType::Burner, MineKind::Burner) => &self.input_inventory,
+            (InventoryType::Module, MineKind::Electric) => &self.module_inventory,
             _ => return None,
         })
     }
 
     fn inventory_mut(&mut self, invtype: InventoryType) -> Option<&mut Inventory> {
-        Some(match invtype {
-            InventoryType::Burner => &mut self.input_inventory,
+        Some(match (invtype, self.kind) {
+            (InventoryType::Burner, MineKind::Burner) => &mut self.input_inventory,
+            (InventoryType::Module, MineKind::Electric) => &mut self.module_inventory,
             _ => return None,
         })
     }
 
+    fn power_sink(&self) -> bool {
+        self.kind == MineKind::Electric
+    }
+
+    fn power_demand(&self) -> f64 {
+        match &self.recipe {
+            Some(recipe) if self.kind == MineKind::Electric && self.power < recipe.power_cost => {
+                self.max_power - self.power
+            }
+            _ => 0.,
+        }
+    }
+
+    fn power_inlet(&mut self, energy: f64) {
+        self.power += energy;
+    }
+
+    fn module_slots(&self) -> usize {
+        match self.kind {
+            MineKind::Burner => 0,
+            MineKind::Electric => 3,
+        }
+    }
+
     fn destroy_inventory(&mut self) -> Inventory {
+        let mut ret = std::mem::take(&mut self.input_inventory);
+        ret.merge(std::mem::take(&mut self.module_inventory));
         // Return the ingredients if it was in the middle of processing a recipe.
         if let Some(recipe) = self.recipe.take() {
             if 0. < self.progress {
-                let mut ret = std::mem::take(&mut self.input_inventory);
                 ret.merge(recipe.input);
-                return ret;
             }
         }
-        std::mem::take(&mut self.input_inventory)
+        ret
+    }
+
+    fn reset_for_blueprint(&mut self) {
+        self.power = 0.;
     }
 
     crate::serialize_impl!();
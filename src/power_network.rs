@@ -1,14 +1,41 @@
 use super::{
-    structure::{StructureDynIter, StructureId},
+    structure::{Structure, StructureDynIter, StructureId},
     PowerWire,
 };
 use std::collections::HashSet;
 
+/// The order in which the power sources in a network are drained.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub(crate) enum PowerPriority {
+    /// Sources without a buffer, whose energy is lost if not drained in the tick, e.g. solar panels
+    Renewable,
+    /// Generators burning fuel, e.g. steam engines
+    Fuel,
+    /// Energy storages, drained only when the generators cannot meet the demand
+    Storage,
+}
+
 #[derive(Debug)]
 pub(crate) struct PowerNetwork {
     pub wires: Vec<PowerWire>,
-    pub sources: HashSet<StructureId>,
+    /// Sorted by [`PowerPriority`]
+    pub sources: Vec<StructureId>,
     pub sinks: HashSet<StructureId>,
+    /// Total energy in kilojoules requested by the sinks in the last tick
+    pub demand: f64,
+    /// Total energy in kilojoules supplied to the sinks in the last tick
+    pub supply: f64,
+}
+
+impl PowerNetwork {
+    /// Ratio of the demand met by the supply, between 0 and 1
+    pub(crate) fn satisfaction(&self) -> f64 {
+        if 0. < self.demand {
+            self.supply / self.demand
+        } else {
+            1.
+        }
+    }
 }
 
 pub(crate) fn build_power_networks(
@@ -16,12 +43,19 @@ pub(crate) fn build_power_networks(
     power_wires: &[PowerWire],
 ) -> Vec<PowerNetwork> {
     let mut left_wires = power_wires.iter().collect::<HashSet<_>>();
+    let mut left_links = supply_links(structures);
     let mut ret = vec![];
 
     for (id, s) in structures.dyn_iter_id() {
         if !s.power_sink() && !s.power_source() {
             continue;
         }
+        // Skip structures already included in a network found earlier
+        if ret.iter().any(|network: &PowerNetwork| {
+            network.sinks.contains(&id) || network.sources.contains(&id)
+        }) {
+            continue;
+        }
         let mut expand_list = HashSet::<StructureId>::new();
         let mut wires = vec![];
         let mut sources = HashSet::new();
@@ -52,17 +86,242 @@ pub(crate) fn build_power_networks(
                     left_wires.remove(&wire);
                     wires.push(*wire);
                 }
+                while let Some(link) = left_links.iter().find(|w| w.0 == id || w.1 == id).copied() {
+                    next_expand.insert(if link.0 == id { link.1 } else { link.0 });
+                    left_links.remove(&link);
+                }
             }
             expand_list = next_expand;
         }
 
-        if !sources.is_empty() && !sinks.is_empty() {
+        let priority = |id: &StructureId| {
+            structures
+                .get(*id)
+                .map_or(PowerPriority::Fuel, |s| s.power_priority())
+        };
+        let has_storage = sources
+            .iter()
+            .any(|id| priority(id) == PowerPriority::Storage);
+        if !sources.is_empty() && (!sinks.is_empty() || has_storage) {
+            let mut sources: Vec<_> = sources.into_iter().collect();
+            sources.sort_by_key(priority);
             ret.push(PowerNetwork {
                 wires,
                 sources,
                 sinks,
+                demand: 0.,
+                supply: 0.,
             });
         }
     }
     ret
 }
+
+/// Connections between electric poles and the power sinks whose bounding box overlaps their
+/// supply area. They work like wires, but are not drawn.
+/// Poles are not connected to each other by supply areas, and generators need wires.
+fn supply_links(structures: &StructureDynIter) -> HashSet<PowerWire> {
+    let areas: Vec<_> = structures
+        .dyn_iter_id()
+        .filter_map(|(id, s)| Some((id, s.supply_area()?)))
+        .collect();
+    let mut ret = HashSet::new();
+    if areas.is_empty() {
+        return ret;
+    }
+    for (id, s) in structures.dyn_iter_id() {
+        if !s.power_sink() || s.supply_area().is_some() {
+            continue;
+        }
+        let bbox = s.bounding_box();
+        for (pole, area) in &areas {
+            if area.intersects(&bbox) {
+                ret.insert(PowerWire(*pole, id));
+            }
+        }
+    }
+    ret
+}
+
+/// Whether the structure is connected to the pole through its supply area
+pub(crate) fn supplied_by(structure: &dyn Structure, pole: &dyn Structure) -> bool {
+    structure.power_sink()
+        && structure.supply_area().is_none()
+        && pole
+            .supply_area()
+            .is_some_and(|area| area.intersects(&structure.bounding_box()))
+}
+
+/// Distribute the energy of the sources to the sinks in each network for this tick.
+///
+/// The total demand of the sinks is drained from the sources in the order of [`PowerPriority`],
+/// and every sink receives the same ratio of its demand, so that an underpowered network slows
+/// down all the consumers evenly instead of starving some of them.
+/// The energy left in the generators afterwards charges the energy storages.
+pub(crate) fn distribute_power(networks: &mut [PowerNetwork], structures: &mut StructureDynIter) {
+    for network in networks {
+        let demands: Vec<_> = network
+            .sinks
+            .iter()
+            .filter_map(|id| Some((*id, structures.get(*id)?.power_demand())))
+            .filter(|(_, demand)| 0. < *demand)
+            .collect();
+        let demand: f64 = demands.iter().map(|(_, demand)| demand).sum();
+        let mut supply = 0.;
+        for id in &network.sources {
+            if demand <= supply {
+                break;
+            }
+            if let Some(energy) = structures
+                .get_mut(*id)
+                .and_then(|source| source.power_outlet(demand - supply))
+            {
+                supply += energy;
+            }
+        }
+        network.demand = demand;
+        network.supply = supply;
+        let satisfaction = network.satisfaction();
+        for (id, demand) in demands {
+            if let Some(sink) = structures.get_mut(id) {
+                sink.power_inlet(demand * satisfaction);
+            }
+        }
+
+        for storage_id in &network.sources {
+            let Some(demand) = structures
+                .get(*storage_id)
+                .and_then(|s| s.power_charge_demand())
+            else {
+                continue;
+            };
+            let mut accumulated = 0.;
+            for id in &network.sources {
+                if let Some(source) = structures.get_mut(*id) {
+                    if source.power_priority() == PowerPriority::Storage {
+                        continue;
+                    }
+                    if let Some(energy) = source.power_outlet(demand - accumulated) {
+                        accumulated += energy;
+                    }
+                }
+            }
+            if let Some(storage) = structures.get_mut(*storage_id) {
+                storage.power_charge(accumulated);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_distribute_power() {
+    use super::{
+        accumulator::Accumulator, elect_pole::ElectPole, lamp::Lamp, solar_panel::SolarPanel,
+        structure::StructureEntry, FactorishState, Position,
+    };
+
+    let mut state = FactorishState::new_test();
+    let mut entry = |s: Box<dyn Structure>| {
+        state.structures.push(StructureEntry {
+            gen: 0,
+            dynamic: Some(s),
+        });
+        StructureId {
+            id: state.structures.len() as u32 - 1,
+            gen: 0,
+        }
+    };
+    let solar = |x| {
+        let mut solar = SolarPanel::new(&Position::new(x, 0));
+        solar
+            .frame_proc(
+                StructureId { id: 0, gen: 0 },
+                &mut FactorishState::new_test(),
+                &mut StructureDynIter::new_all(&mut []),
+            )
+            .unwrap();
+        Box::new(solar)
+    };
+
+    // A lamp demanding more than a solar panel supplies
+    let pole1 = entry(Box::new(ElectPole::new(&Position::new(0, 0))));
+    let solar1 = entry(solar(1));
+    let lamp = entry(Box::new(Lamp::new(&Position::new(2, 0))));
+    // A solar panel charging an accumulator without consumers
+    let pole2 = entry(Box::new(ElectPole::new(&Position::new(20, 0))));
+    let solar2 = entry(solar(21));
+    let accumulator = entry(Box::new(Accumulator::new(&Position::new(22, 0))));
+    let wires = [
+        PowerWire(pole1, solar1),
+        PowerWire(pole1, lamp),
+        PowerWire(pole2, solar2),
+        PowerWire(pole2, accumulator),
+    ];
+
+    let mut structures = StructureDynIter::new_all(&mut state.structures);
+    let mut networks = build_power_networks(&structures, &wires);
+    assert_eq!(networks.len(), 2);
+    distribute_power(&mut networks, &mut structures);
+
+    let (shortage, surplus) = if networks[0].sinks.contains(&lamp) {
+        (&networks[0], &networks[1])
+    } else {
+        (&networks[1], &networks[0])
+    };
+    assert_eq!(shortage.demand, 1.);
+    assert_eq!(shortage.supply, 0.1);
+    assert_eq!(shortage.satisfaction(), 0.1);
+    assert_eq!(structures.get(lamp).unwrap().power_demand(), 0.9);
+    assert_eq!(surplus.demand, 0.);
+    assert_eq!(surplus.satisfaction(), 1.);
+    assert_eq!(
+        structures
+            .get_mut(accumulator)
+            .unwrap()
+            .power_outlet(f64::INFINITY),
+        Some(0.1)
+    );
+    drop(structures);
+
+    state.power_networks = networks;
+    let pole = state.structures[pole1.id as usize]
+        .dynamic
+        .as_deref()
+        .unwrap();
+    assert_eq!(
+        pole.desc(&state),
+        "Demand: 60kW<br>Supply: 6kW<br>Satisfaction: 10%"
+    );
+}
+
+#[test]
+fn test_supply_links() {
+    use super::{
+        elect_pole::ElectPole, lamp::Lamp, solar_panel::SolarPanel, structure::StructureEntry,
+        Position,
+    };
+
+    let mut structures: Vec<_> = vec![
+        Box::new(ElectPole::new(&Position::new(0, 0))) as Box<dyn Structure>,
+        Box::new(Lamp::new(&Position::new(1, 0))),
+        Box::new(SolarPanel::new(&Position::new(-2, 0))),
+    ]
+    .into_iter()
+    .map(|s| StructureEntry {
+        gen: 0,
+        dynamic: Some(s),
+    })
+    .collect();
+    let id = |id| StructureId { id, gen: 0 };
+
+    // Only the consumer is connected by the supply area, the generator needs a wire
+    let structures = StructureDynIter::new_all(&mut structures);
+    assert_eq!(
+        supply_links(&structures),
+        vec![PowerWire(id(0), id(1))].into_iter().collect()
+    );
+    let networks = build_power_networks(&structures, &[PowerWire(id(0), id(2))]);
+    assert_eq!(networks.len(), 1);
+    assert!(networks[0].sinks.contains(&id(1)));
+    assert!(networks[0].sources.contains(&id(2)));
+}
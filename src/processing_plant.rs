@@ -0,0 +1,473 @@
+use super::{
+    drop_items::DropItem,
+    gl::{
+        draw_direction_arrow_gl, draw_electricity_alarm_gl,
+        utils::{enable_buffer, Flatten},
+    },
+    inventory::{filter_inventory, Inventory, InventoryTrait, InventoryType},
+    items::render_item_overlay_gl,
+    research::TechnologyTag,
+    structure::{
+        default_add_inventory, default_reset_for_blueprint, get_powered_progress, Size, Structure,
+        StructureDynIter, StructureId,
+    },
+    water_well::{FluidBox, FluidType},
+    FactorishState, FrameProcResult, ItemType, Position, Recipe, Rotation, TILE_SIZE,
+};
+use cgmath::{Matrix3, Matrix4, Vector2, Vector3};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+use web_sys::{CanvasRenderingContext2d, WebGlRenderingContext as GL};
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum PlantKind {
+    OilRefinery,
+    ChemicalPlant,
+}
+
+/// A processing plant crafts recipes with fluid ingredients or products.
+/// The input fluid comes from the back side and the output fluid goes out of the front side,
+/// in the direction of `rotation`.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct ProcessingPlant {
+    position: Position,
+    rotation: Rotation,
+    kind: PlantKind,
+    input_inventory: Inventory,
+    output_inventory: Inventory,
+    input_fluid_box: FluidBox,
+    output_fluid_box: FluidBox,
+    progress: Option<f64>,
+    power: f64,
+    max_power: f64,
+    recipe: Option<Recipe>,
+}
+
+impl ProcessingPlant {
+    pub(crate) fn new(position: &Position, rotation: Rotation, kind: PlantKind) -> Self {
+        Self {
+            position: *position,
+            rotation,
+            kind,
+            input_inventory: Inventory::new(),
+            output_inventory: Inventory::new(),
+            input_fluid_box: FluidBox::new(true, false),
+            output_fluid_box: FluidBox::new(false, true),
+            progress: None,
+            power: 0.,
+            max_power: 20.,
+            recipe: None,
+        }
+    }
+
+    fn oil_refinery_recipes() -> &'static [Recipe] {
+        static RECIPES: once_cell::sync::Lazy<Vec<Recipe>> = once_cell::sync::Lazy::new(|| {
+            [
+                (FluidType::PetroleumGas, 45.),
+                (FluidType::HeavyOil, 30.),
+                (FluidType::LightOil, 40.),
+            ]
+            .iter()
+            .map(|&output| {
+                Recipe::new_with_requires(
+                    hash_map!(),
+                    hash_map!(),
+                    50.,
+                    100.,
+                    hash_set!(TechnologyTag::OilProcessing),
+                )
+                .with_fluids(Some((FluidType::CrudeOil, 100.)), Some(output))
+            })
+            .collect()
+        });
+
+        &RECIPES[..]
+    }
+
+    fn chemical_plant_recipes() -> &'static [Recipe] {
+        static RECIPES: once_cell::sync::Lazy<Vec<Recipe>> = once_cell::sync::Lazy::new(|| {
+            vec![
+                Recipe::new_with_requires(
+                    hash_map!(ItemType::CoalOre => 1),
+                    hash_map!(ItemType::Plastic => 2),
+                    30.,
+                    50.,
+                    hash_set!(TechnologyTag::OilProcessing),
+                )
+                .with_fluids(Some((FluidType::PetroleumGas, 20.)), None),
+                Recipe::new_with_requires(
+                    hash_map!(),
+                    hash_map!(ItemType::Sulfur => 2),
+                    30.,
+                    50.,
+                    hash_set!(TechnologyTag::OilProcessing),
+                )
+                .with_fluids(Some((FluidType::PetroleumGas, 30.)), None),
+                Recipe::new_with_requires(
+                    hash_map!(),
+                    hash_map!(),
+                    30.,
+                    50.,
+                    hash_set!(TechnologyTag::OilProcessing),
+                )
+                .with_fluids(
+                    Some((FluidType::HeavyOil, 40.)),
+                    Some((FluidType::LightOil, 30.)),
+                ),
+                Recipe::new_with_requires(
+                    hash_map!(),
+                    hash_map!(),
+                    30.,
+                    50.,
+                    hash_set!(TechnologyTag::OilProcessing),
+                )
+                .with_fluids(
+                    Some((FluidType::LightOil, 30.)),
+                    Some((FluidType::PetroleumGas, 20.)),
+                ),
+            ]
+        });
+
+        &RECIPES[..]
+    }
+
+    /// Index of `connect_to` for the given side
+    fn side_index(rotation: Rotation) -> usize {
+        (rotation.angle_4() as usize + 2) % 4
+    }
+
+    /// Whether the ingredients, including the input fluid, are available to start a craft.
+    fn has_ingredients(&self, recipe: &Recipe) -> bool {
+        recipe
+            .input
+            .iter()
+            .all(|(item, count)| *count <= self.input_inventory.count_item(item))
+            && self.input_fluid_box.has_recipe_input(recipe)
+    }
+}
+
+impl Structure for ProcessingPlant {
+    fn name(&self) -> &str {
+        match self.kind {
+            PlantKind::OilRefinery => "Oil Refinery",
+            PlantKind::ChemicalPlant => "Chemical Plant",
+        }
+    }
+
+    fn position(&self) -> &Position {
+        &self.position
+    }
+
+    fn rotation(&self) -> Option<Rotation> {
+        Some(self.rotation)
+    }
+
+    fn size(&self) -> Size {
+        Size::new(3, 3)
+    }
+
+    fn draw(
+        &self,
+        state: &FactorishState,
+        context: &CanvasRenderingContext2d,
+        depth: i32,
+        _is_toolbar: bool,
+    ) -> Result<(), JsValue> {
+        if depth != 0 {
+            return Ok(());
+        }
+        let (x, y) = (
+            self.position.x as f64 * TILE_SIZE,
+            self.position.y as f64 * TILE_SIZE,
+        );
+        match state.image_assembler.as_ref() {
+            Some(img) => {
+                context.draw_image_with_image_bitmap_and_sw_and_sh_and_dx_and_dy_and_dw_and_dh(
+                    &img.bitmap,
+                    0.,
+                    0.,
+                    TILE_SIZE,
+                    TILE_SIZE,
+                    x,
+                    y,
+                    TILE_SIZE,
+                    TILE_SIZE,
+                )?;
+                Ok(())
+            }
+            None => Err(JsValue::from_str("assembler image not available")),
+        }
+    }
+
+    fn draw_gl(
+        &self,
+        state: &FactorishState,
+        gl: &GL,
+        depth: i32,
+        is_ghost: bool,
+    ) -> Result<(), JsValue> {
+        let (x, y) = (
+            self.position.x as f32 + state.viewport.x as f32,
+            self.position.y as f32 + state.viewport.y as f32,
+        );
+        match depth {
+            0 => {
+                let shader = state
+                    .assets
+                    .textured_shader
+                    .as_ref()
+                    .ok_or_else(|| js_str!("Shader not found"))?;
+                gl.use_program(Some(&shader.program));
+                gl.uniform1f(shader.alpha_loc.as_ref(), if is_ghost { 0.5 } else { 1. });
+                gl.active_texture(GL::TEXTURE0);
+                gl.bind_texture(GL::TEXTURE_2D, Some(&state.assets.tex_assembler));
+                let sx = if self.progress.is_some() && 0. < self.power {
+                    (((state.sim_time * 5.) as isize) % 4 + 1) as f32
+                } else {
+                    0.
+                };
+                gl.uniform_matrix3fv_with_f32_array(
+                    shader.tex_transform_loc.as_ref(),
+                    false,
+                    (Matrix3::from_nonuniform_scale(1. / 4., 1.)
+                        * Matrix3::from_translation(Vector2::new(sx, 0.)))
+                    .flatten(),
+                );
+                enable_buffer(gl, &state.assets.screen_buffer, 2, shader.vertex_position);
+                gl.uniform_matrix4fv_with_f32_array(
+                    shader.transform_loc.as_ref(),
+                    false,
+                    (state.get_world_transform()?
+                        * Matrix4::from_scale(2.)
+                        * Matrix4::from_translation(Vector3::new(x, y, 0.))
+                        * Matrix4::from_scale(3.))
+                    .flatten(),
+                );
+                gl.draw_arrays(GL::TRIANGLE_FAN, 0, 4);
+            }
+            2 => {
+                draw_direction_arrow_gl((x + 1., y + 1.), &self.rotation, state, gl)?;
+                if !is_ghost {
+                    if state.alt_mode {
+                        if let Some((output, _)) =
+                            self.recipe.as_ref().and_then(|r| r.output.iter().next())
+                        {
+                            let pos = self.bounding_box().center();
+                            render_item_overlay_gl(state, gl, output, &pos)?;
+                        }
+                    }
+                    if self.recipe.is_some() && self.power == 0. {
+                        draw_electricity_alarm_gl((x, y), state, gl)?;
+                    }
+                }
+            }
+            _ => (),
+        }
+        Ok(())
+    }
+
+    fn desc(&self, _state: &FactorishState) -> String {
+        format!(
+            "{}<br>Power: {:.1}kJ<br>Input fluid: {}<br>Output fluid: {}<br>Input Items: <br>{}Output Items: <br>{}",
+            if self.recipe.is_some() {
+                format!("Progress: {:.0}%", self.progress.unwrap_or(0.) * 100.)
+            } else {
+                String::from("No recipe")
+            },
+            self.power,
+            self.input_fluid_box.desc(),
+            self.output_fluid_box.desc(),
+            self.input_inventory.describe(),
+            self.output_inventory.describe()
+        )
+    }
+
+    fn frame_proc(
+        &mut self,
+        _me: StructureId,
+        _state: &mut FactorishState,
+        structures: &mut StructureDynIter,
+    ) -> Result<FrameProcResult, ()> {
+        // Only the front side gets the output fluid
+        let mut front = [false; 4];
+        front[Self::side_index(self.rotation)] = true;
+        self.output_fluid_box.simulate_sides(structures, front);
+
+        let recipe = if let Some(recipe) = &self.recipe {
+            recipe
+        } else {
+            return Ok(FrameProcResult::None);
+        };
+        let mut ret = FrameProcResult::None;
+
+        if self.progress.is_none() && self.has_ingredients(recipe) {
+            for (item, count) in &recipe.input {
+                self.input_inventory.remove_items(item, *count);
+            }
+            self.input_fluid_box.amount -= recipe.input_fluid_amount;
+            self.progress = Some(0.);
+            ret = FrameProcResult::InventoryChanged(self.position);
+        }
+
+        if let Some(prev_progress) = self.progress {
+            let progress = get_powered_progress(self.power, prev_progress, recipe);
+            if 1. <= prev_progress + progress {
+                // Hold the finished product until the output fluid box has room for it
+                if !self.output_fluid_box.has_recipe_output_space(recipe) {
+                    return Ok(ret);
+                }
+                self.progress = None;
+                for (item, count) in &recipe.output {
+                    self.output_inventory.add_items(item, *count);
+                }
+                if let Some(fluid) = recipe.output_fluid {
+                    self.output_fluid_box.type_ = Some(fluid);
+                    self.output_fluid_box.amount += recipe.output_fluid_amount;
+                }
+                return Ok(FrameProcResult::InventoryChanged(self.position));
+            } else {
+                self.progress = Some(prev_progress + progress);
+                self.power -= progress * recipe.power_cost;
+            }
+        }
+        Ok(ret)
+    }
+
+    fn input(&mut self, o: &DropItem) -> Result<(), JsValue> {
+        if self.recipe.is_some() {
+            if 0 < default_add_inventory(self, InventoryType::Input, &o.type_, 1) {
+                return Ok(());
+            } else {
+                return Err(JsValue::from_str("Item is not part of recipe"));
+            }
+        }
+        Err(JsValue::from_str("Recipe is not initialized"))
+    }
+
+    fn can_output(&self, _structures: &StructureDynIter) -> Inventory {
+        self.output_inventory.clone()
+    }
+
+    fn output(&mut self, _state: &mut FactorishState, item_type: &ItemType) -> Result<(), ()> {
+        if self.output_inventory.remove_item(item_type) {
+            Ok(())
+        } else {
+            Err(())
+        }
+    }
+
+    fn inventory(&self, invtype: InventoryType) -> Option<&Inventory> {
+        Some(match invtype {
+            InventoryType::Input => &self.input_inventory,
+            InventoryType::Output => &self.output_inventory,
+            _ => return None,
+        })
+    }
+
+    fn inventory_mut(&mut self, invtype: InventoryType) -> Option<&mut Inventory> {
+        Some(match invtype {
+            InventoryType::Input => &mut self.input_inventory,
+            InventoryType::Output => &mut self.output_inventory,
+            _ => return None,
+        })
+    }
+
+    fn destroy_inventory(&mut self) -> Inventory {
+        let mut ret = std::mem::take(&mut self.input_inventory);
+        ret.merge(std::mem::take(&mut self.output_inventory));
+        if let Some(mut recipe) = self.recipe.take() {
+            if self.progress.is_some() {
+                ret.merge(std::mem::take(&mut recipe.input));
+            }
+        }
+        ret
+    }
+
+    fn get_recipes(&self) -> std::borrow::Cow<'_, [Recipe]> {
+        std::borrow::Cow::from(match self.kind {
+            PlantKind::OilRefinery => Self::oil_refinery_recipes(),
+            PlantKind::ChemicalPlant => Self::chemical_plant_recipes(),
+        })
+    }
+
+    fn select_recipe(
+        &mut self,
+        index: usize,
+        player_inventory: &mut Inventory,
+    ) -> Result<bool, JsValue> {
+        let recipe = self
+            .get_recipes()
+            .get(index)
+            .ok_or_else(|| js_str!("recipes index out of bound {:?}", index))?
+            .clone();
+
+        self.input_inventory = filter_inventory(
+            std::mem::take(&mut self.input_inventory),
+            |item| recipe.input.contains_key(item),
+            player_inventory,
+        );
+
+        self.output_inventory = filter_inventory(
+            std::mem::take(&mut self.output_inventory),
+            |item| recipe.output.contains_key(item),
+            player_inventory,
+        );
+
+        // Fluids of the previous recipe are discarded
+        self.input_fluid_box.set_recipe_fluid(recipe.input_fluid);
+        self.output_fluid_box.set_recipe_fluid(recipe.output_fluid);
+
+        self.recipe = Some(recipe);
+        Ok(true)
+    }
+
+    fn get_selected_recipe(&self) -> Option<&Recipe> {
+        self.recipe.as_ref()
+    }
+
+    fn get_progress(&self) -> Option<f64> {
+        self.progress
+    }
+
+    fn set_rotation(&mut self, rotation: &Rotation) -> Result<(), ()> {
+        self.rotation = *rotation;
+        Ok(())
+    }
+
+    fn power_sink(&self) -> bool {
+        true
+    }
+
+    fn power_demand(&self) -> f64 {
+        match &self.recipe {
+            Some(recipe) if self.power < recipe.power_cost => self.max_power - self.power,
+            _ => 0.,
+        }
+    }
+
+    fn power_inlet(&mut self, energy: f64) {
+        self.power += energy;
+    }
+
+    fn fluid_connections(&self) -> [bool; 4] {
+        let mut ret = [false; 4];
+        ret[self.rotation.angle_4() as usize] = true;
+        ret[self.rotation.next().next().angle_4() as usize] = true;
+        ret
+    }
+
+    fn fluid_box(&self) -> Option<Vec<&FluidBox>> {
+        Some(vec![&self.input_fluid_box, &self.output_fluid_box])
+    }
+
+    fn fluid_box_mut(&mut self) -> Option<Vec<&mut FluidBox>> {
+        Some(vec![&mut self.input_fluid_box, &mut self.output_fluid_box])
+    }
+
+    fn reset_for_blueprint(&mut self) {
+        default_reset_for_blueprint(self);
+        self.power = 0.;
+    }
+
+    crate::serialize_impl!();
+}
@@ -0,0 +1,234 @@
+use super::{
+    draw_direction_arrow,
+    gl::{
+        draw_direction_arrow_gl, draw_electricity_alarm_gl,
+        utils::{enable_buffer, Flatten},
+    },
+    structure::{default_reset_for_blueprint, RotateErr, Structure, StructureDynIter, StructureId},
+    water_well::FluidBox,
+    FactorishState, FrameProcResult, Position, Rotation, TILE_SIZE,
+};
+use cgmath::{Matrix3, Matrix4, Vector3};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+use web_sys::{CanvasRenderingContext2d, WebGlRenderingContext as GL};
+
+/// Amount of fluid moved in a tick at full power
+const PUMP_RATE: f64 = 2.;
+/// Energy in kilojoules consumed in a tick at full rate
+const PUMP_POWER: f64 = 0.5;
+const PUMP_MAX_POWER: f64 = 10.;
+
+/// A pump moves fluid from the structure behind it to the front in the direction of `rotation`,
+/// at a fixed rate regardless of the pressure. Rotating a placed pump turns it on or off.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct Pump {
+    position: Position,
+    rotation: Rotation,
+    output_fluid_box: FluidBox,
+    power: f64,
+    enabled: bool,
+}
+
+impl Pump {
+    pub(crate) fn new(position: &Position, rotation: Rotation) -> Self {
+        Self {
+            position: *position,
+            rotation,
+            output_fluid_box: FluidBox::new(false, true),
+            power: 0.,
+            enabled: true,
+        }
+    }
+
+    /// Index of `connect_to` for the given side
+    fn side_index(rotation: Rotation) -> usize {
+        (rotation.angle_4() as usize + 2) % 4
+    }
+
+    /// Pull up to `amount` of fluid from the fluid boxes of the structure behind.
+    fn pull(&mut self, source: StructureId, amount: f64, structures: &mut StructureDynIter) -> f64 {
+        let output = &mut self.output_fluid_box;
+        let Some(fluid_boxes) = structures.get_mut(source).and_then(|s| s.fluid_box_mut()) else {
+            return 0.;
+        };
+        for fluid_box in fluid_boxes {
+            if !fluid_box.output_enable
+                || fluid_box.amount <= 0.
+                || 0. < output.amount && output.type_ != fluid_box.type_
+            {
+                continue;
+            }
+            let moved = amount.min(fluid_box.amount);
+            fluid_box.amount -= moved;
+            output.amount += moved;
+            output.type_ = fluid_box.type_;
+            return moved;
+        }
+        0.
+    }
+}
+
+impl Structure for Pump {
+    fn name(&self) -> &str {
+        "Pump"
+    }
+
+    fn position(&self) -> &Position {
+        &self.position
+    }
+
+    fn rotation(&self) -> Option<Rotation> {
+        Some(self.rotation)
+    }
+
+    fn draw(
+        &self,
+        state: &FactorishState,
+        context: &CanvasRenderingContext2d,
+        depth: i32,
+        _is_toolbar: bool,
+    ) -> Result<(), JsValue> {
+        let (x, y) = (
+            self.position.x as f64 * TILE_SIZE,
+            self.position.y as f64 * TILE_SIZE,
+        );
+        match depth {
+            0 => match state.image_offshore_pump.as_ref() {
+                Some(img) => {
+                    context.draw_image_with_image_bitmap(&img.bitmap, x, y)?;
+                }
+                None => return Err(JsValue::from_str("offshore pump image not available")),
+            },
+            2 => draw_direction_arrow((x, y), &self.rotation, state, context)?,
+            _ => (),
+        }
+        Ok(())
+    }
+
+    fn draw_gl(
+        &self,
+        state: &FactorishState,
+        gl: &GL,
+        depth: i32,
+        is_ghost: bool,
+    ) -> Result<(), JsValue> {
+        let (x, y) = (
+            self.position.x as f32 + state.viewport.x as f32,
+            self.position.y as f32 + state.viewport.y as f32,
+        );
+        match depth {
+            0 => {
+                let shader = state
+                    .assets
+                    .textured_shader
+                    .as_ref()
+                    .ok_or_else(|| js_str!("Shader not found"))?;
+                gl.use_program(Some(&shader.program));
+                gl.uniform1f(shader.alpha_loc.as_ref(), if is_ghost { 0.5 } else { 1. });
+                gl.active_texture(GL::TEXTURE0);
+                gl.bind_texture(GL::TEXTURE_2D, Some(&state.assets.tex_offshore_pump));
+                gl.uniform_matrix3fv_with_f32_array(
+                    shader.tex_transform_loc.as_ref(),
+                    false,
+                    Matrix3::from_scale(1.).flatten(),
+                );
+                enable_buffer(gl, &state.assets.screen_buffer, 2, shader.vertex_position);
+                gl.uniform_matrix4fv_with_f32_array(
+                    shader.transform_loc.as_ref(),
+                    false,
+                    (state.get_world_transform()?
+                        * Matrix4::from_scale(2.)
+                        * Matrix4::from_translation(Vector3::new(x, y, 0.)))
+                    .flatten(),
+                );
+                gl.draw_arrays(GL::TRIANGLE_FAN, 0, 4);
+            }
+            2 => {
+                draw_direction_arrow_gl((x, y), &self.rotation, state, gl)?;
+                if !is_ghost && self.enabled && self.power == 0. {
+                    draw_electricity_alarm_gl((x, y), state, gl)?;
+                }
+            }
+            _ => (),
+        }
+        Ok(())
+    }
+
+    fn desc(&self, _state: &FactorishState) -> String {
+        format!(
+            "{}<br>Power: {:.1}kJ<br>{}",
+            if self.enabled { "On" } else { "Off" },
+            self.power,
+            self.output_fluid_box.desc()
+        )
+    }
+
+    fn frame_proc(
+        &mut self,
+        _me: StructureId,
+        _state: &mut FactorishState,
+        structures: &mut StructureDynIter,
+    ) -> Result<FrameProcResult, ()> {
+        // Detach the structure behind while simulating, so that the fluid only flows forward
+        let back = Self::side_index(self.rotation.next().next());
+        let source = self.output_fluid_box.connect_to[back].take();
+        if let Some(source) = source.filter(|_| self.enabled) {
+            let rate = PUMP_RATE * (self.power / PUMP_POWER).min(1.);
+            let space = self.output_fluid_box.max_amount - self.output_fluid_box.amount;
+            let moved = self.pull(source, rate.min(space), structures);
+            self.power -= PUMP_POWER * moved / PUMP_RATE;
+        }
+        self.output_fluid_box.simulate(structures);
+        self.output_fluid_box.connect_to[back] = source;
+        Ok(FrameProcResult::None)
+    }
+
+    fn rotate(
+        &mut self,
+        _state: &mut FactorishState,
+        _others: &StructureDynIter,
+    ) -> Result<(), RotateErr> {
+        self.enabled = !self.enabled;
+        Ok(())
+    }
+
+    fn set_rotation(&mut self, rotation: &Rotation) -> Result<(), ()> {
+        self.rotation = *rotation;
+        Ok(())
+    }
+
+    fn power_sink(&self) -> bool {
+        true
+    }
+
+    fn power_demand(&self) -> f64 {
+        (PUMP_MAX_POWER - self.power).max(0.)
+    }
+
+    fn power_inlet(&mut self, energy: f64) {
+        self.power += energy;
+    }
+
+    fn fluid_connections(&self) -> [bool; 4] {
+        let mut ret = [false; 4];
+        ret[self.rotation.angle_4() as usize] = true;
+        ret[self.rotation.next().next().angle_4() as usize] = true;
+        ret
+    }
+
+    fn fluid_box(&self) -> Option<Vec<&FluidBox>> {
+        Some(vec![&self.output_fluid_box])
+    }
+
+    fn fluid_box_mut(&mut self) -> Option<Vec<&mut FluidBox>> {
+        Some(vec![&mut self.output_fluid_box])
+    }
+
+    fn reset_for_blueprint(&mut self) {
+        default_reset_for_blueprint(self);
+        self.power = 0.;
+    }
+
+    crate::serialize_impl!();
+}
@@ -0,0 +1,212 @@
+use super::{
+    gl::{
+        draw_electricity_alarm_gl,
+        utils::{enable_buffer, Flatten},
+    },
+    structure::{default_reset_for_blueprint, Size, Structure, StructureDynIter, StructureId},
+    water_well::{FluidBox, FluidType},
+    FactorishState, FrameProcResult, Position, TILE_SIZE,
+};
+use cgmath::{Matrix3, Matrix4, Vector2, Vector3};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+use web_sys::{CanvasRenderingContext2d, WebGlRenderingContext as GL};
+
+/// Crude oil extracted in a tick from a deposit with 100% yield
+const PUMPJACK_RATE: f64 = 0.5;
+/// Energy in kilojoules consumed in a tick while pumping
+const PUMPJACK_POWER: f64 = 1.;
+const PUMPJACK_MAX_POWER: f64 = 20.;
+
+/// A pumpjack extracts crude oil from the deposits under its footprint into its fluid box.
+/// Oil deposits are not depleted, but the rate depends on the total yield.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct Pumpjack {
+    position: Position,
+    output_fluid_box: FluidBox,
+    power: f64,
+    #[serde(skip)]
+    pumping: bool,
+}
+
+impl Pumpjack {
+    pub(crate) fn new(position: &Position) -> Self {
+        Self {
+            position: *position,
+            output_fluid_box: FluidBox::new(false, true).set_type(&FluidType::CrudeOil),
+            power: 0.,
+            pumping: false,
+        }
+    }
+
+    /// Total yield of the oil deposits under the pumpjack in percent
+    fn oil_yield(&self, state: &FactorishState) -> u32 {
+        self.bounding_box()
+            .iter_tiles()
+            .filter_map(|p| state.tile_at(&p))
+            .map(|cell| cell.oil)
+            .sum()
+    }
+}
+
+impl Structure for Pumpjack {
+    fn name(&self) -> &str {
+        "Pumpjack"
+    }
+
+    fn position(&self) -> &Position {
+        &self.position
+    }
+
+    fn size(&self) -> Size {
+        Size::new(2, 2)
+    }
+
+    fn draw(
+        &self,
+        state: &FactorishState,
+        context: &CanvasRenderingContext2d,
+        depth: i32,
+        is_toolbar: bool,
+    ) -> Result<(), JsValue> {
+        if depth != 0 {
+            return Ok(());
+        }
+        let (x, y) = (
+            self.position.x as f64 * TILE_SIZE,
+            self.position.y as f64 * TILE_SIZE,
+        );
+        let size = if is_toolbar {
+            TILE_SIZE
+        } else {
+            TILE_SIZE * 2.
+        };
+        match state.image_mine.as_ref() {
+            Some(img) => {
+                context.draw_image_with_image_bitmap_and_sw_and_sh_and_dx_and_dy_and_dw_and_dh(
+                    &img.bitmap,
+                    0.,
+                    0.,
+                    img.bitmap.width() as f64 / 3.,
+                    img.bitmap.height() as f64,
+                    x,
+                    y,
+                    size,
+                    size,
+                )?;
+                Ok(())
+            }
+            None => Err(JsValue::from_str("mine image not available")),
+        }
+    }
+
+    fn draw_gl(
+        &self,
+        state: &FactorishState,
+        gl: &GL,
+        depth: i32,
+        is_ghost: bool,
+    ) -> Result<(), JsValue> {
+        let (x, y) = (
+            self.position.x as f32 + state.viewport.x as f32,
+            self.position.y as f32 + state.viewport.y as f32,
+        );
+        match depth {
+            0 => {
+                let shader = state
+                    .assets
+                    .textured_shader
+                    .as_ref()
+                    .ok_or_else(|| js_str!("Shader not found"))?;
+                gl.use_program(Some(&shader.program));
+                gl.uniform1f(shader.alpha_loc.as_ref(), if is_ghost { 0.5 } else { 1. });
+                gl.active_texture(GL::TEXTURE0);
+                gl.bind_texture(GL::TEXTURE_2D, Some(&state.assets.tex_ore_mine));
+                let sx = if self.pumping {
+                    (((state.sim_time * 5.) as isize) % 2 + 1) as f32 / 3.
+                } else {
+                    0.
+                };
+                gl.uniform_matrix3fv_with_f32_array(
+                    shader.tex_transform_loc.as_ref(),
+                    false,
+                    (Matrix3::from_translation(Vector2::new(sx, 0.))
+                        * Matrix3::from_nonuniform_scale(1. / 3., 1.))
+                    .flatten(),
+                );
+                enable_buffer(gl, &state.assets.screen_buffer, 2, shader.vertex_position);
+                gl.uniform_matrix4fv_with_f32_array(
+                    shader.transform_loc.as_ref(),
+                    false,
+                    (state.get_world_transform()?
+                        * Matrix4::from_scale(2.)
+                        * Matrix4::from_translation(Vector3::new(x, y, 0.))
+                        * Matrix4::from_scale(2.))
+                    .flatten(),
+                );
+                gl.draw_arrays(GL::TRIANGLE_FAN, 0, 4);
+            }
+            2 if !is_ghost && self.power == 0. => {
+                draw_electricity_alarm_gl((x, y), state, gl)?;
+            }
+            _ => (),
+        }
+        Ok(())
+    }
+
+    fn desc(&self, state: &FactorishState) -> String {
+        format!(
+            "Oil yield: {}%<br>Power: {:.1}kJ<br>{}",
+            self.oil_yield(state),
+            self.power,
+            self.output_fluid_box.desc()
+        )
+    }
+
+    fn frame_proc(
+        &mut self,
+        _me: StructureId,
+        state: &mut FactorishState,
+        structures: &mut StructureDynIter,
+    ) -> Result<FrameProcResult, ()> {
+        let oil_yield = self.oil_yield(state) as f64;
+        let fluid_box = &mut self.output_fluid_box;
+        let space = fluid_box.max_amount - fluid_box.amount;
+        let powered = (self.power / PUMPJACK_POWER).min(1.);
+        let amount = (PUMPJACK_RATE * oil_yield / 100. * powered).min(space);
+        self.pumping = 0. < amount;
+        if self.pumping {
+            fluid_box.amount += amount;
+            self.power -= PUMPJACK_POWER * powered;
+        }
+        self.output_fluid_box.simulate(structures);
+        Ok(FrameProcResult::None)
+    }
+
+    fn power_sink(&self) -> bool {
+        true
+    }
+
+    fn power_demand(&self) -> f64 {
+        (PUMPJACK_MAX_POWER - self.power).max(0.)
+    }
+
+    fn power_inlet(&mut self, energy: f64) {
+        self.power += energy;
+    }
+
+    fn fluid_box(&self) -> Option<Vec<&FluidBox>> {
+        Some(vec![&self.output_fluid_box])
+    }
+
+    fn fluid_box_mut(&mut self) -> Option<Vec<&mut FluidBox>> {
+        Some(vec![&mut self.output_fluid_box])
+    }
+
+    fn reset_for_blueprint(&mut self) {
+        default_reset_for_blueprint(self);
+        self.power = 0.;
+    }
+
+    crate::serialize_impl!();
+}
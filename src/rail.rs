@@ -0,0 +1,468 @@
+use super::{
+    gl::draw_flat_rect_gl,
+    inventory::{storage_capacity, InventoryType},
+    structure::{RotateErr, Structure, StructureDynIter},
+    DropItem, FactorishState, Inventory, ItemType, Position, Rotation, TILE_SIZE,
+};
+use rotate_enum::RotateEnum;
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+use web_sys::{CanvasRenderingContext2d, WebGlRenderingContext as GL};
+
+const TIE_COLOR: [f32; 4] = [0.45, 0.3, 0.15, 1.];
+const TRACK_COLOR: [f32; 4] = [0.7, 0.7, 0.75, 1.];
+
+/// A band from the center of a tile to the `side`, placed at `offset` across the band
+/// with the `width`. Returned in tile-local coordinates as (x, y, w, h).
+fn half_band(side: Rotation, offset: f32, width: f32) -> (f32, f32, f32, f32) {
+    let across = offset - width / 2.;
+    let length = 0.5 + width / 2.;
+    match side {
+        Rotation::Left => (0., across, length, width),
+        Rotation::Right => (1. - length, across, length, width),
+        Rotation::Top => (across, 0., width, length),
+        Rotation::Bottom => (across, 1. - length, width, length),
+    }
+}
+
+fn draw_rail_gl(
+    (x, y): (f32, f32),
+    connections: &[Rotation; 2],
+    state: &FactorishState,
+    gl: &GL,
+    is_ghost: bool,
+) -> Result<(), JsValue> {
+    let alpha = if is_ghost { 0.5 } else { 1. };
+    let with_alpha = |color: &[f32; 4]| [color[0], color[1], color[2], alpha];
+    for side in connections {
+        let (bx, by, bw, bh) = half_band(*side, 0.5, 0.8);
+        draw_flat_rect_gl(state, gl, (x + bx, y + by, bw, bh), &with_alpha(&TIE_COLOR))?;
+    }
+    for side in connections {
+        for offset in [0.25, 0.75] {
+            let (bx, by, bw, bh) = half_band(*side, offset, 0.08);
+            draw_flat_rect_gl(
+                state,
+                gl,
+                (x + bx, y + by, bw, bh),
+                &with_alpha(&TRACK_COLOR),
+            )?;
+        }
+    }
+    Ok(())
+}
+
+fn draw_rail(
+    (x, y): (f64, f64),
+    connections: &[Rotation; 2],
+    context: &CanvasRenderingContext2d,
+) -> Result<(), JsValue> {
+    for (offset, width, color) in [
+        (0.5, 0.8, "#734d26"),
+        (0.25, 0.08, "#b3b3bf"),
+        (0.75, 0.08, "#b3b3bf"),
+    ] {
+        context.set_fill_style(&js_str!(color));
+        for side in connections {
+            let (bx, by, bw, bh) = half_band(*side, offset, width);
+            context.fill_rect(
+                x + bx as f64 * TILE_SIZE,
+                y + by as f64 * TILE_SIZE,
+                bw as f64 * TILE_SIZE,
+                bh as f64 * TILE_SIZE,
+            );
+        }
+    }
+    Ok(())
+}
+
+fn straight_connections(rotation: Rotation) -> [Rotation; 2] {
+    [rotation, rotation.next().next()]
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum RailShape {
+    Straight,
+    /// Connects the side of the rotation and the next side clockwise
+    Curve,
+}
+
+/// A rail piece that trains run on. A straight rail connects the opposite sides of the tile
+/// and a curved rail connects two adjacent sides, so tracks never branch.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct Rail {
+    position: Position,
+    rotation: Rotation,
+    shape: RailShape,
+}
+
+impl Rail {
+    pub(crate) fn new(position: &Position, rotation: Rotation, shape: RailShape) -> Self {
+        Self {
+            position: *position,
+            rotation,
+            shape,
+        }
+    }
+}
+
+impl Structure for Rail {
+    fn name(&self) -> &str {
+        match self.shape {
+            RailShape::Straight => "Rail",
+            RailShape::Curve => "Curved Rail",
+        }
+    }
+
+    fn position(&self) -> &Position {
+        &self.position
+    }
+
+    fn walkable(&self) -> bool {
+        true
+    }
+
+    fn rotation(&self) -> Option<Rotation> {
+        Some(self.rotation)
+    }
+
+    fn draw(
+        &self,
+        _state: &FactorishState,
+        context: &CanvasRenderingContext2d,
+        depth: i32,
+        _is_toolbar: bool,
+    ) -> Result<(), JsValue> {
+        if depth != 0 {
+            return Ok(());
+        }
+        let (x, y) = (
+            self.position.x as f64 * TILE_SIZE,
+            self.position.y as f64 * TILE_SIZE,
+        );
+        draw_rail((x, y), &self.rail_connections().unwrap(), context)
+    }
+
+    fn draw_gl(
+        &self,
+        state: &FactorishState,
+        gl: &GL,
+        depth: i32,
+        is_ghost: bool,
+    ) -> Result<(), JsValue> {
+        if depth != 0 {
+            return Ok(());
+        }
+        let (x, y) = (
+            self.position.x as f32 + state.viewport.x as f32,
+            self.position.y as f32 + state.viewport.y as f32,
+        );
+        draw_rail_gl(
+            (x, y),
+            &self.rail_connections().unwrap(),
+            state,
+            gl,
+            is_ghost,
+        )
+    }
+
+    fn desc(&self, state: &FactorishState) -> String {
+        state
+            .train_at(&self.position)
+            .map(|train| train.desc())
+            .unwrap_or_default()
+    }
+
+    fn rotate(
+        &mut self,
+        _state: &mut FactorishState,
+        _others: &StructureDynIter,
+    ) -> Result<(), RotateErr> {
+        self.rotation = self.rotation.next();
+        Ok(())
+    }
+
+    fn set_rotation(&mut self, rotation: &Rotation) -> Result<(), ()> {
+        self.rotation = *rotation;
+        Ok(())
+    }
+
+    fn rail_connections(&self) -> Option<[Rotation; 2]> {
+        Some(match self.shape {
+            RailShape::Straight => straight_connections(self.rotation),
+            RailShape::Curve => [self.rotation, self.rotation.next()],
+        })
+    }
+
+    crate::serialize_impl!();
+}
+
+/// A rail signal divides rails into blocks. A train does not pass a signal while
+/// another train occupies the block beyond it.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct RailSignal {
+    position: Position,
+    rotation: Rotation,
+}
+
+impl RailSignal {
+    pub(crate) fn new(position: &Position, rotation: Rotation) -> Self {
+        Self {
+            position: *position,
+            rotation,
+        }
+    }
+}
+
+impl Structure for RailSignal {
+    fn name(&self) -> &str {
+        "Rail Signal"
+    }
+
+    fn position(&self) -> &Position {
+        &self.position
+    }
+
+    fn rotation(&self) -> Option<Rotation> {
+        Some(self.rotation)
+    }
+
+    fn draw(
+        &self,
+        _state: &FactorishState,
+        context: &CanvasRenderingContext2d,
+        depth: i32,
+        _is_toolbar: bool,
+    ) -> Result<(), JsValue> {
+        if depth != 0 {
+            return Ok(());
+        }
+        let (x, y) = (
+            self.position.x as f64 * TILE_SIZE,
+            self.position.y as f64 * TILE_SIZE,
+        );
+        draw_rail((x, y), &straight_connections(self.rotation), context)?;
+        context.set_fill_style(&js_str!("#ffbf00"));
+        context.fill_rect(x, y, TILE_SIZE * 0.3, TILE_SIZE * 0.3);
+        Ok(())
+    }
+
+    fn draw_gl(
+        &self,
+        state: &FactorishState,
+        gl: &GL,
+        depth: i32,
+        is_ghost: bool,
+    ) -> Result<(), JsValue> {
+        if depth != 0 {
+            return Ok(());
+        }
+        let (x, y) = (
+            self.position.x as f32 + state.viewport.x as f32,
+            self.position.y as f32 + state.viewport.y as f32,
+        );
+        draw_rail_gl(
+            (x, y),
+            &straight_connections(self.rotation),
+            state,
+            gl,
+            is_ghost,
+        )?;
+        draw_flat_rect_gl(state, gl, (x, y, 0.3, 0.3), &[1., 0.75, 0., 1.])
+    }
+
+    fn desc(&self, state: &FactorishState) -> String {
+        state
+            .train_at(&self.position)
+            .map(|train| train.desc())
+            .unwrap_or_default()
+    }
+
+    fn set_rotation(&mut self, rotation: &Rotation) -> Result<(), ()> {
+        self.rotation = *rotation;
+        Ok(())
+    }
+
+    fn rail_connections(&self) -> Option<[Rotation; 2]> {
+        Some(straight_connections(self.rotation))
+    }
+
+    fn rail_signal(&self) -> bool {
+        true
+    }
+
+    crate::serialize_impl!();
+}
+
+/// `Load` moves items from the buffer of the stop into the cargo wagons of a waiting train,
+/// and `Unload` moves them the other way.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, RotateEnum)]
+pub(crate) enum TrainStopMode {
+    Load,
+    Unload,
+}
+
+/// A train stop is a straight rail with a name, which train schedules refer to.
+/// It has a buffer inventory that inserters fill or drain like a chest.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct TrainStop {
+    position: Position,
+    rotation: Rotation,
+    name: String,
+    mode: TrainStopMode,
+    inventory: Inventory,
+}
+
+impl TrainStop {
+    pub(crate) fn new(position: &Position, rotation: Rotation) -> Self {
+        Self {
+            position: *position,
+            rotation,
+            name: format!("Stop {},{}", position.x, position.y),
+            mode: TrainStopMode::Load,
+            inventory: Inventory::new(),
+        }
+    }
+}
+
+impl Structure for TrainStop {
+    fn name(&self) -> &str {
+        "Train Stop"
+    }
+
+    fn position(&self) -> &Position {
+        &self.position
+    }
+
+    fn rotation(&self) -> Option<Rotation> {
+        Some(self.rotation)
+    }
+
+    fn draw(
+        &self,
+        _state: &FactorishState,
+        context: &CanvasRenderingContext2d,
+        depth: i32,
+        _is_toolbar: bool,
+    ) -> Result<(), JsValue> {
+        if depth != 0 {
+            return Ok(());
+        }
+        let (x, y) = (
+            self.position.x as f64 * TILE_SIZE,
+            self.position.y as f64 * TILE_SIZE,
+        );
+        draw_rail((x, y), &straight_connections(self.rotation), context)?;
+        context.set_fill_style(&js_str!("#3f7fff"));
+        context.fill_rect(x, y, TILE_SIZE * 0.3, TILE_SIZE * 0.3);
+        Ok(())
+    }
+
+    fn draw_gl(
+        &self,
+        state: &FactorishState,
+        gl: &GL,
+        depth: i32,
+        is_ghost: bool,
+    ) -> Result<(), JsValue> {
+        if depth != 0 {
+            return Ok(());
+        }
+        let (x, y) = (
+            self.position.x as f32 + state.viewport.x as f32,
+            self.position.y as f32 + state.viewport.y as f32,
+        );
+        draw_rail_gl(
+            (x, y),
+            &straight_connections(self.rotation),
+            state,
+            gl,
+            is_ghost,
+        )?;
+        draw_flat_rect_gl(state, gl, (x, y, 0.3, 0.3), &[0.25, 0.5, 1., 1.])
+    }
+
+    fn desc(&self, state: &FactorishState) -> String {
+        format!(
+            "Name: {}<br>Mode: {:?}<br>Items:<br>{}{}",
+            self.name,
+            self.mode,
+            self.inventory
+                .iter()
+                .map(|item| format!("{:?}: {}<br>", item.0, item.1))
+                .fold(String::from(""), |accum, item| accum + &item),
+            state
+                .train_at(&self.position)
+                .map(|train| train.desc())
+                .unwrap_or_default()
+        )
+    }
+
+    /// Rotating a train stop toggles between loading and unloading, like loaders.
+    fn rotate(
+        &mut self,
+        _state: &mut FactorishState,
+        _others: &StructureDynIter,
+    ) -> Result<(), RotateErr> {
+        self.mode = self.mode.next();
+        Ok(())
+    }
+
+    fn set_rotation(&mut self, rotation: &Rotation) -> Result<(), ()> {
+        self.rotation = *rotation;
+        Ok(())
+    }
+
+    fn input(&mut self, o: &DropItem) -> Result<(), JsValue> {
+        if 0 < self.add_inventory(InventoryType::Storage, &o.type_, 1) {
+            Ok(())
+        } else {
+            js_err!("Train stop is full")
+        }
+    }
+
+    fn can_input(&self, item_type: &ItemType) -> bool {
+        0 < storage_capacity(&self.inventory, item_type, self.storage_slots(), &[])
+    }
+
+    fn can_output(&self, _structures: &StructureDynIter) -> Inventory {
+        self.inventory.clone()
+    }
+
+    fn output(&mut self, _state: &mut FactorishState, item_type: &ItemType) -> Result<(), ()> {
+        if 0 < -self.add_inventory(InventoryType::Storage, item_type, -1) {
+            Ok(())
+        } else {
+            Err(())
+        }
+    }
+
+    fn inventory(&self, invtype: InventoryType) -> Option<&Inventory> {
+        match invtype {
+            InventoryType::Storage => Some(&self.inventory),
+            _ => None,
+        }
+    }
+
+    fn inventory_mut(&mut self, invtype: InventoryType) -> Option<&mut Inventory> {
+        match invtype {
+            InventoryType::Storage => Some(&mut self.inventory),
+            _ => None,
+        }
+    }
+
+    fn rail_connections(&self) -> Option<[Rotation; 2]> {
+        Some(straight_connections(self.rotation))
+    }
+
+    fn train_stop(&self) -> Option<(&str, TrainStopMode)> {
+        Some((&self.name, self.mode))
+    }
+
+    fn set_train_stop_name(&mut self, name: &str) -> Result<(), JsValue> {
+        self.name = name.to_string();
+        Ok(())
+    }
+
+    crate::serialize_impl!();
+}
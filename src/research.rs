@@ -5,7 +5,7 @@ use crate::{
     items::{item_to_str, ItemType},
     FactorishState,
 };
-use once_cell::unsync::Lazy;
+use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
@@ -14,6 +14,91 @@ pub(crate) enum TechnologyTag {
     Transportation,
     Electricity,
     SteelWorks,
+    LogisticRobotics,
+    Railway,
+    FluidHandling,
+    OilProcessing,
+    MiningProductivity,
+    Modules,
+    SolarEnergy,
+    ElectricEnergyDistribution,
+    CircuitNetwork,
+    Optics,
+    InserterSpeed,
+    ResearchSpeed,
+    AssemblerSpeed,
+    ChestCapacity,
+}
+
+/// Items that labs consume to make research progress, in the order of the tiers
+pub(crate) const SCIENCE_PACKS: [ItemType; 5] = [
+    ItemType::SciencePack1,
+    ItemType::SciencePack2,
+    ItemType::MilitarySciencePack,
+    ItemType::ChemicalSciencePack,
+    ItemType::ProductionSciencePack,
+];
+
+/// A modifier applied to the whole factory for each level of a researched technology.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub(crate) enum TechnologyEffect {
+    /// Ratio added to the swing speed of inserters
+    InserterSpeed(f64),
+    /// Ratio of ores mined without depleting the deposit
+    MiningProductivity(f64),
+    /// Ratio added to the speed of labs
+    LabSpeed(f64),
+    /// Ratio added to the crafting speed of assemblers
+    CraftingSpeed(f64),
+    /// Slots added to every chest
+    ChestSlots(usize),
+}
+
+impl TechnologyEffect {
+    pub(crate) fn describe(&self) -> String {
+        match self {
+            Self::InserterSpeed(v) => format!("Inserter speed: +{:.0}%", v * 100.),
+            Self::MiningProductivity(v) => format!("Mining productivity: +{:.0}%", v * 100.),
+            Self::LabSpeed(v) => format!("Lab speed: +{:.0}%", v * 100.),
+            Self::CraftingSpeed(v) => format!("Assembler speed: +{:.0}%", v * 100.),
+            Self::ChestSlots(v) => format!("Chest capacity: +{} slots", v),
+        }
+    }
+}
+
+/// Sum of the effects of all researched technologies. It is kept in the state and updated
+/// whenever a research completes, so that structures can look it up every tick.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) struct ResearchModifiers {
+    pub inserter_speed: f64,
+    pub mining_productivity: f64,
+    pub lab_speed: f64,
+    pub crafting_speed: f64,
+    pub chest_slots: usize,
+}
+
+impl ResearchModifiers {
+    pub(crate) fn new(level: impl Fn(TechnologyTag) -> usize) -> Self {
+        let mut ret = Self::default();
+        for tech in TECHNOLOGIES.iter() {
+            for _ in 0..level(tech.tag) {
+                for effect in &tech.effects {
+                    ret.apply(effect);
+                }
+            }
+        }
+        ret
+    }
+
+    fn apply(&mut self, effect: &TechnologyEffect) {
+        match *effect {
+            TechnologyEffect::InserterSpeed(v) => self.inserter_speed += v,
+            TechnologyEffect::MiningProductivity(v) => self.mining_productivity += v,
+            TechnologyEffect::LabSpeed(v) => self.lab_speed += v,
+            TechnologyEffect::CraftingSpeed(v) => self.crafting_speed += v,
+            TechnologyEffect::ChestSlots(v) => self.chest_slots += v,
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize)]
@@ -22,6 +107,22 @@ pub(crate) struct Technology {
     pub input: Inventory,
     pub steps: usize,
     pub research_time: f64,
+    /// A repeatable technology can be researched again to reach the next level,
+    /// each level costing more steps than the last.
+    pub repeatable: bool,
+    /// Modifiers applied for each level of this technology in addition to the unlocked recipes
+    pub effects: Vec<TechnologyEffect>,
+}
+
+impl Technology {
+    /// Research steps needed to complete the given level, counted from 0.
+    pub(crate) fn steps_at(&self, level: usize) -> usize {
+        if self.repeatable {
+            self.steps * (level + 1)
+        } else {
+            self.steps
+        }
+    }
 }
 
 #[derive(Serialize)]
@@ -32,7 +133,11 @@ pub(crate) struct TechnologySerial {
     pub steps: usize,
     pub research_time: f64,
     pub unlocked: bool,
+    pub repeatable: bool,
+    /// Number of times a repeatable technology has been researched
+    pub level: usize,
     pub unlocks: Vec<String>,
+    pub effects: Vec<String>,
 }
 
 impl TechnologySerial {
@@ -43,21 +148,42 @@ impl TechnologySerial {
                 TechnologyTag::Transportation => "Transport Belt",
                 TechnologyTag::Electricity => "Electric Pole",
                 TechnologyTag::SteelWorks => "Steel Plate",
+                TechnologyTag::LogisticRobotics => "Roboport",
+                TechnologyTag::Railway => "Locomotive",
+                TechnologyTag::FluidHandling => "Storage Tank",
+                TechnologyTag::OilProcessing => "Oil Refinery",
+                TechnologyTag::MiningProductivity => "Electric Mining Drill",
+                TechnologyTag::Modules => "Speed Module",
+                TechnologyTag::SolarEnergy => "Solar Panel",
+                TechnologyTag::ElectricEnergyDistribution => "Medium Electric Pole",
+                TechnologyTag::CircuitNetwork => "Arithmetic Combinator",
+                TechnologyTag::Optics => "Lamp",
+                TechnologyTag::InserterSpeed => "Inserter",
+                TechnologyTag::ResearchSpeed => "Lab",
+                TechnologyTag::AssemblerSpeed => "Assembler",
+                TechnologyTag::ChestCapacity => "Chest",
             },
             input: tech
                 .input
                 .iter()
                 .map(|(k, v)| (item_to_str(k), *v))
                 .collect(),
-            steps: tech.steps,
+            steps: tech.steps_at(state.technology_level(tech.tag)),
             research_time: tech.research_time,
-            unlocked: state.unlocked_technologies.contains(&tech.tag),
+            unlocked: !tech.repeatable && state.unlocked_technologies.contains(&tech.tag),
+            repeatable: tech.repeatable,
+            level: state.technology_level(tech.tag),
             unlocks: Assembler::get_recipes()
                 .iter()
                 .chain(RECIPES.iter())
                 .filter(|recipe| recipe.requires_technology.contains(&tech.tag))
                 .filter_map(|recipe| Some(item_to_str(recipe.output.keys().next()?)))
                 .collect(),
+            effects: tech
+                .effects
+                .iter()
+                .map(|effect| effect.describe())
+                .collect(),
         }
     }
 }
@@ -72,27 +198,174 @@ pub(crate) struct Research {
 pub(crate) struct ResearchSerial {
     pub technology: TechnologyTag,
     pub progress: f64,
+    /// The level being researched if the technology is repeatable
+    pub level: Option<usize>,
 }
 
-pub(crate) const TECHNOLOGIES: Lazy<Vec<Technology>> = Lazy::new(|| {
+pub(crate) static TECHNOLOGIES: Lazy<Vec<Technology>> = Lazy::new(|| {
     vec![
         Technology {
             tag: TechnologyTag::Transportation,
             input: hash_map!(ItemType::SciencePack1 => 1),
             steps: 20,
             research_time: 30.,
+            repeatable: false,
+            effects: vec![],
         },
         Technology {
             tag: TechnologyTag::Electricity,
             input: hash_map!(ItemType::SciencePack1 => 1),
             steps: 30,
             research_time: 30.,
+            repeatable: false,
+            effects: vec![],
         },
         Technology {
             tag: TechnologyTag::SteelWorks,
             input: hash_map!(ItemType::SciencePack1 => 1, ItemType::SciencePack2 => 1),
             steps: 50,
             research_time: 30.,
+            repeatable: false,
+            effects: vec![],
+        },
+        Technology {
+            tag: TechnologyTag::LogisticRobotics,
+            input: hash_map!(ItemType::SciencePack1 => 1, ItemType::SciencePack2 => 1, ItemType::ChemicalSciencePack => 1),
+            steps: 75,
+            research_time: 30.,
+            repeatable: false,
+            effects: vec![],
+        },
+        Technology {
+            tag: TechnologyTag::Railway,
+            input: hash_map!(ItemType::SciencePack1 => 1, ItemType::SciencePack2 => 1, ItemType::MilitarySciencePack => 1),
+            steps: 75,
+            research_time: 30.,
+            repeatable: false,
+            effects: vec![],
+        },
+        Technology {
+            tag: TechnologyTag::FluidHandling,
+            input: hash_map!(ItemType::SciencePack1 => 1, ItemType::SciencePack2 => 1),
+            steps: 50,
+            research_time: 30.,
+            repeatable: false,
+            effects: vec![],
+        },
+        Technology {
+            tag: TechnologyTag::OilProcessing,
+            input: hash_map!(ItemType::SciencePack1 => 1, ItemType::SciencePack2 => 1),
+            steps: 100,
+            research_time: 30.,
+            repeatable: false,
+            effects: vec![],
+        },
+        Technology {
+            tag: TechnologyTag::MiningProductivity,
+            input: hash_map!(ItemType::SciencePack1 => 1, ItemType::SciencePack2 => 1, ItemType::ProductionSciencePack => 1),
+            steps: 100,
+            research_time: 30.,
+            repeatable: true,
+            effects: vec![TechnologyEffect::MiningProductivity(0.1)],
+        },
+        Technology {
+            tag: TechnologyTag::Modules,
+            input: hash_map!(ItemType::SciencePack1 => 1, ItemType::SciencePack2 => 1),
+            steps: 100,
+            research_time: 30.,
+            repeatable: false,
+            effects: vec![],
+        },
+        Technology {
+            tag: TechnologyTag::SolarEnergy,
+            input: hash_map!(ItemType::SciencePack1 => 1, ItemType::SciencePack2 => 1),
+            steps: 100,
+            research_time: 30.,
+            repeatable: false,
+            effects: vec![],
+        },
+        Technology {
+            tag: TechnologyTag::ElectricEnergyDistribution,
+            input: hash_map!(ItemType::SciencePack1 => 1, ItemType::SciencePack2 => 1),
+            steps: 100,
+            research_time: 30.,
+            repeatable: false,
+            effects: vec![],
+        },
+        Technology {
+            tag: TechnologyTag::CircuitNetwork,
+            input: hash_map!(ItemType::SciencePack1 => 1, ItemType::SciencePack2 => 1),
+            steps: 100,
+            research_time: 30.,
+            repeatable: false,
+            effects: vec![],
+        },
+        Technology {
+            tag: TechnologyTag::Optics,
+            input: hash_map!(ItemType::SciencePack1 => 1),
+            steps: 20,
+            research_time: 30.,
+            repeatable: false,
+            effects: vec![],
+        },
+        Technology {
+            tag: TechnologyTag::InserterSpeed,
+            input: hash_map!(ItemType::SciencePack1 => 1, ItemType::SciencePack2 => 1, ItemType::MilitarySciencePack => 1),
+            steps: 50,
+            research_time: 30.,
+            repeatable: true,
+            effects: vec![TechnologyEffect::InserterSpeed(0.2)],
+        },
+        Technology {
+            tag: TechnologyTag::ResearchSpeed,
+            input: hash_map!(ItemType::SciencePack1 => 1, ItemType::SciencePack2 => 1, ItemType::ChemicalSciencePack => 1),
+            steps: 50,
+            research_time: 30.,
+            repeatable: true,
+            effects: vec![TechnologyEffect::LabSpeed(0.2)],
+        },
+        Technology {
+            tag: TechnologyTag::AssemblerSpeed,
+            input: hash_map!(ItemType::SciencePack1 => 1, ItemType::SciencePack2 => 1, ItemType::ProductionSciencePack => 1),
+            steps: 75,
+            research_time: 30.,
+            repeatable: false,
+            effects: vec![TechnologyEffect::CraftingSpeed(0.25)],
+        },
+        Technology {
+            tag: TechnologyTag::ChestCapacity,
+            input: hash_map!(ItemType::SciencePack1 => 1),
+            steps: 30,
+            research_time: 30.,
+            repeatable: false,
+            effects: vec![TechnologyEffect::ChestSlots(8)],
         },
     ]
 });
+
+#[test]
+fn test_repeatable_steps() {
+    let tech = |repeatable| Technology {
+        tag: TechnologyTag::MiningProductivity,
+        input: hash_map!(ItemType::SciencePack1 => 1),
+        steps: 100,
+        research_time: 30.,
+        repeatable,
+        effects: vec![],
+    };
+    assert_eq!(tech(false).steps_at(3), 100);
+    assert_eq!(tech(true).steps_at(0), 100);
+    assert_eq!(tech(true).steps_at(2), 300);
+}
+
+#[test]
+fn test_research_modifiers() {
+    let modifiers = ResearchModifiers::new(|tag| match tag {
+        TechnologyTag::MiningProductivity => 3,
+        TechnologyTag::ChestCapacity => 1,
+        _ => 0,
+    });
+    assert!((modifiers.mining_productivity - 0.3).abs() < 1e-6);
+    assert_eq!(modifiers.chest_slots, 8);
+    assert_eq!(modifiers.inserter_speed, 0.);
+}
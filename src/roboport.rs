@@ -0,0 +1,233 @@
+use super::{
+    gl::{
+        draw_electricity_alarm_gl,
+        utils::{enable_buffer, Flatten},
+    },
+    inventory::InventoryType,
+    logistics::{draw_logistic_coverage_gl, draw_logistic_marker_gl},
+    serialize_impl,
+    structure::{default_add_inventory, Size, Structure},
+    DropItem, FactorishState, Inventory, InventoryTrait, ItemType, Position, TILE_SIZE,
+};
+use cgmath::{Matrix3, Matrix4, Vector3};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+use web_sys::{CanvasRenderingContext2d, WebGlRenderingContext as GL};
+
+/// How far the logistic coverage extends beyond the roboport in tiles
+const ROBOPORT_RANGE: i32 = 12;
+const ROBOPORT_MAX_POWER: f64 = 100.;
+
+/// A roboport defines the coverage area of a logistic network and launches robots from it.
+/// Robots are stored as items in its inventory and charged for the whole trip at launch.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct Roboport {
+    position: Position,
+    inventory: Inventory,
+    power: f64,
+}
+
+impl Roboport {
+    pub(crate) fn new(position: &Position) -> Self {
+        Roboport {
+            position: *position,
+            inventory: Inventory::new(),
+            power: 0.,
+        }
+    }
+
+    fn robots(&self) -> usize {
+        self.inventory.count_item(&ItemType::LogisticRobot)
+    }
+}
+
+impl Structure for Roboport {
+    fn name(&self) -> &str {
+        "Roboport"
+    }
+
+    fn position(&self) -> &Position {
+        &self.position
+    }
+
+    fn size(&self) -> Size {
+        Size::new(2, 2)
+    }
+
+    fn draw(
+        &self,
+        state: &FactorishState,
+        context: &CanvasRenderingContext2d,
+        depth: i32,
+        is_toolbar: bool,
+    ) -> Result<(), JsValue> {
+        if depth != 0 {
+            return Ok(());
+        };
+        let (x, y) = (
+            self.position.x as f64 * TILE_SIZE,
+            self.position.y as f64 * TILE_SIZE,
+        );
+        let size = if is_toolbar {
+            TILE_SIZE
+        } else {
+            TILE_SIZE * 2.
+        };
+        match state.image_chest.as_ref() {
+            Some(img) => {
+                context.draw_image_with_image_bitmap_and_dw_and_dh(
+                    &img.bitmap,
+                    x,
+                    y,
+                    size,
+                    size,
+                )?;
+                Ok(())
+            }
+            None => Err(JsValue::from_str("chest image not available")),
+        }
+    }
+
+    fn draw_gl(
+        &self,
+        state: &FactorishState,
+        gl: &GL,
+        depth: i32,
+        is_ghost: bool,
+    ) -> Result<(), JsValue> {
+        let (x, y) = (
+            self.position.x as f32 + state.viewport.x as f32,
+            self.position.y as f32 + state.viewport.y as f32,
+        );
+        match depth {
+            0 => {
+                let shader = state
+                    .assets
+                    .textured_shader
+                    .as_ref()
+                    .ok_or_else(|| js_str!("Shader not found"))?;
+                gl.use_program(Some(&shader.program));
+                gl.uniform1f(shader.alpha_loc.as_ref(), if is_ghost { 0.5 } else { 1. });
+                gl.active_texture(GL::TEXTURE0);
+                gl.bind_texture(GL::TEXTURE_2D, Some(&state.assets.tex_chest));
+                gl.uniform_matrix3fv_with_f32_array(
+                    shader.tex_transform_loc.as_ref(),
+                    false,
+                    Matrix3::from_nonuniform_scale(1., 1.).flatten(),
+                );
+                enable_buffer(gl, &state.assets.screen_buffer, 2, shader.vertex_position);
+                gl.uniform_matrix4fv_with_f32_array(
+                    shader.transform_loc.as_ref(),
+                    false,
+                    (state.get_world_transform()?
+                        * Matrix4::from_scale(2.)
+                        * Matrix4::from_translation(Vector3::new(x, y, 0.))
+                        * Matrix4::from_scale(2.))
+                    .flatten(),
+                );
+                gl.draw_arrays(GL::TRIANGLE_FAN, 0, 4);
+
+                draw_logistic_marker_gl((x + 1., y + 1.), &[1., 0.6, 0., 1.], state, gl)?;
+            }
+            2 => {
+                // Show the coverage while placing or with alt mode
+                if is_ghost || state.alt_mode {
+                    draw_logistic_coverage_gl(&self.bounding_box(), ROBOPORT_RANGE, state, gl)?;
+                }
+                if !is_ghost && self.power == 0. {
+                    draw_electricity_alarm_gl((x, y), state, gl)?;
+                }
+            }
+            _ => (),
+        }
+        Ok(())
+    }
+
+    fn desc(&self, _state: &FactorishState) -> String {
+        format!(
+            r#"Robots: {}<br>Power: {:.1}kJ <div style='position: relative; width: 100px; height: 10px; background-color: #001f1f; margin: 2px; border: 1px solid #3f3f3f'>
+            <div style='position: absolute; width: {}px; height: 10px; background-color: #ff00ff'></div></div>"#,
+            self.robots(),
+            self.power,
+            self.power / ROBOPORT_MAX_POWER * 100.
+        )
+    }
+
+    fn input(&mut self, o: &DropItem) -> Result<(), JsValue> {
+        if 0 < self.add_inventory(InventoryType::Storage, &o.type_, 1) {
+            Ok(())
+        } else {
+            js_err!("Roboport only accepts logistic robots")
+        }
+    }
+
+    fn can_input(&self, item_type: &ItemType) -> bool {
+        *item_type == ItemType::LogisticRobot
+    }
+
+    /// Only accepts logistic robots
+    fn add_inventory(
+        &mut self,
+        inventory_type: InventoryType,
+        item_type: &ItemType,
+        count: isize,
+    ) -> isize {
+        if 0 < count && *item_type != ItemType::LogisticRobot {
+            return 0;
+        }
+        default_add_inventory(self, inventory_type, item_type, count)
+    }
+
+    fn inventory(&self, invtype: InventoryType) -> Option<&Inventory> {
+        match invtype {
+            InventoryType::Storage => Some(&self.inventory),
+            _ => None,
+        }
+    }
+
+    fn inventory_mut(&mut self, invtype: InventoryType) -> Option<&mut Inventory> {
+        match invtype {
+            InventoryType::Storage => Some(&mut self.inventory),
+            _ => None,
+        }
+    }
+
+    fn storage_slots(&self) -> usize {
+        1
+    }
+
+    fn power_sink(&self) -> bool {
+        true
+    }
+
+    fn power_demand(&self) -> f64 {
+        (ROBOPORT_MAX_POWER - self.power).max(0.)
+    }
+
+    fn power_inlet(&mut self, energy: f64) {
+        self.power += energy;
+    }
+
+    fn logistic_range(&self) -> Option<i32> {
+        Some(ROBOPORT_RANGE)
+    }
+
+    fn launch_robot(&mut self, energy: f64) -> bool {
+        if energy <= self.power && self.inventory.remove_item(&ItemType::LogisticRobot) {
+            self.power -= energy;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn dock_robot(&mut self) -> bool {
+        0 < self.add_inventory(InventoryType::Storage, &ItemType::LogisticRobot, 1)
+    }
+
+    fn reset_for_blueprint(&mut self) {
+        self.power = 0.;
+    }
+
+    serialize_impl!();
+}
@@ -70,7 +70,10 @@ fn default_scenario(
         wrap_structure(Box::new(TransportBelt::new(12, 3, Rotation::Left))),
         wrap_structure(Box::new(OreMine::new(12, 2, Rotation::Bottom))),
         wrap_structure(Box::new(Furnace::new(&Position::new(8, 3)))),
-        wrap_structure(Box::new(Assembler::new(&Position::new(6, 3)))),
+        wrap_structure(Box::new(Assembler::new(
+            &Position::new(6, 3),
+            Rotation::Left,
+        ))),
         wrap_structure(Box::new(Boiler::new(&Position::new(13, 5)))),
         wrap_structure(Box::new(Pipe::new(&Position::new(12, 5)))),
         wrap_structure(Box::new(SteamEngine::new(&Position::new(11, 5)))),
@@ -189,7 +192,7 @@ fn electric_bench(
 
     structures.extend((10..=100).map(|x| {
         if x % 2 == 0 {
-            let p = Box::new(Assembler::new(&Position::new(x, 10)));
+            let p = Box::new(Assembler::new(&Position::new(x, 10), Rotation::Left));
             wrap_structure(p as Box<dyn Structure>)
         } else {
             let p = Box::new(ElectPole::new(&Position::new(x, 10)));
@@ -198,21 +201,24 @@ fn electric_bench(
     }));
     structures.extend((10..=100).map(|x| {
         wrap_structure(if x % 2 == 0 {
-            Box::new(Assembler::new(&Position::new(x, 100))) as Box<dyn Structure>
+            Box::new(Assembler::new(&Position::new(x, 100), Rotation::Left)) as Box<dyn Structure>
         } else {
             Box::new(ElectPole::new(&Position::new(x, 100))) as Box<dyn Structure>
         })
     }));
     structures.extend((11..=99).map(|x| {
         if x % 2 == 0 {
-            wrap_structure(Box::new(Assembler::new(&Position::new(10, x))) as Box<dyn Structure>)
+            wrap_structure(
+                Box::new(Assembler::new(&Position::new(10, x), Rotation::Left))
+                    as Box<dyn Structure>,
+            )
         } else {
             wrap_structure(Box::new(ElectPole::new(&Position::new(10, x))) as Box<dyn Structure>)
         }
     }));
     structures.extend((11..=99).map(|x| {
         wrap_structure(if x % 2 == 0 {
-            Box::new(Assembler::new(&Position::new(100, x))) as Box<dyn Structure>
+            Box::new(Assembler::new(&Position::new(100, x), Rotation::Left)) as Box<dyn Structure>
         } else {
             Box::new(ElectPole::new(&Position::new(100, x))) as Box<dyn Structure>
         })
@@ -0,0 +1,168 @@
+use super::{
+    gl::draw_flat_rect_gl,
+    power_network::PowerPriority,
+    structure::{Size, Structure, StructureDynIter, StructureId},
+    FactorishState, FrameProcResult, Position, TILE_SIZE,
+};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+use web_sys::{CanvasRenderingContext2d, WebGlRenderingContext as GL};
+
+/// Length of a full day and night cycle in seconds
+pub(crate) const DAY_LENGTH: f64 = 420.;
+/// Energy in kilojoules generated in a tick in full daylight
+const SOLAR_POWER: f64 = 0.1;
+
+/// Brightness of the sun at the given simulation time, between 0 (night) and 1 (day).
+/// A cycle starts with the day, which takes half of the cycle, followed by dusk, night and dawn.
+pub(crate) fn daylight(sim_time: f64) -> f64 {
+    let phase = (sim_time / DAY_LENGTH).fract();
+    if phase < 0.5 {
+        1.
+    } else if phase < 0.6 {
+        (0.6 - phase) / 0.1
+    } else if phase < 0.8 {
+        0.
+    } else {
+        (phase - 0.8) / 0.2
+    }
+}
+
+/// A solar panel generates electricity from the sunlight without fuel.
+/// It has no buffer; the energy not drained in a tick is lost.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct SolarPanel {
+    position: Position,
+    #[serde(skip)]
+    power: f64,
+}
+
+impl SolarPanel {
+    pub(crate) fn new(position: &Position) -> Self {
+        Self {
+            position: *position,
+            power: 0.,
+        }
+    }
+}
+
+impl Structure for SolarPanel {
+    fn name(&self) -> &str {
+        "Solar Panel"
+    }
+
+    fn position(&self) -> &Position {
+        &self.position
+    }
+
+    fn size(&self) -> Size {
+        Size::new(3, 3)
+    }
+
+    fn draw(
+        &self,
+        _state: &FactorishState,
+        context: &CanvasRenderingContext2d,
+        depth: i32,
+        is_toolbar: bool,
+    ) -> Result<(), JsValue> {
+        if depth != 0 {
+            return Ok(());
+        }
+        let (x, y) = (
+            self.position.x as f64 * TILE_SIZE,
+            self.position.y as f64 * TILE_SIZE,
+        );
+        let size = if is_toolbar {
+            TILE_SIZE
+        } else {
+            TILE_SIZE * 3.
+        };
+        context.set_fill_style(&js_str!("#7f7f8f"));
+        context.fill_rect(x, y, size, size);
+        context.set_fill_style(&js_str!("#1f2f6f"));
+        for (cx, cy) in [(0., 0.), (1., 0.), (0., 1.), (1., 1.)] {
+            context.fill_rect(
+                x + (cx * 0.5 + 0.05) * size,
+                y + (cy * 0.5 + 0.05) * size,
+                size * 0.4,
+                size * 0.4,
+            );
+        }
+        Ok(())
+    }
+
+    fn draw_gl(
+        &self,
+        state: &FactorishState,
+        gl: &GL,
+        depth: i32,
+        is_ghost: bool,
+    ) -> Result<(), JsValue> {
+        if depth != 0 {
+            return Ok(());
+        }
+        let (x, y) = (
+            self.position.x as f32 + state.viewport.x as f32,
+            self.position.y as f32 + state.viewport.y as f32,
+        );
+        let alpha = if is_ghost { 0.5 } else { 1. };
+        draw_flat_rect_gl(state, gl, (x, y, 3., 3.), &[0.5, 0.5, 0.55, alpha])?;
+        for (cx, cy) in [(0., 0.), (1., 0.), (0., 1.), (1., 1.)] {
+            draw_flat_rect_gl(
+                state,
+                gl,
+                (x + cx * 1.5 + 0.15, y + cy * 1.5 + 0.15, 1.2, 1.2),
+                &[0.12, 0.18, 0.43, alpha],
+            )?;
+        }
+        Ok(())
+    }
+
+    fn desc(&self, state: &FactorishState) -> String {
+        format!(
+            "Daylight: {:.0}%<br>Output: {:.0}kW",
+            daylight(state.sim_time) * 100.,
+            SOLAR_POWER * daylight(state.sim_time) * 60.
+        )
+    }
+
+    fn frame_proc(
+        &mut self,
+        _me: StructureId,
+        state: &mut FactorishState,
+        _structures: &mut StructureDynIter,
+    ) -> Result<FrameProcResult, ()> {
+        self.power = SOLAR_POWER * daylight(state.sim_time);
+        Ok(FrameProcResult::None)
+    }
+
+    fn power_source(&self) -> bool {
+        true
+    }
+
+    fn power_priority(&self) -> PowerPriority {
+        PowerPriority::Renewable
+    }
+
+    fn power_outlet(&mut self, demand: f64) -> Option<f64> {
+        let energy = demand.min(self.power);
+        self.power -= energy;
+        Some(energy)
+    }
+
+    fn reset_for_blueprint(&mut self) {
+        self.power = 0.;
+    }
+
+    crate::serialize_impl!();
+}
+
+#[test]
+fn test_daylight() {
+    assert_eq!(daylight(0.), 1.);
+    assert_eq!(daylight(DAY_LENGTH * 0.7), 0.);
+    assert!((daylight(DAY_LENGTH * 0.55) - 0.5).abs() < 1e-6);
+    assert!((daylight(DAY_LENGTH * 0.9) - 0.5).abs() < 1e-6);
+    assert_eq!(daylight(DAY_LENGTH * 1.2), 1.);
+}
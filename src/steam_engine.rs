@@ -2,7 +2,7 @@ use super::{
     gl::utils::{enable_buffer, Flatten},
     pipe::Pipe,
     serialize_impl,
-    structure::{Structure, StructureDynIter, StructureId},
+    structure::{default_reset_for_blueprint, Structure, StructureDynIter, StructureId},
     water_well::{FluidBox, FluidType},
     FactorishState, FrameProcResult, Position, Recipe,
 };
@@ -33,8 +33,10 @@ impl SteamEngine {
             recipe: Some(Recipe {
                 input: HashMap::new(),
                 input_fluid: Some(FluidType::Steam),
+                input_fluid_amount: Self::FLUID_PER_PROGRESS,
                 output: HashMap::new(),
                 output_fluid: None,
+                output_fluid_amount: 0.,
                 power_cost: -100.,
                 recipe_time: 100.,
                 requires_technology: HashSet::new(),
@@ -231,5 +233,10 @@ impl Structure for SteamEngine {
         Some(energy)
     }
 
+    fn reset_for_blueprint(&mut self) {
+        default_reset_for_blueprint(self);
+        self.power = 0.;
+    }
+
     serialize_impl!();
 }
@@ -0,0 +1,192 @@
+use super::{
+    circuit::{SignalId, Signals},
+    gl::{
+        draw_flat_rect_gl,
+        utils::{enable_buffer, Flatten},
+    },
+    structure::{Size, Structure, StructureDynIter, StructureId},
+    water_well::FluidBox,
+    FactorishState, FrameProcResult, Position, TILE_SIZE,
+};
+use cgmath::{Matrix3, Matrix4, Vector3};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+use web_sys::{CanvasRenderingContext2d, WebGlRenderingContext as GL};
+
+const TANK_CAPACITY: f64 = 25000.;
+
+/// A storage tank buffers a large amount of fluid. Fluid flows by the fill ratio,
+/// so the tank fills and drains pipes on any side without overflowing them.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct StorageTank {
+    position: Position,
+    fluid_box: FluidBox,
+}
+
+impl StorageTank {
+    pub(crate) fn new(position: &Position) -> Self {
+        let mut fluid_box = FluidBox::new(true, true);
+        fluid_box.max_amount = TANK_CAPACITY;
+        Self {
+            position: *position,
+            fluid_box,
+        }
+    }
+}
+
+impl Structure for StorageTank {
+    fn name(&self) -> &str {
+        "Storage Tank"
+    }
+
+    fn position(&self) -> &Position {
+        &self.position
+    }
+
+    fn size(&self) -> Size {
+        Size::new(3, 3)
+    }
+
+    fn draw(
+        &self,
+        state: &FactorishState,
+        context: &CanvasRenderingContext2d,
+        depth: i32,
+        is_toolbar: bool,
+    ) -> Result<(), JsValue> {
+        if depth != 0 {
+            return Ok(());
+        };
+        let (x, y) = (
+            self.position.x as f64 * TILE_SIZE,
+            self.position.y as f64 * TILE_SIZE,
+        );
+        let size = if is_toolbar {
+            TILE_SIZE
+        } else {
+            TILE_SIZE * 3.
+        };
+        match state.image_water_well.as_ref() {
+            Some(img) => {
+                context.draw_image_with_image_bitmap_and_dw_and_dh(
+                    &img.bitmap,
+                    x,
+                    y,
+                    size,
+                    size,
+                )?;
+                Ok(())
+            }
+            None => Err(JsValue::from_str("water well image not available")),
+        }
+    }
+
+    fn draw_gl(
+        &self,
+        state: &FactorishState,
+        gl: &GL,
+        depth: i32,
+        is_ghost: bool,
+    ) -> Result<(), JsValue> {
+        let (x, y) = (
+            self.position.x as f32 + state.viewport.x as f32,
+            self.position.y as f32 + state.viewport.y as f32,
+        );
+        match depth {
+            0 => {
+                let shader = state
+                    .assets
+                    .textured_shader
+                    .as_ref()
+                    .ok_or_else(|| js_str!("Shader not found"))?;
+                gl.use_program(Some(&shader.program));
+                gl.uniform1f(shader.alpha_loc.as_ref(), if is_ghost { 0.5 } else { 1. });
+                gl.active_texture(GL::TEXTURE0);
+                gl.bind_texture(GL::TEXTURE_2D, Some(&state.assets.tex_water_well));
+                gl.uniform_matrix3fv_with_f32_array(
+                    shader.tex_transform_loc.as_ref(),
+                    false,
+                    Matrix3::from_scale(1.).flatten(),
+                );
+                enable_buffer(gl, &state.assets.screen_buffer, 2, shader.vertex_position);
+                gl.uniform_matrix4fv_with_f32_array(
+                    shader.transform_loc.as_ref(),
+                    false,
+                    (state.get_world_transform()?
+                        * Matrix4::from_scale(2.)
+                        * Matrix4::from_translation(Vector3::new(x, y, 0.))
+                        * Matrix4::from_scale(3.))
+                    .flatten(),
+                );
+                gl.draw_arrays(GL::TRIANGLE_FAN, 0, 4);
+            }
+            2 => {
+                if is_ghost {
+                    return Ok(());
+                }
+                // Fill level gauge at the bottom
+                let ratio = (self.fluid_box.amount / self.fluid_box.max_amount) as f32;
+                draw_flat_rect_gl(
+                    state,
+                    gl,
+                    (x + 0.25, y + 2.6, 2.5, 0.2),
+                    &[0., 0.12, 0.12, 1.],
+                )?;
+                draw_flat_rect_gl(
+                    state,
+                    gl,
+                    (x + 0.25, y + 2.6, 2.5 * ratio, 0.2),
+                    &self
+                        .fluid_box
+                        .type_
+                        .map(|type_| type_.color())
+                        .unwrap_or([0.5, 0.5, 0.5, 1.]),
+                )?;
+            }
+            _ => (),
+        }
+        Ok(())
+    }
+
+    fn desc(&self, _state: &FactorishState) -> String {
+        format!(
+            "{:.0} / {:.0}<br>{}",
+            self.fluid_box.amount,
+            self.fluid_box.max_amount,
+            self.fluid_box.desc()
+        )
+    }
+
+    fn frame_proc(
+        &mut self,
+        _me: StructureId,
+        _state: &mut FactorishState,
+        structures: &mut StructureDynIter,
+    ) -> Result<FrameProcResult, ()> {
+        self.fluid_box.simulate(structures);
+        Ok(FrameProcResult::None)
+    }
+
+    fn fluid_box(&self) -> Option<Vec<&FluidBox>> {
+        Some(vec![&self.fluid_box])
+    }
+
+    fn circuit_connectable(&self) -> bool {
+        true
+    }
+
+    fn circuit_signals(&self) -> Option<Signals> {
+        Some(
+            self.fluid_box
+                .type_
+                .map(|fluid| hash_map!(SignalId::Fluid(fluid) => self.fluid_box.amount as i32))
+                .unwrap_or_default(),
+        )
+    }
+
+    fn fluid_box_mut(&mut self) -> Option<Vec<&mut FluidBox>> {
+        Some(vec![&mut self.fluid_box])
+    }
+
+    crate::serialize_impl!();
+}
@@ -1,11 +1,15 @@
 mod iter;
 
 use super::{
+    circuit::{CircuitConfig, Signals},
     drop_items::DropItem,
     dyn_iter::{DynIter, DynIterMut},
-    inventory::InventoryType,
-    inventory::STACK_SIZE,
+    inventory::{storage_capacity, InventoryType},
     items::ItemType,
+    logistics::LogisticChestKind,
+    modules::{is_module, ModuleEffect},
+    power_network::PowerPriority,
+    rail::TrainStopMode,
     underground_belt::UnderDirection,
     water_well::FluidBox,
     FactorishState, Inventory, InventoryTrait, Recipe, Vector2d, Vector2f, TILE_SIZE_F,
@@ -153,6 +157,20 @@ impl BoundingBox {
         }
     }
 
+    /// Returns the bounding box grown by `margin` tiles on every side.
+    pub fn expand(&self, margin: i32) -> Self {
+        Self {
+            x0: self.x0 - margin,
+            y0: self.y0 - margin,
+            x1: self.x1 + margin,
+            y1: self.y1 + margin,
+        }
+    }
+
+    pub fn intersects(&self, other: &BoundingBox) -> bool {
+        self.x0 < other.x1 && other.x0 < self.x1 && self.y0 < other.y1 && other.y0 < self.y1
+    }
+
     pub fn intersects_position(&self, position: Position) -> bool {
         self.x0 <= position.x
             && position.x < self.x1
@@ -160,6 +178,24 @@ impl BoundingBox {
             && position.y < self.y1
     }
 
+    /// Bounding box version of [`Position::neighbor_index`], which returns the index
+    /// if `self` touches `other` on a side.
+    pub fn neighbor_index(&self, other: &BoundingBox) -> Option<u32> {
+        let overlap_x = self.x0 < other.x1 && other.x0 < self.x1;
+        let overlap_y = self.y0 < other.y1 && other.y0 < self.y1;
+        if self.x1 == other.x0 && overlap_y {
+            Some(0)
+        } else if self.y1 == other.y0 && overlap_x {
+            Some(1)
+        } else if self.x0 == other.x1 && overlap_y {
+            Some(2)
+        } else if self.y0 == other.y1 && overlap_x {
+            Some(3)
+        } else {
+            None
+        }
+    }
+
     pub fn center(&self) -> Vector2f {
         Vector2f::new(
             (self.x0 + self.x1) as f32 / 2.,
@@ -254,8 +290,16 @@ pub(crate) enum RotateErr {
 /// Factories will have input inventory capacity of recipe ingredients enough to make this many products
 pub(crate) const RECIPE_CAPACITY_MULTIPLIER: usize = 3;
 
-/// Chest storage size, matching to Factorio
-const STORAGE_MAX_SLOTS: usize = 48;
+/// Default storage size in stacks, matching to Factorio's steel chest
+pub(crate) const STORAGE_MAX_SLOTS: usize = 48;
+
+/// Empty the fluid boxes of the structure, which is what [`Structure::reset_for_blueprint`]
+/// does by default.
+pub(crate) fn default_reset_for_blueprint(s: &mut (impl Structure + ?Sized)) {
+    for fluid_box in s.fluid_box_mut().into_iter().flatten() {
+        fluid_box.amount = 0.;
+    }
+}
 
 pub(crate) fn default_add_inventory(
     s: &mut (impl Structure + ?Sized),
@@ -295,14 +339,18 @@ pub(crate) fn default_add_inventory(
             }
             InventoryType::Storage => {
                 if let Some(inventory) = s.inventory(inventory_type) {
-                    let occupied_slots = inventory.count_slots() as isize;
-                    let mut left_count =
-                        (STORAGE_MAX_SLOTS as isize - occupied_slots) * STACK_SIZE as isize;
-                    let last_stack = inventory.count_item(item_type) % STACK_SIZE;
-                    if 0 < last_stack {
-                        left_count += (STACK_SIZE - last_stack) as isize;
-                    }
-                    count = count.min(left_count);
+                    let left_count =
+                        storage_capacity(inventory, item_type, s.storage_slots(), s.slot_filters());
+                    count = count.min(left_count as isize);
+                }
+            }
+            InventoryType::Module => {
+                if !is_module(item_type) {
+                    return 0;
+                }
+                if let Some(inventory) = s.inventory(inventory_type) {
+                    let used: usize = inventory.values().sum();
+                    count = count.min(s.module_slots().saturating_sub(used) as isize);
                 }
             }
             _ => (),
@@ -335,6 +383,11 @@ pub(crate) trait Structure {
         None
     }
 
+    /// Specialized method to get the paired underground belt.
+    fn underground_pair(&self) -> Option<StructureId> {
+        None
+    }
+
     fn size(&self) -> Size {
         Size {
             width: 1,
@@ -397,6 +450,10 @@ pub(crate) trait Structure {
     fn movable(&self) -> bool {
         false
     }
+    /// Whether the player's character can walk over it
+    fn walkable(&self) -> bool {
+        false
+    }
     fn rotate(
         &mut self,
         _state: &mut FactorishState,
@@ -449,6 +506,14 @@ pub(crate) trait Structure {
     ) -> isize {
         default_add_inventory(self, inventory_type, item_type, count)
     }
+    /// Number of modules this structure accepts in its [`InventoryType::Module`] inventory
+    fn module_slots(&self) -> usize {
+        0
+    }
+    /// Returns the supply area and the effect transmitted to the machines in it, if this is a beacon.
+    fn beacon_effect(&self) -> Option<(BoundingBox, ModuleEffect)> {
+        None
+    }
     fn burner_energy(&self) -> Option<(f64, f64)> {
         None
     }
@@ -458,6 +523,65 @@ pub(crate) trait Structure {
     fn inventory_mut(&mut self, _inventory_type: InventoryType) -> Option<&mut Inventory> {
         None
     }
+    /// Number of stacks the storage inventory can hold.
+    fn storage_slots(&self) -> usize {
+        STORAGE_MAX_SLOTS
+    }
+    /// Per-slot item filters of the storage inventory. `None` means the slot accepts any item.
+    fn slot_filters(&self) -> &[Option<ItemType>] {
+        &[]
+    }
+    /// Limit of storage slots that automated insertion (e.g. inserters) can fill.
+    /// Manual transfers by the player ignore this limit.
+    fn inventory_bar(&self) -> Option<usize> {
+        None
+    }
+    fn set_inventory_bar(&mut self, _bar: Option<usize>) -> Result<(), JsValue> {
+        js_err!("Inventory bar is not supported")
+    }
+    fn set_slot_filter(&mut self, _slot: usize, _item: Option<ItemType>) -> Result<(), JsValue> {
+        js_err!("Slot filter is not supported")
+    }
+    /// Specialized method for logistic chests to tell their role in the logistic network.
+    fn logistic_chest(&self) -> Option<LogisticChestKind> {
+        None
+    }
+    /// Items that a requester chest asks the logistic network to deliver.
+    fn logistic_requests(&self) -> Option<&Inventory> {
+        None
+    }
+    /// Set the requested amount of an item. Zero count clears the request.
+    fn set_logistic_request(&mut self, _item: ItemType, _count: usize) -> Result<(), JsValue> {
+        js_err!("Logistic request is not supported")
+    }
+    /// Specialized method for roboports. Returns how far the logistic coverage extends
+    /// beyond the bounding box in tiles.
+    fn logistic_range(&self) -> Option<i32> {
+        None
+    }
+    /// Try to launch an idle robot charged with `energy` in kilojoules for the whole trip.
+    fn launch_robot(&mut self, _energy: f64) -> bool {
+        false
+    }
+    /// Try to accept a robot returning from a trip.
+    fn dock_robot(&mut self) -> bool {
+        false
+    }
+    /// Specialized method for rails. Returns the two sides of the tile that the rail connects.
+    fn rail_connections(&self) -> Option<[Rotation; 2]> {
+        None
+    }
+    /// Specialized method for train stops to return the station name and the mode.
+    fn train_stop(&self) -> Option<(&str, TrainStopMode)> {
+        None
+    }
+    fn set_train_stop_name(&mut self, _name: &str) -> Result<(), JsValue> {
+        js_err!("Station name is not supported")
+    }
+    /// Specialized method for rail signals, which divide rails into blocks.
+    fn rail_signal(&self) -> bool {
+        false
+    }
     /// Some structures don't have an inventory, but still can have some item, e.g. inserter hands.
     /// We need to retrieve them when we destory such a structure, or we might lose items into void.
     /// It will take away the inventory by default, destroying the instance's inventory.
@@ -473,6 +597,12 @@ pub(crate) trait Structure {
         }
         ret
     }
+    /// Empty the fluid boxes and the energy buffers of a copy of the structure put in a
+    /// blueprint, so that pasting it does not duplicate them. The items are taken away by
+    /// [`Structure::destroy_inventory`].
+    fn reset_for_blueprint(&mut self) {
+        default_reset_for_blueprint(self);
+    }
     /// Returns a list of recipes. The return value is wrapped in a Cow because some
     /// structures can return dynamically configured list of recipes, while some others
     /// have static fixed list of recipes. In reality, all our structures return a fixed list though.
@@ -551,9 +681,58 @@ pub(crate) trait Structure {
     fn power_outlet(&mut self, _demand: f64) -> Option<f64> {
         None
     }
+    /// The order in which this power source is drained in the network
+    fn power_priority(&self) -> PowerPriority {
+        PowerPriority::Fuel
+    }
+    /// Energy in kilojoules this power sink requests from the network in this tick.
+    fn power_demand(&self) -> f64 {
+        0.
+    }
+    /// Receive the energy distributed by the network for the demand of this tick.
+    fn power_inlet(&mut self, _energy: f64) {}
+    /// Energy in kilojoules this structure can store from the surplus of the grid in this tick.
+    /// @returns None if it is not an energy storage.
+    fn power_charge_demand(&self) -> Option<f64> {
+        None
+    }
+    /// Store energy from the surplus of the grid. Called once every tick for energy storages.
+    fn power_charge(&mut self, _energy: f64) {}
     fn wire_reach(&self) -> u32 {
         3
     }
+    /// Whether signal wires can be connected to this structure
+    fn circuit_connectable(&self) -> bool {
+        false
+    }
+    /// Tile of the output terminal, which is separate from the input terminal only in combinators.
+    fn circuit_output_terminal(&self) -> Option<Position> {
+        None
+    }
+    /// Signals sent to the circuit networks connected to the output terminal, or the only
+    /// terminal if the structure has only one.
+    fn circuit_signals(&self) -> Option<Signals> {
+        None
+    }
+    /// Receive the sum of the signals of the networks connected to the input terminal in this tick.
+    /// `signals` is None if no network is connected.
+    fn circuit_input(&mut self, _signals: Option<&Signals>) {}
+    fn circuit_config(&self) -> Option<CircuitConfig> {
+        None
+    }
+    fn set_circuit_config(&mut self, _config: CircuitConfig) -> Result<(), JsValue> {
+        js_err!("This structure has no circuit settings")
+    }
+    /// Area in which power sinks and sources are connected to this pole without a wire.
+    /// @returns None if it is not an electric pole.
+    fn supply_area(&self) -> Option<BoundingBox> {
+        None
+    }
+    /// Color of the light emitted around this structure, rendered as a glow.
+    /// @returns None if it does not emit light now.
+    fn light_color(&self) -> Option<[f32; 3]> {
+        None
+    }
     fn serialize(&self) -> serde_json::Result<serde_json::Value>;
 }
 
@@ -21,6 +21,11 @@ pub(crate) struct TerrainParameters {
     pub noise_octaves: u32,
 }
 
+/// Additional noise threshold for oil deposits to make them sparse
+const OIL_NOISE_THRESHOLD: f64 = 0.15;
+/// Oil deposits are measured in yield percent rather than in amounts of ore
+const OIL_YIELD_FACTOR: f64 = 0.1;
+
 pub(crate) const CHUNK_SIZE: usize = 16;
 pub(crate) const CHUNK_SIZE_I: i32 = CHUNK_SIZE as i32;
 pub(crate) const CHUNK_SIZE_F: f64 = CHUNK_SIZE as f64;
@@ -103,6 +108,7 @@ pub(crate) fn gen_chunk(position: Position, terrain_params: &TerrainParameters)
     let copper_terms = gen_terms(&mut rng, bits);
     let coal_terms = gen_terms(&mut rng, bits);
     let stone_terms = gen_terms(&mut rng, bits);
+    let oil_terms = gen_terms(&mut rng, bits);
     for y in 0..CHUNK_SIZE {
         for x in 0..CHUNK_SIZE {
             let dx = x as f64 + position.x as f64 * CHUNK_SIZE as f64;
@@ -134,6 +140,16 @@ pub(crate) fn gen_chunk(position: Position, terrain_params: &TerrainParameters)
                 Some((ore, v)) if 0 < v => cell.ore = Some(OreValue(*ore, v)),
                 _ => (),
             }
+
+            // Oil deposits are rarer than ores and do not overlap with them
+            if cell.ore.is_none() {
+                let oil = (perlin_noise_pixel(fx, fy, bits, &oil_terms)
+                    - noise_threshold
+                    - OIL_NOISE_THRESHOLD)
+                    * factor
+                    * OIL_YIELD_FACTOR;
+                cell.oil = oil.max(0.) as u32;
+            }
         }
     }
     Chunk::new(ret)
@@ -0,0 +1,562 @@
+use super::{
+    gl::draw_flat_rect_gl,
+    inventory::{storage_capacity, InventoryType, STACK_SIZE},
+    rail::TrainStopMode,
+    structure::{Structure, StructureEntry},
+    FactorishState, FrameProcResult, Inventory, InventoryTrait, ItemType, Position, Rotation,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use wasm_bindgen::prelude::*;
+use web_sys::WebGlRenderingContext as GL;
+
+/// Distance in tiles that a train runs in a tick
+const TRAIN_SPEED: f64 = 0.2;
+/// Number of stacks a cargo wagon can hold
+pub(crate) const WAGON_SLOTS: usize = 40;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum TrainCarKind {
+    Locomotive,
+    CargoWagon,
+}
+
+impl TrainCarKind {
+    pub(crate) fn from_item(item: &ItemType) -> Option<Self> {
+        match item {
+            ItemType::Locomotive => Some(Self::Locomotive),
+            ItemType::CargoWagon => Some(Self::CargoWagon),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn item(&self) -> ItemType {
+        match self {
+            Self::Locomotive => ItemType::Locomotive,
+            Self::CargoWagon => ItemType::CargoWagon,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct TrainCar {
+    kind: TrainCarKind,
+    inventory: Inventory,
+}
+
+/// The condition for a train to leave the station.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub(crate) enum WaitCondition {
+    /// Every cargo wagon holds as many items as its slots can hold
+    Full,
+    /// All the cargo wagons are empty
+    Empty,
+    /// Wait for the given seconds
+    Time(f64),
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub(crate) struct ScheduleEntry {
+    pub station: String,
+    pub condition: WaitCondition,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+enum TrainState {
+    Moving,
+    /// Waiting at a station with elapsed seconds
+    Waiting(f64),
+}
+
+/// A train is a chain of cars running on rails. Each car occupies a rail tile.
+///
+/// Trains are not structures, because they move around. They refer to the rails by positions.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct Train {
+    /// Tiles occupied by the cars, from the front to the back
+    tiles: VecDeque<Position>,
+    /// The side of the front tile that the train is heading to
+    heading: Rotation,
+    cars: Vec<TrainCar>,
+    schedule: Vec<ScheduleEntry>,
+    current: usize,
+    state: TrainState,
+    /// Progress to the next tile
+    progress: f64,
+}
+
+/// A snapshot of a rail tile for the train simulation
+struct RailInfo {
+    connections: [Rotation; 2],
+    signal: bool,
+    stop: Option<(String, TrainStopMode)>,
+}
+
+type RailMap = HashMap<Position, RailInfo>;
+
+fn build_rail_map(structures: &[StructureEntry]) -> RailMap {
+    structures
+        .iter()
+        .filter_map(|s| {
+            let s = s.dynamic.as_deref()?;
+            Some((
+                *s.position(),
+                RailInfo {
+                    connections: s.rail_connections()?,
+                    signal: s.rail_signal(),
+                    stop: s.train_stop().map(|(name, mode)| (name.to_string(), mode)),
+                },
+            ))
+        })
+        .collect()
+}
+
+fn opposite(rotation: Rotation) -> Rotation {
+    rotation.next().next()
+}
+
+/// Returns the other side of a rail piece if it connects to `side`.
+fn other_side(connections: &[Rotation; 2], side: Rotation) -> Option<Rotation> {
+    if connections[0] == side {
+        Some(connections[1])
+    } else if connections[1] == side {
+        Some(connections[0])
+    } else {
+        None
+    }
+}
+
+/// Follow the rail from `position` entered from the `entry` side, calling `f` with each tile
+/// until a signal or a dead end. Returns true if `f` returned true for any tile.
+fn scan_block(
+    rails: &RailMap,
+    mut position: Position,
+    mut entry: Rotation,
+    mut f: impl FnMut(&Position) -> bool,
+) -> bool {
+    // The rail pieces do not branch, so a block is a simple path which could loop back.
+    for _ in 0..rails.len() {
+        let Some(rail) = rails.get(&position) else {
+            return false;
+        };
+        if rail.signal {
+            return false;
+        }
+        if f(&position) {
+            return true;
+        }
+        let Some(exit) = other_side(&rail.connections, entry) else {
+            return false;
+        };
+        position = position.add(exit.delta());
+        entry = opposite(exit);
+    }
+    false
+}
+
+impl Train {
+    fn new(position: Position, heading: Rotation, kind: TrainCarKind) -> Self {
+        Self {
+            tiles: VecDeque::from(vec![position]),
+            heading,
+            cars: vec![TrainCar {
+                kind,
+                inventory: Inventory::new(),
+            }],
+            schedule: vec![],
+            current: 0,
+            state: TrainState::Moving,
+            progress: 0.,
+        }
+    }
+
+    fn front(&self) -> Position {
+        self.tiles[0]
+    }
+
+    fn wagons(&self) -> impl Iterator<Item = &TrainCar> {
+        self.cars
+            .iter()
+            .filter(|car| car.kind == TrainCarKind::CargoWagon)
+    }
+
+    fn cargo(&self) -> Inventory {
+        let mut ret = Inventory::new();
+        for wagon in self.wagons() {
+            ret.merge(wagon.inventory.clone());
+        }
+        ret
+    }
+
+    fn condition_met(&self, condition: &WaitCondition, elapsed: f64) -> bool {
+        match condition {
+            WaitCondition::Full => {
+                self.wagons().next().is_some()
+                    && self.wagons().all(|wagon| {
+                        WAGON_SLOTS * STACK_SIZE <= wagon.inventory.values().sum::<usize>()
+                    })
+            }
+            WaitCondition::Empty => self.wagons().all(|wagon| wagon.inventory.is_empty()),
+            WaitCondition::Time(seconds) => *seconds <= elapsed,
+        }
+    }
+
+    /// Turn around at a dead end, like a train with locomotives on both ends.
+    fn reverse(&mut self, rails: &RailMap) {
+        let tiles = self.tiles.iter().rev().copied().collect::<VecDeque<_>>();
+        let front = tiles[0];
+        let Some(rail) = rails.get(&front) else {
+            return;
+        };
+        let heading = if let Some(next) = tiles.get(1) {
+            rail.connections
+                .iter()
+                .find(|side| front.add(side.delta()) == *next)
+                .and_then(|side| other_side(&rail.connections, *side))
+        } else {
+            other_side(&rail.connections, self.heading)
+        };
+        if let Some(heading) = heading {
+            self.heading = heading;
+            self.tiles = tiles;
+            self.cars.reverse();
+        }
+    }
+
+    /// Try to move forward by a tile. Returns false if the train is blocked.
+    fn step(&mut self, rails: &RailMap, occupied: &HashSet<Position>) -> bool {
+        let next = self.front().add(self.heading.delta());
+        let entry = opposite(self.heading);
+        let Some(exit) = rails
+            .get(&next)
+            .and_then(|rail| other_side(&rail.connections, entry))
+        else {
+            self.reverse(rails);
+            return false;
+        };
+        if occupied.contains(&next) {
+            return false;
+        }
+        if rails.get(&next).map(|rail| rail.signal).unwrap_or(false)
+            && scan_block(rails, next.add(exit.delta()), opposite(exit), |position| {
+                occupied.contains(position)
+            })
+        {
+            return false;
+        }
+        self.tiles.push_front(next);
+        self.tiles.pop_back();
+        self.heading = exit;
+        true
+    }
+
+    /// Move items between the train stop and the cargo wagons.
+    fn transfer(&mut self, stop: &mut dyn Structure, mode: TrainStopMode) -> bool {
+        match mode {
+            TrainStopMode::Load => {
+                let Some(inventory) = stop.inventory(InventoryType::Storage) else {
+                    return false;
+                };
+                let mut items = inventory.keys().copied().collect::<Vec<_>>();
+                items.sort();
+                for item in items {
+                    if let Some(wagon) = self.cars.iter_mut().find(|car| {
+                        car.kind == TrainCarKind::CargoWagon
+                            && 0 < storage_capacity(&car.inventory, &item, WAGON_SLOTS, &[])
+                    }) {
+                        if 0 < -stop.add_inventory(InventoryType::Storage, &item, -1) {
+                            wagon.inventory.add_item(&item);
+                            return true;
+                        }
+                    }
+                }
+            }
+            TrainStopMode::Unload => {
+                for wagon in self
+                    .cars
+                    .iter_mut()
+                    .filter(|car| car.kind == TrainCarKind::CargoWagon)
+                {
+                    let mut items = wagon.inventory.keys().copied().collect::<Vec<_>>();
+                    items.sort();
+                    for item in items {
+                        if 0 < stop.add_inventory(InventoryType::Storage, &item, 1) {
+                            wagon.inventory.remove_item(&item);
+                            return true;
+                        }
+                    }
+                }
+            }
+        }
+        false
+    }
+
+    fn proceed(
+        &mut self,
+        rails: &RailMap,
+        occupied: &HashSet<Position>,
+        structures: &mut [StructureEntry],
+        delta_time: f64,
+        results: &mut Vec<FrameProcResult>,
+    ) {
+        if self.schedule.is_empty()
+            || !self
+                .cars
+                .iter()
+                .any(|car| car.kind == TrainCarKind::Locomotive)
+        {
+            return;
+        }
+        self.current %= self.schedule.len();
+        match self.state {
+            TrainState::Moving => {
+                self.progress += TRAIN_SPEED;
+                if self.progress < 1. {
+                    return;
+                }
+                if !self.step(rails, occupied) {
+                    self.progress = 0.;
+                    return;
+                }
+                self.progress -= 1.;
+                if rails
+                    .get(&self.front())
+                    .and_then(|rail| rail.stop.as_ref())
+                    .map(|(name, _)| *name == self.schedule[self.current].station)
+                    .unwrap_or(false)
+                {
+                    self.state = TrainState::Waiting(0.);
+                    self.progress = 0.;
+                }
+            }
+            TrainState::Waiting(elapsed) => {
+                let front = self.front();
+                if let Some((_, mode)) = rails.get(&front).and_then(|rail| rail.stop.as_ref()) {
+                    if let Some(stop) = structures
+                        .iter_mut()
+                        .filter_map(|s| s.dynamic.as_deref_mut())
+                        .find(|s| *s.position() == front)
+                    {
+                        if self.transfer(stop, *mode) {
+                            results.push(FrameProcResult::InventoryChanged(front));
+                        }
+                    }
+                }
+                let elapsed = elapsed + delta_time;
+                if self.condition_met(&self.schedule[self.current].condition, elapsed) {
+                    self.current = (self.current + 1) % self.schedule.len();
+                    self.state = TrainState::Moving;
+                } else {
+                    self.state = TrainState::Waiting(elapsed);
+                }
+            }
+        }
+    }
+
+    pub(crate) fn desc(&self) -> String {
+        format!(
+            "Train: {} cars<br>{}<br>Cargo:<br>{}",
+            self.cars.len(),
+            match (self.state, self.schedule.get(self.current)) {
+                (_, None) => "No schedule".to_string(),
+                (TrainState::Moving, Some(entry)) => format!("Heading to {}", entry.station),
+                (TrainState::Waiting(elapsed), Some(entry)) => format!(
+                    "Waiting at {} for {:?} ({:.0}s)",
+                    entry.station, entry.condition, elapsed
+                ),
+            },
+            self.cargo().describe()
+        )
+    }
+}
+
+/// Run all the trains by a tick. It should be called while the structures are moved out of the state.
+pub(crate) fn simulate_trains(
+    trains: &mut [Train],
+    structures: &mut [StructureEntry],
+    delta_time: f64,
+) -> Vec<FrameProcResult> {
+    let mut results = vec![];
+    if trains.is_empty() {
+        return results;
+    }
+    let rails = build_rail_map(structures);
+    for i in 0..trains.len() {
+        let occupied = trains
+            .iter()
+            .enumerate()
+            .filter(|(j, _)| *j != i)
+            .flat_map(|(_, train)| train.tiles.iter().copied())
+            .collect::<HashSet<_>>();
+        trains[i].proceed(&rails, &occupied, structures, delta_time, &mut results);
+    }
+    results
+}
+
+impl FactorishState {
+    pub(crate) fn train_at(&self, position: &Position) -> Option<&Train> {
+        self.trains
+            .iter()
+            .find(|train| train.tiles.contains(position))
+    }
+
+    fn train_at_mut(&mut self, position: &Position) -> Option<&mut Train> {
+        self.trains
+            .iter_mut()
+            .find(|train| train.tiles.contains(position))
+    }
+
+    /// Put a locomotive or a cargo wagon on a rail. A car next to the back of an existing train
+    /// is coupled to it, otherwise a locomotive makes a new train heading to the tool rotation.
+    pub(super) fn place_train_car(
+        &mut self,
+        kind: TrainCarKind,
+        position: Position,
+    ) -> Result<(), JsValue> {
+        let connections = self
+            .find_structure_tile(&[position.x, position.y])
+            .and_then(|s| s.rail_connections())
+            .ok_or_else(|| js_str!("Train cars can only be placed on rails"))?;
+        if self.train_at(&position).is_some() {
+            return js_err!("There is already a train");
+        }
+        let car = TrainCar {
+            kind,
+            inventory: Inventory::new(),
+        };
+        if let Some(train) = self.trains.iter_mut().find(|train| {
+            let back = *train.tiles.back().unwrap();
+            connections
+                .iter()
+                .any(|side| position.add(side.delta()) == back)
+        }) {
+            train.tiles.push_back(position);
+            train.cars.push(car);
+            return Ok(());
+        }
+        if kind != TrainCarKind::Locomotive {
+            return js_err!("Cargo wagons need to be coupled to a train");
+        }
+        let heading = if connections.contains(&self.tool_rotation) {
+            self.tool_rotation
+        } else {
+            connections[0]
+        };
+        self.trains.push(Train::new(position, heading, kind));
+        Ok(())
+    }
+
+    /// Remove the whole train at the position and returns its cars and cargo.
+    pub(super) fn harvest_train(&mut self, position: &Position) -> Option<Inventory> {
+        let idx = self
+            .trains
+            .iter()
+            .position(|train| train.tiles.contains(position))?;
+        let train = self.trains.remove(idx);
+        let mut ret = Inventory::new();
+        for car in train.cars {
+            ret.add_item(&car.kind.item());
+            ret.merge(car.inventory);
+        }
+        Some(ret)
+    }
+
+    pub(super) fn set_train_schedule_at(
+        &mut self,
+        position: &Position,
+        schedule: Vec<ScheduleEntry>,
+    ) -> Result<(), JsValue> {
+        let train = self
+            .train_at_mut(position)
+            .ok_or_else(|| js_str!("Train is not found"))?;
+        train.schedule = schedule;
+        train.current = 0;
+        train.state = TrainState::Moving;
+        Ok(())
+    }
+
+    pub(super) fn train_schedule_at(&self, position: &Position) -> Option<&[ScheduleEntry]> {
+        self.train_at(position).map(|train| &train.schedule[..])
+    }
+
+    pub(super) fn render_trains_gl(&self, gl: &GL) -> Result<(), JsValue> {
+        const CAR_SIZE: f32 = 0.8;
+        for train in &self.trains {
+            let progress = train.progress as f32;
+            for (i, (tile, car)) in train.tiles.iter().zip(train.cars.iter()).enumerate() {
+                // Interpolate toward the tile that the car is going to
+                let (dx, dy) = if i == 0 {
+                    train.heading.delta()
+                } else {
+                    let ahead = train.tiles[i - 1];
+                    (ahead.x - tile.x, ahead.y - tile.y)
+                };
+                let (x, y) = (
+                    tile.x as f32 + dx as f32 * progress + self.viewport.x as f32,
+                    tile.y as f32 + dy as f32 * progress + self.viewport.y as f32,
+                );
+                draw_flat_rect_gl(
+                    self,
+                    gl,
+                    (
+                        x + (1. - CAR_SIZE) / 2.,
+                        y + (1. - CAR_SIZE) / 2.,
+                        CAR_SIZE,
+                        CAR_SIZE,
+                    ),
+                    &match car.kind {
+                        TrainCarKind::Locomotive => [0.6, 0.15, 0.1, 1.],
+                        TrainCarKind::CargoWagon => [0.45, 0.45, 0.5, 1.],
+                    },
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_train_step() {
+    let rail = |x: i32, signal: bool| {
+        (
+            Position::new(x, 0),
+            RailInfo {
+                connections: [Rotation::Left, Rotation::Right],
+                signal,
+                stop: None,
+            },
+        )
+    };
+    // A dead-end track with a signal in the middle
+    let rails: RailMap = vec![
+        rail(0, false),
+        rail(1, false),
+        rail(2, true),
+        rail(3, false),
+        rail(4, false),
+    ]
+    .into_iter()
+    .collect();
+    let mut train = Train::new(
+        Position::new(1, 0),
+        Rotation::Right,
+        TrainCarKind::Locomotive,
+    );
+
+    // Another train in the block beyond the signal holds the train
+    let occupied = hash_set!(Position::new(4, 0));
+    assert!(!train.step(&rails, &occupied));
+    assert_eq!(train.front(), Position::new(1, 0));
+
+    let occupied = HashSet::new();
+    assert!(train.step(&rails, &occupied));
+    assert!(train.step(&rails, &occupied));
+    assert!(train.step(&rails, &occupied));
+    assert_eq!(train.front(), Position::new(4, 0));
+
+    // Turn around at the dead end
+    assert!(!train.step(&rails, &occupied));
+    assert!(train.heading == Rotation::Left);
+    assert!(train.step(&rails, &occupied));
+    assert_eq!(train.front(), Position::new(3, 0));
+}
@@ -1,4 +1,5 @@
 use super::{
+    circuit::{CircuitCondition, CircuitConfig, Signals},
     drop_items::DropItem,
     gl::{
         utils::{enable_buffer, Flatten},
@@ -18,6 +19,11 @@ pub(crate) const BELT_SPEED: f64 = 0.25;
 pub(crate) struct TransportBelt {
     position: Position,
     rotation: Rotation,
+    #[serde(default)]
+    circuit_condition: Option<CircuitCondition>,
+    /// Whether the circuit condition is not met in the last tick
+    #[serde(skip)]
+    circuit_disabled: bool,
 }
 
 impl TransportBelt {
@@ -25,6 +31,8 @@ impl TransportBelt {
         TransportBelt {
             position: Position { x, y },
             rotation,
+            circuit_condition: None,
+            circuit_disabled: false,
         }
     }
 
@@ -160,6 +168,10 @@ impl Structure for TransportBelt {
         true
     }
 
+    fn walkable(&self) -> bool {
+        true
+    }
+
     fn rotate(
         &mut self,
         _state: &mut FactorishState,
@@ -175,8 +187,35 @@ impl Structure for TransportBelt {
     }
 
     fn item_response(&mut self, item: &DropItem) -> Result<ItemResponseResult, ()> {
+        if self.circuit_disabled {
+            return Ok((ItemResponse::Move(item.x, item.y), None));
+        }
         Self::transport_item(self.rotation, item)
     }
 
+    fn circuit_connectable(&self) -> bool {
+        true
+    }
+
+    fn circuit_input(&mut self, signals: Option<&Signals>) {
+        self.circuit_disabled = signals
+            .zip(self.circuit_condition.as_ref())
+            .is_some_and(|(signals, condition)| !condition.evaluate(signals));
+    }
+
+    fn circuit_config(&self) -> Option<CircuitConfig> {
+        Some(CircuitConfig::Condition(self.circuit_condition.clone()))
+    }
+
+    fn set_circuit_config(&mut self, config: CircuitConfig) -> Result<(), JsValue> {
+        match config {
+            CircuitConfig::Condition(condition) => {
+                self.circuit_condition = condition;
+                Ok(())
+            }
+            _ => js_err!("Wrong circuit settings for a transport belt"),
+        }
+    }
+
     crate::serialize_impl!();
 }
@@ -15,8 +15,6 @@ use std::collections::VecDeque;
 use wasm_bindgen::prelude::*;
 use web_sys::{CanvasRenderingContext2d, WebGlRenderingContext as GL};
 
-const UNDERGROUND_REACH: i32 = 4;
-
 #[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Copy, RotateEnum)]
 pub(crate) enum UnderDirection {
     ToGround,
@@ -25,11 +23,30 @@ pub(crate) enum UnderDirection {
 
 use UnderDirection::*;
 
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub(crate) enum UndergroundBeltTier {
+    #[default]
+    Normal,
+    Fast,
+}
+
+impl UndergroundBeltTier {
+    /// Maximum distance in tiles between the pair of underground belts
+    pub(crate) fn reach(&self) -> i32 {
+        match self {
+            Self::Normal => 4,
+            Self::Fast => 6,
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize)]
 pub(crate) struct UndergroundBelt {
     position: Position,
     rotation: Rotation,
     direction: UnderDirection,
+    #[serde(default)]
+    tier: UndergroundBeltTier,
     target: Option<StructureId>,
 
     /// Items in the underground belt. First value is the absolute position in the underground belt
@@ -39,10 +56,21 @@ pub(crate) struct UndergroundBelt {
 
 impl UndergroundBelt {
     pub(crate) fn new(x: i32, y: i32, rotation: Rotation, direction: UnderDirection) -> Self {
+        Self::new_tier(x, y, rotation, direction, UndergroundBeltTier::Normal)
+    }
+
+    pub(crate) fn new_tier(
+        x: i32,
+        y: i32,
+        rotation: Rotation,
+        direction: UnderDirection,
+        tier: UndergroundBeltTier,
+    ) -> Self {
         Self {
             position: Position { x, y },
             rotation,
             direction,
+            tier,
             target: None,
             items: VecDeque::new(),
         }
@@ -66,11 +94,162 @@ impl UndergroundBelt {
             Rotation::Bottom => dy,
         })
     }
+
+    /// Distance to the other structure if it can be paired with us, regardless of the reach.
+    /// Only the same tier of underground belts facing each other can be paired.
+    fn pair_distance(&self, other: &dyn Structure) -> Option<i32> {
+        if other.name() != self.name() || other.rotation() != Some(self.rotation.next().next()) {
+            return None;
+        }
+        Some(self.distance(other.position())?).filter(|d| 1 <= *d)
+    }
+
+    /// Find the nearest underground belt that can be paired with us, regardless of the reach.
+    /// The caller should check the returned distance with the reach.
+    fn find_nearest<'a>(
+        &self,
+        others: impl Iterator<Item = (StructureId, &'a dyn Structure)>,
+    ) -> Option<(StructureId, &'a dyn Structure, i32)> {
+        others
+            .filter_map(|(id, other)| Some((id, other, self.pair_distance(other)?)))
+            .min_by_key(|(_, _, d)| *d)
+    }
+
+    /// Draw an overlay from this underground belt to the pair `dist` tiles away.
+    fn draw_connection_gl(
+        &self,
+        state: &FactorishState,
+        gl: &GL,
+        (x, y): (f32, f32),
+        dist: i32,
+    ) -> Result<(), JsValue> {
+        let shader = state
+            .assets
+            .textured_shader
+            .as_ref()
+            .ok_or_else(|| js_str!("Shader not found"))?;
+        gl.use_program(Some(&shader.program));
+        gl.active_texture(GL::TEXTURE0);
+        gl.bind_texture(GL::TEXTURE_2D, Some(&state.assets.tex_connect_overlay));
+
+        let scale = (dist + 1) as f32;
+        let (scale_x, scale_y) = if self.rotation.is_horizontal() {
+            (scale, 1.)
+        } else {
+            (1., scale)
+        };
+        let x = if self.rotation == Rotation::Left {
+            x - dist as f32
+        } else {
+            x
+        };
+        let y = if self.rotation == Rotation::Top {
+            y - dist as f32
+        } else {
+            y
+        };
+
+        let mut arrow_rotation = self.rotation;
+        if self.direction == UnderDirection::ToGround {
+            arrow_rotation = arrow_rotation.next().next();
+        }
+
+        gl.uniform_matrix3fv_with_f32_array(
+            shader.tex_transform_loc.as_ref(),
+            false,
+            (Matrix3::from_angle_z(Rad(self.rotation.angle_rad() as f32))
+                * Matrix3::from_nonuniform_scale(scale_x, scale_y))
+            .flatten(),
+        );
+
+        enable_buffer(gl, &state.assets.screen_buffer, 2, shader.vertex_position);
+        gl.uniform_matrix4fv_with_f32_array(
+            shader.transform_loc.as_ref(),
+            false,
+            (state.get_world_transform()?
+                * Matrix4::from_scale(2.)
+                * Matrix4::from_translation(Vector3::new(x, y, 0.))
+                * Matrix4::from_nonuniform_scale(scale_x, scale_y, 1.))
+            .flatten(),
+        );
+
+        gl.draw_arrays(GL::TRIANGLE_FAN, 0, 4);
+
+        gl.bind_texture(GL::TEXTURE_2D, Some(&state.assets.tex_sparse_direction));
+
+        gl.uniform_matrix3fv_with_f32_array(
+            shader.tex_transform_loc.as_ref(),
+            false,
+            (Matrix3::from_nonuniform_scale(scale * 2., 1.)
+                * Matrix3::from_angle_z(Rad(-arrow_rotation.angle_rad() as f32)))
+            .flatten(),
+        );
+
+        let (x, y, scale_x, scale_y) = if self.rotation.is_horizontal() {
+            (x, y + 0.25, scale, 0.5)
+        } else {
+            (x + 0.25, y, 0.5, scale)
+        };
+
+        gl.uniform_matrix4fv_with_f32_array(
+            shader.transform_loc.as_ref(),
+            false,
+            (state.get_world_transform()?
+                * Matrix4::from_scale(2.)
+                * Matrix4::from_translation(Vector3::new(x, y, 0.))
+                * Matrix4::from_nonuniform_scale(scale_x, scale_y, 1.))
+            .flatten(),
+        );
+
+        gl.draw_arrays(GL::TRIANGLE_FAN, 0, 4);
+        Ok(())
+    }
+
+    /// Draw a red box over the reach of this underground belt to indicate that
+    /// the nearest candidate is too far to be paired.
+    fn draw_out_of_reach_gl(
+        &self,
+        state: &FactorishState,
+        gl: &GL,
+        (x, y): (f32, f32),
+    ) -> Result<(), JsValue> {
+        let shader = state
+            .assets
+            .flat_shader
+            .as_ref()
+            .ok_or_else(|| js_str!("Shader not found"))?;
+        gl.use_program(Some(&shader.program));
+        gl.uniform4fv_with_f32_array(shader.color_loc.as_ref(), &[1., 0., 0., 0.5]);
+
+        let reach = self.tier.reach();
+        let (dx, dy) = self.rotation.delta();
+        let (x0, y0) = (
+            x.min(x + (dx * reach) as f32),
+            y.min(y + (dy * reach) as f32),
+        );
+        let (w, h) = ((dx.abs() * reach + 1) as f32, (dy.abs() * reach + 1) as f32);
+
+        enable_buffer(gl, &state.assets.screen_buffer, 2, shader.vertex_position);
+        gl.uniform_matrix4fv_with_f32_array(
+            shader.transform_loc.as_ref(),
+            false,
+            (state.get_world_transform()?
+                * Matrix4::from_scale(2.)
+                * Matrix4::from_translation(Vector3::new(x0, y0, 0.))
+                * Matrix4::from_nonuniform_scale(w, h, 1.))
+            .flatten(),
+        );
+        gl.draw_arrays(GL::LINE_LOOP, 0, 4);
+        Ok(())
+    }
 }
 
 impl Structure for UndergroundBelt {
     fn name(&self) -> &str {
-        "Underground Belt"
+        match self.tier {
+            UndergroundBeltTier::Normal => "Underground Belt",
+            UndergroundBeltTier::Fast => "Fast Underground Belt",
+        }
     }
 
     fn position(&self) -> &Position {
@@ -85,6 +264,10 @@ impl Structure for UndergroundBelt {
         Some(self.direction)
     }
 
+    fn underground_pair(&self) -> Option<StructureId> {
+        self.target
+    }
+
     fn draw(
         &self,
         state: &FactorishState,
@@ -175,6 +358,17 @@ impl Structure for UndergroundBelt {
                 gl.draw_arrays(GL::TRIANGLE_FAN, 0, 4);
             }
             2 => {
+                if is_ghost {
+                    // Preview which existing underground belt the new one will pair with
+                    if let Some((_, _, dist)) = self.find_nearest(state.structure_id_iter()) {
+                        if dist <= self.tier.reach() {
+                            self.draw_connection_gl(state, gl, (x, y), dist)?;
+                        } else {
+                            self.draw_out_of_reach_gl(state, gl, (x, y))?;
+                        }
+                    }
+                    return Ok(());
+                }
                 let on_cursor = state.cursor == Some([self.position.x, self.position.y]);
                 if state.alt_mode && self.direction == UnderDirection::ToGround || on_cursor {
                     if let Some(dist) = self
@@ -182,85 +376,7 @@ impl Structure for UndergroundBelt {
                         .and_then(|id| state.get_structure(id))
                         .and_then(|s| self.distance(s.position()))
                     {
-                        let shader = state
-                            .assets
-                            .textured_shader
-                            .as_ref()
-                            .ok_or_else(|| js_str!("Shader not found"))?;
-                        gl.use_program(Some(&shader.program));
-                        gl.active_texture(GL::TEXTURE0);
-                        gl.bind_texture(GL::TEXTURE_2D, Some(&state.assets.tex_connect_overlay));
-
-                        let scale = (dist + 1) as f32;
-                        let (scale_x, scale_y) = if self.rotation.is_horizontal() {
-                            (scale, 1.)
-                        } else {
-                            (1., scale)
-                        };
-                        let x = if self.rotation == Rotation::Left {
-                            x - dist as f32
-                        } else {
-                            x
-                        };
-                        let y = if self.rotation == Rotation::Top {
-                            y - dist as f32
-                        } else {
-                            y
-                        };
-
-                        let mut arrow_rotation = self.rotation;
-                        if self.direction == UnderDirection::ToGround {
-                            arrow_rotation = arrow_rotation.next().next();
-                        }
-
-                        gl.uniform_matrix3fv_with_f32_array(
-                            shader.tex_transform_loc.as_ref(),
-                            false,
-                            (Matrix3::from_angle_z(Rad(self.rotation.angle_rad() as f32))
-                                * Matrix3::from_nonuniform_scale(scale_x, scale_y))
-                            .flatten(),
-                        );
-
-                        enable_buffer(&gl, &state.assets.screen_buffer, 2, shader.vertex_position);
-                        gl.uniform_matrix4fv_with_f32_array(
-                            shader.transform_loc.as_ref(),
-                            false,
-                            (state.get_world_transform()?
-                                * Matrix4::from_scale(2.)
-                                * Matrix4::from_translation(Vector3::new(x, y, 0.))
-                                * Matrix4::from_nonuniform_scale(scale_x, scale_y, 1.))
-                            .flatten(),
-                        );
-
-                        gl.draw_arrays(GL::TRIANGLE_FAN, 0, 4);
-
-                        gl.bind_texture(GL::TEXTURE_2D, Some(&state.assets.tex_sparse_direction));
-
-                        gl.uniform_matrix3fv_with_f32_array(
-                            shader.tex_transform_loc.as_ref(),
-                            false,
-                            (Matrix3::from_nonuniform_scale(scale * 2., 1.)
-                                * Matrix3::from_angle_z(Rad(-arrow_rotation.angle_rad() as f32)))
-                            .flatten(),
-                        );
-
-                        let (x, y, scale_x, scale_y) = if self.rotation.is_horizontal() {
-                            (x, y + 0.25, scale, 0.5)
-                        } else {
-                            (x + 0.25, y, 0.5, scale)
-                        };
-
-                        gl.uniform_matrix4fv_with_f32_array(
-                            shader.transform_loc.as_ref(),
-                            false,
-                            (state.get_world_transform()?
-                                * Matrix4::from_scale(2.)
-                                * Matrix4::from_translation(Vector3::new(x, y, 0.))
-                                * Matrix4::from_nonuniform_scale(scale_x, scale_y, 1.))
-                            .flatten(),
-                        );
-
-                        gl.draw_arrays(GL::TRIANGLE_FAN, 0, 4);
+                        self.draw_connection_gl(state, gl, (x, y), dist)?;
                     }
                 }
             }
@@ -399,8 +515,16 @@ impl Structure for UndergroundBelt {
         Inventory::new()
     }
 
-    fn desc(&self, _state: &FactorishState) -> String {
-        format!("Connection: {:?}<br>Items: {:?}", self.target, self.items)
+    fn desc(&self, state: &FactorishState) -> String {
+        format!(
+            "Connection: {}<br>Reach: {}<br>Items: {:?}",
+            self.target
+                .and_then(|id| state.get_structure(id))
+                .map(|s| format!("{:?}", s.position()))
+                .unwrap_or_else(|| "None".to_string()),
+            self.tier.reach(),
+            self.items
+        )
     }
 
     fn on_construction(
@@ -417,24 +541,19 @@ impl Structure for UndergroundBelt {
             }
             return Ok(());
         }
-        if other.name() != self.name() || other.rotation() != Some(self.rotation.next().next()) {
-            return Ok(());
-        }
-        let opos = *other.position();
-        let d = if let Some(d) = self.distance(&opos) {
+        let d = if let Some(d) = self.pair_distance(other) {
             d
         } else {
             return Ok(());
         };
 
-        if d < 1 || UNDERGROUND_REACH < d {
+        if self.tier.reach() < d {
             return Ok(());
         }
 
         // If there is already an underground belt with shorter distance, don't connect to the new one.
         if let Some(target) = self.target.and_then(|target| others.get(target)) {
-            let target_pos = target.position();
-            if let Some(target_d) = self.distance(target_pos) {
+            if let Some(target_d) = self.distance(target.position()) {
                 if target_d < d {
                     return Ok(());
                 }
@@ -452,37 +571,10 @@ impl Structure for UndergroundBelt {
         others: &StructureDynIter,
         _construct: bool,
     ) -> Result<(), JsValue> {
-        if let Some((id, _)) = others.dyn_iter_id().find(|(_, other)| {
-            if other.name() != self.name() || other.rotation() != Some(self.rotation.next().next())
-            {
-                return false;
-            }
-
-            let opos = *other.position();
-            let d = if let Some(d) = self.distance(&opos) {
-                d
-            } else {
-                return false;
-            };
-
-            if d < 1 || UNDERGROUND_REACH < d {
-                return false;
-            }
-
-            // If there is already an underground belt with shorter distance, don't connect to the new one.
-            if let Some(target) = self.target.and_then(|target| others.get(target)) {
-                let target_pos = target.position();
-                if let Some(target_d) = self.distance(target_pos) {
-                    if target_d < d {
-                        return false;
-                    }
-                }
-            }
-
-            true
-        }) {
-            self.target = Some(id);
-        }
+        self.target = self
+            .find_nearest(others.dyn_iter_id())
+            .filter(|(_, _, d)| *d <= self.tier.reach())
+            .map(|(id, _, _)| id);
         Ok(())
     }
 
@@ -496,3 +588,67 @@ impl Structure for UndergroundBelt {
 
     crate::serialize_impl!();
 }
+
+#[test]
+fn test_underground_pairing() {
+    use super::structure::StructureEntry;
+    use UndergroundBeltTier::*;
+
+    // Exits at the x coordinates, facing an entrance at the origin
+    let exits = |exits: Vec<(i32, UndergroundBeltTier)>| {
+        exits
+            .into_iter()
+            .map(|(x, tier)| StructureEntry {
+                gen: 0,
+                dynamic: Some(Box::new(UndergroundBelt::new_tier(
+                    x,
+                    0,
+                    Rotation::Left,
+                    ToSurface,
+                    tier,
+                ))),
+            })
+            .collect::<Vec<_>>()
+    };
+    // The x coordinate of the exit that an entrance built at the origin is paired with
+    let pair = |tier, exits: Vec<StructureEntry>| -> Option<i32> {
+        let mut structures = exits;
+        let mut entrance = UndergroundBelt::new_tier(0, 0, Rotation::Right, ToGround, tier);
+        let me = StructureId {
+            id: structures.len() as u32,
+            gen: 0,
+        };
+        entrance
+            .on_construction_self(me, &StructureDynIter::new_all(&mut structures), true)
+            .unwrap();
+        let target = structures[entrance.target?.id as usize]
+            .dynamic
+            .as_deref()?;
+        Some(target.position().x)
+    };
+
+    // Each tier has its own reach
+    assert_eq!(pair(Normal, exits(vec![(4, Normal)])), Some(4));
+    assert_eq!(pair(Normal, exits(vec![(5, Normal)])), None);
+    assert_eq!(pair(Fast, exits(vec![(6, Fast)])), Some(6));
+    assert_eq!(pair(Fast, exits(vec![(7, Fast)])), None);
+
+    // The nearest exit is paired
+    assert_eq!(
+        pair(Normal, exits(vec![(4, Normal), (2, Normal), (3, Normal)])),
+        Some(2)
+    );
+
+    // Different tiers are not paired, even if they are nearer
+    assert_eq!(pair(Normal, exits(vec![(2, Fast)])), None);
+    assert_eq!(pair(Fast, exits(vec![(2, Normal), (5, Fast)])), Some(5));
+
+    // Building exits one by one keeps the nearest one in reach
+    let mut structures = exits(vec![(3, Normal), (2, Normal), (4, Normal), (5, Normal)]);
+    let mut entrance = UndergroundBelt::new(0, 0, Rotation::Right, ToGround);
+    let others = StructureDynIter::new_all(&mut structures);
+    for (id, other) in others.dyn_iter_id() {
+        entrance.on_construction(id, other, &others, true).unwrap();
+    }
+    assert_eq!(entrance.target, Some(StructureId { id: 1, gen: 0 }));
+}
@@ -2,7 +2,7 @@ use super::{
     gl::utils::{enable_buffer, Flatten},
     pipe::Pipe,
     structure::{Structure, StructureDynIter, StructureId},
-    FactorishState, FrameProcResult, Position,
+    FactorishState, FrameProcResult, Position, Recipe,
 };
 use cgmath::{Matrix3, Matrix4, Vector3};
 use serde::{Deserialize, Serialize};
@@ -13,10 +13,52 @@ use std::cmp::Eq;
 
 const FLOW_PER_PRESSURE: f64 = 0.1 / 0.05 / 60.;
 
-#[derive(Eq, PartialEq, Clone, Copy, Debug, Serialize, Deserialize)]
+#[derive(Eq, PartialEq, Clone, Copy, Debug, Hash, Serialize, Deserialize)]
 pub(crate) enum FluidType {
     Water,
     Steam,
+    CrudeOil,
+    HeavyOil,
+    LightOil,
+    PetroleumGas,
+}
+
+impl FluidType {
+    pub(crate) const ALL: [FluidType; 6] = [
+        Self::Water,
+        Self::Steam,
+        Self::CrudeOil,
+        Self::HeavyOil,
+        Self::LightOil,
+        Self::PetroleumGas,
+    ];
+
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            Self::Water => "Water",
+            Self::Steam => "Steam",
+            Self::CrudeOil => "Crude Oil",
+            Self::HeavyOil => "Heavy Oil",
+            Self::LightOil => "Light Oil",
+            Self::PetroleumGas => "Petroleum Gas",
+        }
+    }
+
+    pub(crate) fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|fluid| fluid.name() == name)
+    }
+
+    /// Color of the fluid in gauges
+    pub(crate) fn color(&self) -> [f32; 4] {
+        match self {
+            Self::Water => [0., 1., 1., 1.],
+            Self::Steam => [0.75, 0.75, 0.75, 1.],
+            Self::CrudeOil => [0.2, 0.1, 0.2, 1.],
+            Self::HeavyOil => [0.75, 0.3, 0., 1.],
+            Self::LightOil => [1., 0.8, 0.2, 1.],
+            Self::PetroleumGas => [0.6, 0.4, 0.7, 1.],
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize)]
@@ -82,6 +124,52 @@ impl FluidBox {
             )
     }
 
+    /// Whether the box holds enough of the input fluid of the recipe, if any.
+    pub(crate) fn has_recipe_input(&self, recipe: &Recipe) -> bool {
+        recipe.input_fluid.is_none()
+            || recipe.input_fluid == self.type_ && recipe.input_fluid_amount <= self.amount
+    }
+
+    /// Whether the box has room for the output fluid of the recipe, if any.
+    pub(crate) fn has_recipe_output_space(&self, recipe: &Recipe) -> bool {
+        recipe.output_fluid.is_none()
+            || (self.amount == 0. || self.type_ == recipe.output_fluid)
+                && recipe.output_fluid_amount <= self.max_amount - self.amount
+    }
+
+    /// Restrict the box to `fluid`, discarding the contents of any other fluid.
+    pub(crate) fn set_recipe_fluid(&mut self, fluid: Option<FluidType>) {
+        if self.type_ != fluid {
+            self.amount = 0.;
+            self.type_ = None;
+        }
+        self.filter = fluid;
+    }
+
+    /// Simulate with only the connections on the sides enabled in `sides`, indexed like `connect_to`.
+    pub(crate) fn simulate_sides(&mut self, structures: &mut StructureDynIter, sides: [bool; 4]) {
+        let connect_to = self.connect_to;
+        for (connection, enabled) in self.connect_to.iter_mut().zip(sides.iter()) {
+            if !enabled {
+                *connection = None;
+            }
+        }
+        self.simulate(structures);
+        self.connect_to = connect_to;
+    }
+
+    /// Pressure from a box with `amount` out of `max_amount` toward `other`, which is negative
+    /// if the fluid flows to `other`.
+    fn pressure(amount: f64, max_amount: f64, other: &FluidBox) -> f64 {
+        if max_amount == other.max_amount {
+            other.amount - amount
+        } else {
+            // Compare the fill ratios so that a large fluid box does not overflow a small one
+            (other.amount / other.max_amount - amount / max_amount)
+                * other.max_amount.min(max_amount)
+        }
+    }
+
     pub(crate) fn simulate(&mut self, structures: &mut StructureDynIter) {
         let mut _biggest_flow_idx = -1;
         let mut biggest_flow_amount = 1e-3; // At least this amount of flow is required for displaying flow direction
@@ -107,7 +195,7 @@ impl FluidBox {
                     {
                         continue;
                     }
-                    let pressure = fluid_box.amount - self.amount;
+                    let pressure = Self::pressure(self.amount, self.max_amount, fluid_box);
                     if 0. < pressure {
                         continue;
                     }
@@ -262,3 +350,26 @@ impl Structure for WaterWell {
 
     crate::serialize_impl!();
 }
+
+#[test]
+fn test_pipe_flow() {
+    use super::structure::StructureEntry;
+
+    let mut pipe = Pipe::new(&Position::new(1, 0));
+    pipe.fluid_box_mut().unwrap()[0].amount = 20.;
+    let mut structures = vec![StructureEntry {
+        gen: 0,
+        dynamic: Some(Box::new(pipe)),
+    }];
+    let mut fluid_box = FluidBox::new(true, true).set_type(&FluidType::Water);
+    fluid_box.amount = 80.;
+    fluid_box.connect_to[0] = Some(StructureId { id: 0, gen: 0 });
+    fluid_box.simulate(&mut StructureDynIter::new_all(&mut structures));
+
+    // Boxes of the same size flow by the difference of the amounts
+    let flow = 60. * FLOW_PER_PRESSURE;
+    assert_eq!(fluid_box.amount, 80. - flow);
+    let pipe = structures[0].dynamic.as_deref().unwrap();
+    assert_eq!(pipe.fluid_box().unwrap()[0].amount, 20. + flow);
+    assert_eq!(pipe.fluid_box().unwrap()[0].type_, Some(FluidType::Water));
+}